@@ -1,15 +1,21 @@
 use core::fmt;
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, path::PathBuf, sync::Arc};
 
 use log::error;
-use tokio::{signal, sync::Mutex, task};
+use serde::{Deserialize, Serialize};
+use tokio::{pin, signal, task};
+use tokio_stream::StreamExt;
 
-use crate::service::{OverallStatus, Service, ServiceManager, ServiceManagerBuilder};
+use crate::service::{
+    BuildError, OverallStatus, ServiceHandle, ServiceManager, ServiceManagerBuilder,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExitReason {
     SIGINT,
     EssentialServiceFailed,
+    StartupFailed,
+    ShutdownRequested,
 }
 
 impl Display for ExitReason {
@@ -17,6 +23,8 @@ impl Display for ExitReason {
         match self {
             Self::SIGINT => write!(f, "SIGINT"),
             Self::EssentialServiceFailed => write!(f, "Essential Service Failed"),
+            Self::StartupFailed => write!(f, "Startup Failed"),
+            Self::ShutdownRequested => write!(f, "Shutdown Requested"),
         }
     }
 }
@@ -24,6 +32,7 @@ impl Display for ExitReason {
 pub struct BotBuilder {
     name: String,
     service_manager: ServiceManagerBuilder,
+    run_report_path: Option<PathBuf>,
 }
 
 impl BotBuilder {
@@ -31,27 +40,46 @@ impl BotBuilder {
         Self {
             name: name.to_string(),
             service_manager: ServiceManager::builder(),
+            run_report_path: None,
         }
     }
 
-    pub async fn with_service(mut self, service: Arc<Mutex<dyn Service>>) -> Self {
-        self.service_manager = self.service_manager.with_service(service).await; // The ServiceManagerBuilder itself will warn when adding a service multiple times
+    // Service statuses and the exit reason are persisted there, so the next run can report on them
+    pub fn with_run_report_path(mut self, path: PathBuf) -> Self {
+        self.run_report_path = Some(path);
+        self
+    }
 
+    pub fn with_config<T>(mut self, config: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.service_manager = self.service_manager.with_config(config);
         self
     }
 
-    pub async fn with_services(mut self, services: Vec<Arc<Mutex<dyn Service>>>) -> Self {
+    pub async fn with_service<S>(mut self, service: S) -> Result<Self, BuildError>
+    where
+        S: Into<ServiceHandle>,
+    {
+        self.service_manager = self.service_manager.with_service(service).await?; // The ServiceManagerBuilder itself will warn when adding a service multiple times
+
+        Ok(self)
+    }
+
+    pub async fn with_services(mut self, services: Vec<ServiceHandle>) -> Result<Self, BuildError> {
         for service in services {
-            self.service_manager = self.service_manager.with_service(service).await;
+            self.service_manager = self.service_manager.with_service(service).await?;
         }
 
-        self
+        Ok(self)
     }
 
     pub async fn build(self) -> Bot {
         Bot {
             name: self.name,
             service_manager: self.service_manager.build().await,
+            run_report_path: self.run_report_path,
         }
     }
 }
@@ -59,6 +87,7 @@ impl BotBuilder {
 pub struct Bot {
     pub name: String,
     pub service_manager: Arc<ServiceManager>,
+    pub run_report_path: Option<PathBuf>,
 }
 
 impl Bot {
@@ -72,13 +101,14 @@ impl Bot {
     }
 
     pub async fn stop(&mut self) {
+        self.service_manager.drain().await;
         self.service_manager.stop_services().await;
         //TODO: Potential for further deinitialization here, like modules
     }
 
     pub async fn join(&self) -> ExitReason {
         let name_clone = self.name.clone();
-        let signal_task = tokio::spawn(async move {
+        let mut signal_task = tokio::spawn(async move {
             let name = name_clone;
 
             let result = signal::ctrl_c().await;
@@ -98,25 +128,39 @@ impl Bot {
         let subscriber_name = format!("Bot join on task {}", task_id);
 
         let service_manager_clone = self.service_manager.clone();
-        let (_, mut receiver) = self
+        let (status_subscription, status_changes) = self
             .service_manager
             .on_status_change
             .event
-            .subscribe_channel(subscriber_name, 2, true, true)
+            .subscribe_stream(subscriber_name, 2, true, true)
             .await;
+        // Ends without a value once the subscription is removed or the event is closed
         let status_task = tokio::spawn(async move {
-            let service_manager = service_manager_clone;
-            while (receiver.recv().await).is_some() {
-                let overall_status = service_manager.overall_status().await;
-                if overall_status == OverallStatus::Unhealthy {
-                    return;
-                }
-            }
+            let unhealthy = status_changes
+                .then(move |_| {
+                    let service_manager = Arc::clone(&service_manager_clone);
+                    async move { service_manager.overall_status().await }
+                })
+                .filter(|overall_status| *overall_status == OverallStatus::Unhealthy);
+            pin!(unhealthy);
+
+            unhealthy.next().await.is_some()
         });
 
-        tokio::select! {
-            _ = signal_task => ExitReason::SIGINT,
-            _ = status_task => ExitReason::EssentialServiceFailed,
-        }
+        let exit_reason = tokio::select! {
+            _ = &mut signal_task => ExitReason::SIGINT,
+            Ok(true) = status_task => ExitReason::EssentialServiceFailed,
+            _ = self.service_manager.shutdown_requested() => ExitReason::ShutdownRequested,
+        };
+
+        // Waiting for SIGINT can't be ended any other way
+        signal_task.abort();
+        self.service_manager
+            .on_status_change
+            .event
+            .unsubscribe(&status_subscription)
+            .await;
+
+        exit_reason
     }
 }
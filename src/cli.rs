@@ -0,0 +1,215 @@
+use std::{convert::Infallible, ffi::OsString, path::PathBuf};
+
+use clap::{error::ErrorKind, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::LevelFilter;
+
+use crate::{
+    config::{CliConfig, Secret},
+    log::SystemLog,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum CliCommand {
+    // Asks the running instance for its status through the admin socket
+    #[command(about = "Print the status of the running instance (needs adminSocket)")]
+    Status,
+    #[command(about = "Validate the config, print it with secrets redacted and exit")]
+    CheckConfig,
+    #[command(about = "Print a new key for encrypting config values")]
+    GenerateConfigKey,
+    // Reads a secret from stdin and prints it encrypted with the config key, ready to be pasted into the config file.
+    // The help names the variable of the key, which depends on the environment prefix.
+    EncryptSecret,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        help = "Use this config file instead of the default one"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "LEVEL",
+        value_parser = parse_log_level,
+        help = "Log level (off, error, warn, info, debug, trace)"
+    )]
+    pub log_level: Option<LevelFilter>,
+
+    #[arg(
+        long,
+        help = "Do not color log output. Output that is not a terminal or with NO_COLOR set is never colored."
+    )]
+    pub no_color: bool,
+
+    #[arg(
+        long,
+        value_name = "LOG",
+        help = "Log to journald or syslog instead of stdout or the log file (Unix only, needs the journald or syslog feature)"
+    )]
+    pub system_log: Option<SystemLog>,
+
+    #[arg(
+        short,
+        long,
+        help = "Run in the background, write a PID file and log to a file (Unix only)"
+    )]
+    pub daemon: bool,
+
+    #[arg(long, help = "Same as check-config")]
+    validate_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+#[command(
+    next_help_heading = "Config overrides (take precedence over the config file and environment, never saved)"
+)]
+struct Overrides {
+    #[arg(long, value_name = "TOKEN", value_parser = parse_secret, help = "Token of the bot")]
+    discord_token: Option<Secret<String>>,
+
+    #[arg(
+        long,
+        value_name = "INTENT,...",
+        value_delimiter = ',',
+        help = "Gateway intents like GUILD_MESSAGES"
+    )]
+    discord_intents: Option<Vec<String>>,
+
+    #[arg(long, value_name = "COUNT", help = "Number of shards")]
+    discord_shards: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "CHANNEL ID",
+        help = "Channel that warnings and errors are forwarded to"
+    )]
+    discord_log_channel: Option<u64>,
+
+    #[arg(long, value_name = "PATH", help = "Unix socket for admin commands")]
+    admin_socket: Option<String>,
+
+    #[arg(
+        long,
+        overrides_with = "no_console",
+        help = "Start the interactive console"
+    )]
+    console: bool,
+
+    #[arg(
+        long,
+        overrides_with = "console",
+        help = "Do not start the interactive console"
+    )]
+    no_console: bool,
+}
+
+impl Cli {
+    // Prints the help or the error and exits if the arguments can't be parsed
+    pub fn from_env(env_prefix: &str) -> Self {
+        match Self::try_parse_with_env_prefix(std::env::args_os(), env_prefix) {
+            Ok(cli) => cli,
+            Err(err) => err.exit(),
+        }
+    }
+
+    // The environment variables in the help are named after the prefix of the config handler, e.g. LUM_CONFIG_PATH
+    pub fn try_parse_with_env_prefix<I, T>(
+        arguments: I,
+        env_prefix: &str,
+    ) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Self::command()
+            .mut_subcommand("encrypt-secret", |subcommand| {
+                subcommand.about(format!(
+                    "Encrypt a value read from stdin with the key from {}_CONFIG_KEY",
+                    env_prefix
+                ))
+            })
+            .after_help(environment_help(env_prefix));
+
+        let matches = command.try_get_matches_from_mut(arguments)?;
+        let mut cli = Self::from_arg_matches(&matches).map_err(|err| err.format(&mut command))?;
+
+        if cli.validate_config {
+            match cli.command {
+                None | Some(CliCommand::CheckConfig) => cli.command = Some(CliCommand::CheckConfig),
+                Some(_) => {
+                    return Err(command.error(
+                        ErrorKind::ArgumentConflict,
+                        "--validate-config can not be combined with another command",
+                    ))
+                }
+            }
+        }
+
+        Ok(cli)
+    }
+
+    pub fn overrides(&self) -> CliConfig {
+        let overrides = &self.overrides;
+
+        // Intents may be separated by ", ", and a trailing comma should not add an empty intent
+        let discord_intents = overrides.discord_intents.as_ref().map(|intents| {
+            intents
+                .iter()
+                .map(|intent| intent.trim().to_string())
+                .filter(|intent| !intent.is_empty())
+                .collect()
+        });
+
+        let console = match (overrides.console, overrides.no_console) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+
+        CliConfig {
+            discord_token: overrides.discord_token.clone(),
+            discord_intents,
+            discord_shards: overrides.discord_shards,
+            discord_log_channel: overrides.discord_log_channel,
+            console,
+            admin_socket: overrides.admin_socket.clone(),
+        }
+    }
+}
+
+fn environment_help(env_prefix: &str) -> String {
+    format!(
+        "Environment:
+  {prefix}_CONFIG_PATH
+          Directory for the config and all other files, instead of the OS config directory
+  {prefix}_CONFIG_KEY, {prefix}_CONFIG_KEY_FILE
+          Key for encrypted config values, or a file to read it from
+  {prefix}_CONFIG_SOURCE
+          Comma-separated URLs of remote configs, the first reachable one is applied on top of the config
+          file (needs the remote-config feature)",
+        prefix = env_prefix
+    )
+}
+
+fn parse_log_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse()
+        .map_err(|_| String::from("expected off, error, warn, info, debug or trace"))
+}
+
+fn parse_secret(secret: &str) -> Result<Secret<String>, Infallible> {
+    Ok(Secret::new(secret.to_string()))
+}
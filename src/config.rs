@@ -1,7 +1,16 @@
+pub mod cli_config;
 pub mod config_handler;
 pub mod environment_config;
 pub mod file_config;
+pub mod migration;
+#[cfg(feature = "remote-config")]
+pub mod remote;
+pub mod schema;
+pub mod secrets;
+pub mod source;
+pub mod validation;
 
+pub use cli_config::CliConfig;
 pub use config_handler::{
     ConfigHandler, ConfigInitError, ConfigParseError, ConfigPathError, ConfigSaveError,
     EnvironmentConfigParseError, FileConfigParseError, Merge,
@@ -9,3 +18,7 @@ pub use config_handler::{
 
 pub use environment_config::EnvironmentConfig;
 pub use file_config::FileConfig;
+pub use migration::{Migration, MigrationError};
+pub use secrets::{Redact, Secret, SecretError, SecretKey};
+pub use source::{ConfigSource, ConfigSourceError};
+pub use validation::{ConfigProblem, Severity, Validate};
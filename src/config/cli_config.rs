@@ -0,0 +1,18 @@
+use super::secrets::Secret;
+
+// Overrides given on the command line, e.g. for ephemeral testing. They are never written to the config file.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CliConfig {
+    pub discord_token: Option<Secret<String>>,
+    pub discord_intents: Option<Vec<String>>,
+    pub discord_shards: Option<u32>,
+    pub discord_log_channel: Option<u64>,
+    pub console: Option<bool>,
+    pub admin_socket: Option<String>,
+}
+
+impl CliConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
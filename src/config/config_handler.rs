@@ -1,15 +1,41 @@
-use std::{fs, io, marker::PhantomData, path::PathBuf};
-
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use ::log::{info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
 
+use super::{
+    migration::{self, Migration, MigrationError},
+    schema::{self, FieldDescriptions},
+    secrets::{self, SecretError, SecretKey},
+    validation::{ConfigProblem, Severity, Validate},
+};
+
 pub trait Merge<T> {
     fn merge(&self, other: &T) -> Self;
 }
 
+// Lets ConfigHandler be used without a command-line layer
+impl<T> Merge<()> for T
+where
+    T: Clone,
+{
+    fn merge(&self, _other: &()) -> Self {
+        self.clone()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ConfigPathError {
-    #[error("Unable to get OS-specific config directory")]
+    #[error(
+        "Unable to get OS-specific config directory, the config path has to be set explicitly"
+    )]
     UnknownBasePath,
 }
 
@@ -53,12 +79,30 @@ pub enum FileConfigParseError {
 
     #[error("Unable to serialize or deserialize config: {0}")]
     Serde(#[from] serde_json::Error),
+
+    #[error("Unable to decrypt config: {0}")]
+    Secret(#[from] SecretError),
+
+    #[error("Unable to migrate config: {0}")]
+    Migration(#[from] MigrationError),
+
+    #[error("Unable to back up config to {0} before migrating it: {1}")]
+    Backup(PathBuf, io::Error),
+
+    #[error("Unable to load config overlay {0}: {1}")]
+    Overlay(PathBuf, Box<FileConfigParseError>),
 }
 
 #[derive(Debug, Error)]
 pub enum EnvironmentConfigParseError {
     #[error("Unable to parse environment variables: {0}")]
     Envy(#[from] serde_env::Error),
+
+    #[error("Both {0} and {0}_FILE are set, only one of them may be used")]
+    AmbiguousSecret(String),
+
+    #[error("Unable to read {0} from file {1}: {2}")]
+    SecretFile(String, PathBuf, io::Error),
 }
 
 #[derive(Debug, Error)]
@@ -68,33 +112,136 @@ pub enum ConfigParseError {
 
     #[error("Unable to parse config from environment: {0}")]
     Env(#[from] EnvironmentConfigParseError),
+
+    #[error("Config is invalid:\n{}", format_problems(.0))]
+    Invalid(Vec<ConfigProblem>),
 }
 
+fn format_problems(problems: &[ConfigProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!(" - {}", problem))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Configs are layered: file < environment < command line. The command line layer is optional.
 #[derive(Debug)]
-pub struct ConfigHandler<FILE, ENV>
+pub struct ConfigHandler<FILE, ENV, CLI = ()>
 where
-    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV>,
+    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV> + Merge<CLI> + Validate + Default,
     ENV: Serialize + for<'de> Deserialize<'de>,
 {
     pub app_name: String,
+    env_prefix: Option<String>,
+    config_dir_path: Option<PathBuf>,
+    config_file_path: Option<PathBuf>,
+    cli_config: Option<CLI>,
+    migrations: Vec<Migration>,
+    field_descriptions: Option<FieldDescriptions>,
+    remote_config: Option<Value>,
     _phantom_file: PhantomData<FILE>,
     _phantom_env: PhantomData<ENV>,
 }
 
-impl<FILE, ENV> ConfigHandler<FILE, ENV>
+// Derived Clone would require FILE and ENV to be Clone, although only PhantomData holds them
+impl<FILE, ENV, CLI> Clone for ConfigHandler<FILE, ENV, CLI>
 where
-    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV>,
+    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV> + Merge<CLI> + Validate + Default,
+    ENV: Serialize + for<'de> Deserialize<'de>,
+    CLI: Clone,
+{
+    fn clone(&self) -> Self {
+        ConfigHandler {
+            app_name: self.app_name.clone(),
+            env_prefix: self.env_prefix.clone(),
+            config_dir_path: self.config_dir_path.clone(),
+            config_file_path: self.config_file_path.clone(),
+            cli_config: self.cli_config.clone(),
+            migrations: self.migrations.clone(),
+            field_descriptions: self.field_descriptions,
+            remote_config: self.remote_config.clone(),
+            _phantom_file: PhantomData,
+            _phantom_env: PhantomData,
+        }
+    }
+}
+
+impl<FILE, ENV, CLI> ConfigHandler<FILE, ENV, CLI>
+where
+    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV> + Merge<CLI> + Validate + Default,
     ENV: Serialize + for<'de> Deserialize<'de>,
 {
     pub fn new(app_name: &str) -> Self {
         ConfigHandler {
             app_name: app_name.to_string(),
+            env_prefix: None,
+            config_dir_path: None,
+            config_file_path: None,
+            cli_config: None,
+            migrations: Vec::new(),
+            field_descriptions: None,
+            remote_config: None,
             _phantom_file: PhantomData,
             _phantom_env: PhantomData,
         }
     }
 
+    // All files are kept in the given directory instead of the OS-specific config directory
+    pub fn with_path(app_name: &str, config_dir_path: PathBuf) -> Self {
+        let mut config_handler = Self::new(app_name);
+        config_handler.config_dir_path = Some(config_dir_path);
+        config_handler
+    }
+
+    // Other files like the run report stay in the config directory
+    pub fn with_config_file_path(mut self, path: PathBuf) -> Self {
+        self.config_file_path = Some(path);
+        self
+    }
+
+    // Without a prefix, the uppercased app name is used, e.g. LUM for LUM_DISCORD_TOKEN
+    pub fn with_env_prefix(mut self, env_prefix: &str) -> Self {
+        self.env_prefix = Some(env_prefix.trim_end_matches('_').to_string());
+        self
+    }
+
+    // Command line values override both the file and the environment
+    pub fn with_cli_config(mut self, cli_config: CLI) -> Self {
+        self.cli_config = Some(cli_config);
+        self
+    }
+
+    // Old config files are upgraded with these before they are parsed
+    pub fn with_migrations(mut self, migrations: &[Migration]) -> Self {
+        self.migrations = migrations.to_vec();
+        self
+    }
+
+    // A JSON schema with the descriptions is written next to the config file and referenced by it
+    pub fn with_field_descriptions(mut self, field_descriptions: FieldDescriptions) -> Self {
+        self.field_descriptions = Some(field_descriptions);
+        self
+    }
+
+    // A config fetched from a ConfigSource, applied on top of the config file and its overlays. It is never saved.
+    pub fn with_remote_config(mut self, remote_config: Value) -> Self {
+        self.remote_config = Some(remote_config);
+        self
+    }
+
+    // An explicit path takes precedence over <PREFIX>_CONFIG_PATH, which takes precedence over the OS-specific directory
     pub fn get_config_dir_path(&self) -> Result<PathBuf, ConfigPathError> {
+        if let Some(path) = &self.config_dir_path {
+            return Ok(path.clone());
+        }
+
+        if let Some(path) = env::var_os(self.config_path_variable()) {
+            if !path.is_empty() {
+                return Ok(PathBuf::from(path));
+            }
+        }
+
         let mut path = match dirs::config_dir() {
             Some(path) => path,
             None => return Err(ConfigPathError::UnknownBasePath),
@@ -112,54 +259,401 @@ where
     }
 
     pub fn get_config_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        if let Some(path) = &self.config_file_path {
+            return Ok(path.clone());
+        }
+
         let mut path = self.get_config_dir_path()?;
         path.push("config.json");
 
         Ok(path)
     }
 
+    fn create_config_file_dir(config_file_path: &Path) -> Result<(), ConfigInitError> {
+        match config_file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // config.json is documented by config.schema.json
+    pub fn get_config_schema_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let config_file_path = self.get_config_file_path()?;
+        let mut file_name = config_file_path
+            .file_stem()
+            .map(|file_stem| file_stem.to_os_string())
+            .unwrap_or_default();
+        file_name.push(".schema.json");
+
+        Ok(config_file_path.with_file_name(file_name))
+    }
+
+    // Overlays in conf.d next to the config file are applied on top of it, in lexicographic order
+    pub fn get_config_overlay_dir_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let config_file_path = self.get_config_file_path()?;
+        Ok(config_file_path.with_file_name("conf.d"))
+    }
+
+    pub fn get_config_overlay_file_paths(&self) -> Result<Vec<PathBuf>, FileConfigParseError> {
+        let dir_path = self.get_config_overlay_dir_path()?;
+        let entries = match fs::read_dir(&dir_path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    pub fn get_run_report_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let mut path = self.get_config_dir_path()?;
+        path.push("last_run.json");
+
+        Ok(path)
+    }
+
+    pub fn get_pid_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let mut path = self.get_config_dir_path()?;
+        path.push(format!("{}.pid", self.app_name));
+
+        Ok(path)
+    }
+
+    pub fn get_log_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let mut path = self.get_config_dir_path()?;
+        path.push(format!("{}.log", self.app_name));
+
+        Ok(path)
+    }
+
+    pub fn get_console_history_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let mut path = self.get_config_dir_path()?;
+        path.push("console_history");
+
+        Ok(path)
+    }
+
+    pub fn get_permission_overrides_file_path(&self) -> Result<PathBuf, ConfigPathError> {
+        let mut path = self.get_config_dir_path()?;
+        path.push("permission_overrides.json");
+
+        Ok(path)
+    }
+
     pub fn save_config(&self, config: &FILE) -> Result<(), ConfigSaveError> {
         let path = self.get_config_file_path()?;
         if !path.exists() {
-            self.create_config_dir_path()?;
+            Self::create_config_file_dir(&path)?;
         }
 
-        let config_json = serde_json::to_string_pretty(config)?;
-        fs::write(path, config_json)?;
+        let mut config_json = serde_json::to_value(config)?;
+        migration::set_version(
+            &mut config_json,
+            migration::current_version(&self.migrations),
+        );
+        fs::write(path, serde_json::to_string_pretty(&config_json)?)?;
 
         Ok(())
     }
 
+    // The key for encrypted values is read from <PREFIX>_CONFIG_KEY or <PREFIX>_CONFIG_KEY_FILE
+    pub fn get_secret_key(&self) -> Result<Option<SecretKey>, SecretError> {
+        SecretKey::from_env(&self.config_key_variable())
+    }
+
+    pub fn config_path_variable(&self) -> String {
+        format!("{}_CONFIG_PATH", self.env_prefix())
+    }
+
+    pub fn config_key_variable(&self) -> String {
+        format!("{}_CONFIG_KEY", self.env_prefix())
+    }
+
     pub fn load_config_from_file(&self) -> Result<FILE, FileConfigParseError> {
         let path = self.get_config_file_path()?;
         if !path.exists() {
-            self.create_config_dir_path()?;
-            fs::write(&path, "{}")?;
+            Self::create_config_file_dir(&path)?;
+
+            // New files contain every option with its default and start at the current version, so no migrations run on them
+            let mut default = serde_json::to_value(FILE::default())?;
+            migration::set_version(&mut default, migration::current_version(&self.migrations));
+            fs::write(&path, serde_json::to_string_pretty(&default)?)?;
+        }
+
+        let schema_reference = self.write_config_schema();
+
+        let config_json = fs::read_to_string(&path)?;
+        let mut original: Value = serde_json::from_str(&config_json)?;
+
+        let migrated_from = migration::migrate(&mut original, &self.migrations)?;
+        if let Some(version) = migrated_from {
+            let backup_path = Self::backup_file_path(&path, version);
+            fs::write(&backup_path, &config_json)
+                .map_err(|err| FileConfigParseError::Backup(backup_path.clone(), err))?;
+
+            info!(
+                "Migrated config from version {} to {}, the old config was saved to {}",
+                version,
+                migration::current_version(&self.migrations),
+                backup_path.display()
+            );
+        }
+
+        let base_version =
+            migrated_from.unwrap_or_else(|| migration::current_version(&self.migrations));
+        let mut merged = original.clone();
+        for overlay_path in self.get_config_overlay_file_paths()? {
+            let overlay = self
+                .load_config_overlay(&overlay_path, base_version)
+                .map_err(|err| FileConfigParseError::Overlay(overlay_path, Box::new(err)))?;
+            merge_values(&mut merged, overlay);
+        }
+        if let Some(remote_config) = &self.remote_config {
+            let remote_config = self.migrate_overlay(remote_config.clone(), base_version)?;
+            merge_values(&mut merged, remote_config);
         }
 
-        let config_json = fs::read_to_string(path)?;
-        let config = serde_json::from_str(&config_json)?;
-        self.save_config(&config)?; // In case the config file was missing some fields which serde used the defaults for
+        let mut decrypted = merged.clone();
+        if let Value::Object(fields) = &mut decrypted {
+            fields.remove(migration::VERSION_FIELD);
+        }
+        if secrets::contains_encrypted(&decrypted) {
+            let key = match self.get_secret_key()? {
+                Some(key) => key,
+                None => return Err(SecretError::MissingKey(self.config_key_variable()).into()),
+            };
+            secrets::decrypt_all(&mut decrypted, &key)?;
+        }
+
+        let config: FILE = serde_json::from_value(decrypted)?;
+
+        // Fields that no file sets are added to the config file with their defaults, so it shows every option.
+        // Existing values are never rewritten, which keeps encrypted values encrypted and overlay values out of it.
+        let mut completed = original.clone();
+        if let (
+            Value::Object(completed_fields),
+            Value::Object(fields),
+            Value::Object(merged_fields),
+        ) = (&mut completed, serde_json::to_value(&config)?, &merged)
+        {
+            for (name, value) in fields {
+                if !merged_fields.contains_key(&name) {
+                    completed_fields.insert(name, value);
+                }
+            }
+        }
+        if let (Value::Object(completed_fields), Some(schema_reference)) =
+            (&mut completed, schema_reference)
+        {
+            completed_fields
+                .entry(schema::SCHEMA_FIELD)
+                .or_insert(Value::String(schema_reference));
+        }
+        migration::set_version(&mut completed, migration::current_version(&self.migrations));
+        if migrated_from.is_some() || completed != original {
+            fs::write(&path, serde_json::to_string_pretty(&completed)?)?;
+        }
 
         Ok(config)
     }
 
+    fn load_config_overlay(
+        &self,
+        path: &Path,
+        base_version: u32,
+    ) -> Result<Value, FileConfigParseError> {
+        let overlay = serde_json::from_str(&fs::read_to_string(path)?)?;
+        self.migrate_overlay(overlay, base_version)
+    }
+
+    // Overlays are usually managed by deployment tooling, so they are migrated in memory only.
+    // Overlays without a version are assumed to be as old as the config file.
+    fn migrate_overlay(
+        &self,
+        mut overlay: Value,
+        base_version: u32,
+    ) -> Result<Value, FileConfigParseError> {
+        if let Value::Object(fields) = &overlay {
+            if !fields.contains_key(migration::VERSION_FIELD) {
+                migration::set_version(&mut overlay, base_version);
+            }
+        }
+
+        migration::migrate(&mut overlay, &self.migrations)?;
+        if let Value::Object(fields) = &mut overlay {
+            fields.remove(migration::VERSION_FIELD);
+        }
+
+        Ok(overlay)
+    }
+
+    // Returns how the config file refers to the schema. A failure only costs the documentation, so it is not fatal.
+    fn write_config_schema(&self) -> Option<String> {
+        let field_descriptions = self.field_descriptions?;
+        let schema_path = self.get_config_schema_file_path().ok()?;
+
+        let defaults = serde_json::to_value(FILE::default()).ok()?;
+        let schema = schema::generate(&self.app_name, &defaults, field_descriptions);
+        let content = serde_json::to_string_pretty(&schema).ok()?;
+
+        if fs::read_to_string(&schema_path).ok().as_ref() != Some(&content) {
+            if let Err(err) = fs::write(&schema_path, &content) {
+                warn!(
+                    "Unable to write config schema to {}: {}",
+                    schema_path.display(),
+                    err
+                );
+                return None;
+            }
+        }
+
+        // Relative to the config file, so the directory can be moved
+        let schema_file_name = schema_path.file_name()?.to_string_lossy();
+        Some(format!("./{}", schema_file_name))
+    }
+
+    // config.json of version 2 is backed up as config.json.v2.bak
+    fn backup_file_path(config_file_path: &Path, version: u32) -> PathBuf {
+        let mut file_name = config_file_path
+            .file_name()
+            .map(|file_name| file_name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(format!(".v{}.bak", version));
+
+        config_file_path.with_file_name(file_name)
+    }
+
+    pub fn env_prefix(&self) -> String {
+        match &self.env_prefix {
+            Some(env_prefix) => env_prefix.clone(),
+            None => self.app_name.to_uppercase(),
+        }
+    }
+
     pub fn load_config_from_env(&self) -> Result<ENV, EnvironmentConfigParseError> {
-        let prefix = self.app_name.to_uppercase();
-        let config = serde_env::from_env_with_prefix(&prefix)?;
+        let prefix = self.env_prefix();
+        // Variables that are not valid unicode can not belong to the config, so they are skipped instead of panicking
+        let variables = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        let variables = resolve_secret_files(variables, &prefix)?;
+        let variables = variables
+            .into_iter()
+            .filter_map(|(name, value)| Some((config_key(&name, &prefix)?, value)));
+        let config = serde_env::from_iter(variables)?;
 
         Ok(config)
     }
 
     pub fn merge_configs(prioritized_config: &ENV, secondary_config: FILE) -> FILE {
-        secondary_config.merge(prioritized_config)
+        Merge::<ENV>::merge(&secondary_config, prioritized_config)
     }
 
+    // Fails if the config has problems of severity Error. Warnings are logged.
     pub fn load_config(&self) -> Result<FILE, ConfigParseError> {
+        let config = self.load_unvalidated_config()?;
+
+        let (errors, warnings): (Vec<_>, Vec<_>) = config
+            .validate(&self.env_prefix())
+            .into_iter()
+            .partition(|problem| problem.severity == Severity::Error);
+        if !errors.is_empty() {
+            return Err(ConfigParseError::Invalid(errors));
+        }
+
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+
+        Ok(config)
+    }
+
+    pub fn load_unvalidated_config(&self) -> Result<FILE, ConfigParseError> {
         let env_config = self.load_config_from_env()?;
         let file_config = self.load_config_from_file()?;
-        let merged_config = ConfigHandler::merge_configs(&env_config, file_config);
+        let merged_config = Self::merge_configs(&env_config, file_config);
+
+        match &self.cli_config {
+            Some(cli_config) => Ok(Merge::<CLI>::merge(&merged_config, cli_config)),
+            None => Ok(merged_config),
+        }
+    }
+}
 
-        Ok(merged_config)
+// Objects are merged field by field, everything else is replaced by the overlay
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_fields), Value::Object(overlay_fields)) => {
+            for (name, value) in overlay_fields {
+                match base_fields.get_mut(&name) {
+                    Some(base_value) => merge_values(base_value, value),
+                    None => {
+                        base_fields.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
+
+// serde_env treats every underscore as a possible nesting level, so nested fields can already be set with single underscores.
+// Double underscores make the nesting readable, e.g. LUM_MY_SERVICE__API_KEY for my_service.api_key, and are folded into one.
+fn config_key(name: &str, prefix: &str) -> Option<String> {
+    let key = name.strip_prefix(prefix)?.strip_prefix('_')?;
+    Some(key.replace("__", "_"))
+}
+
+// Only these can be read from files, so other variables ending in _FILE, like LUM_CONFIG_KEY_FILE, are left alone
+const SECRET_FILE_VARIABLES: [&str; 3] =
+    ["DISCORD_TOKEN", "REMOTE_ADMIN_TOKEN", "HTTP_ADMIN_TOKEN"];
+
+// Follows the Docker secrets convention: LUM_DISCORD_TOKEN_FILE=/run/secrets/token sets LUM_DISCORD_TOKEN to the file's content
+fn resolve_secret_files(
+    mut variables: BTreeMap<String, String>,
+    prefix: &str,
+) -> Result<BTreeMap<String, String>, EnvironmentConfigParseError> {
+    let prefix = format!("{}_", prefix);
+    let secret_files = variables
+        .iter()
+        .filter_map(|(name, path)| {
+            let secret_name = name.strip_suffix("_FILE")?;
+            let variable = secret_name.strip_prefix(&prefix)?;
+            if !SECRET_FILE_VARIABLES.contains(&variable) {
+                return None;
+            }
+
+            Some((name.clone(), secret_name.to_string(), PathBuf::from(path)))
+        })
+        .collect::<Vec<_>>();
+
+    for (file_name, name, path) in secret_files {
+        variables.remove(&file_name);
+
+        if variables.contains_key(&name) {
+            return Err(EnvironmentConfigParseError::AmbiguousSecret(name));
+        }
+
+        // Secret files usually end with a newline, which is not part of the secret
+        let secret = fs::read_to_string(&path)
+            .map_err(|err| EnvironmentConfigParseError::SecretFile(name.clone(), path, err))?;
+        variables.insert(name, secret.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    Ok(variables)
+}
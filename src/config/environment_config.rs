@@ -2,14 +2,35 @@ use std::fmt::{self, Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
+use super::secrets::{Redact, Secret, REDACTED};
+
 #[derive(Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
 pub struct EnvironmentConfig {
-    pub discord_token: Option<String>,
+    pub discord_token: Option<Secret<String>>,
+    pub discord_intents: Option<String>,
+    pub discord_shards: Option<u32>,
+    pub discord_log_channel: Option<u64>,
+    pub console: Option<bool>,
+    pub remote_admin_token: Option<Secret<String>>,
+    pub http_admin_token: Option<Secret<String>>,
+}
+
+impl Redact for EnvironmentConfig {
+    fn redacted(&self) -> Self {
+        let redact = |_: &Secret<String>| Secret::new(REDACTED.to_string());
+
+        EnvironmentConfig {
+            discord_token: self.discord_token.as_ref().map(redact),
+            remote_admin_token: self.remote_admin_token.as_ref().map(redact),
+            http_admin_token: self.http_admin_token.as_ref().map(redact),
+            ..self.clone()
+        }
+    }
 }
 
 impl Display for EnvironmentConfig {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let content = match serde_json::to_string(self) {
+        let content = match serde_json::to_string(&self.redacted()) {
             Ok(content) => content,
             Err(error) => {
                 return write!(f, "Unable to serialize config: {}", error);
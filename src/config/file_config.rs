@@ -1,13 +1,135 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
 
+use log::LevelFilter;
 use serde::{Deserialize, Serialize};
+use serenity::all::GatewayIntents;
 
-use super::{EnvironmentConfig, Merge};
+use super::{
+    schema::FieldDescriptions,
+    secrets::{Redact, Secret, REDACTED},
+    CliConfig, EnvironmentConfig, Merge, Migration,
+};
+
+// Placeholder in new config files, which validation reports as a missing token
+pub const DEFAULT_DISCORD_TOKEN: &str = "Please provide a token";
+
+// Whenever a field is renamed or changes its format, a migration that upgrades old config files has to be appended here
+pub const MIGRATIONS: &[Migration] = &[];
+
+pub const FIELD_DESCRIPTIONS: FieldDescriptions = &[
+    ("discordToken", "Token of the bot from https://discord.com/developers/applications"),
+    ("discordIntents", "Gateway intents like GUILD_MESSAGES. Privileged intents have to be enabled in the Discord developer portal first."),
+    ("discordShards", "Number of shards. Discord's recommended shard count is used if not set."),
+    ("botAdmins", "IDs of the users who may use admin commands"),
+    ("discordLogChannel", "ID of a channel that warnings and errors are forwarded to"),
+    ("webhooks", "Webhook URLs by name"),
+    ("console", "Whether the interactive console is started. Defaults to whether stdin is a terminal."),
+    ("adminSocket", "Path of the Unix socket for admin commands, which is only created if set"),
+    ("remoteAdminAddress", "Address like 127.0.0.1:7070 for the remote admin API, which is only started if both address and token are set"),
+    ("remoteAdminToken", "Token that clients of the remote admin API have to send"),
+    ("httpAdminAddress", "Address like 0.0.0.0:8080 for the HTTP status and admin endpoints"),
+    ("httpAdminToken", "Token for controlling services over HTTP, which is disabled if not set"),
+    ("commandTestGuilds", "IDs of guilds that commands are registered in instead of globally, which is faster during development"),
+    ("commandRegistrationDryRun", "Log command registration changes instead of applying them"),
+    ("logLevels", "Log levels by target like serenity or lum::service, e.g. {\"serenity\": \"info\"}. Overrides the defaults that silence chatty dependencies."),
+    ("logColors", "Whether log levels are colored. Colors are never used when logging to a file or when NO_COLOR is set."),
+];
+
+fn default_log_colors() -> bool {
+    true
+}
+
+// Privileged intents have to be enabled in the Discord developer portal, so they are opt-in
+fn default_discord_intents() -> Vec<String> {
+    GatewayIntents::non_privileged()
+        .iter_names()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
 
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
 pub struct FileConfig {
     #[serde(rename = "discordToken")]
-    pub discord_token: String,
+    pub discord_token: Secret<String>,
+
+    #[serde(rename = "discordIntents", default = "default_discord_intents")]
+    pub discord_intents: Vec<String>,
+
+    // Without a shard count, Discord's recommended shard count is used
+    #[serde(rename = "discordShards", default)]
+    pub discord_shards: Option<u32>,
+
+    #[serde(rename = "botAdmins", default)]
+    pub bot_admins: Vec<u64>,
+
+    // Warnings and errors are forwarded to this channel if set
+    #[serde(rename = "discordLogChannel", default)]
+    pub discord_log_channel: Option<u64>,
+
+    // Webhook URLs by name
+    #[serde(default)]
+    pub webhooks: BTreeMap<String, Secret<String>>,
+
+    // Without a value, the console service decides at startup whether stdin is a terminal. Not written to the file then,
+    // so a config created on a headless deployment does not turn the console off everywhere else.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub console: Option<bool>,
+
+    // Path of the Unix socket for admin commands. The socket is only created if set.
+    #[serde(rename = "adminSocket", default)]
+    pub admin_socket: Option<String>,
+
+    // Address like 127.0.0.1:7070 for the remote admin API, which is only started if both address and token are set
+    #[serde(rename = "remoteAdminAddress", default)]
+    pub remote_admin_address: Option<String>,
+
+    #[serde(rename = "remoteAdminToken", default)]
+    pub remote_admin_token: Option<Secret<String>>,
+
+    // Address like 0.0.0.0:8080 for the HTTP status and admin endpoints
+    #[serde(rename = "httpAdminAddress", default)]
+    pub http_admin_address: Option<String>,
+
+    // Service control over HTTP is disabled without a token
+    #[serde(rename = "httpAdminToken", default)]
+    pub http_admin_token: Option<Secret<String>>,
+
+    // Commands are only registered in these guilds if set, which is faster during development
+    #[serde(rename = "commandTestGuilds", default)]
+    pub command_test_guilds: Vec<u64>,
+
+    // Logs command registration changes instead of applying them
+    #[serde(rename = "commandRegistrationDryRun", default)]
+    pub command_registration_dry_run: bool,
+
+    // Levels by log target, applied on top of lum's defaults
+    #[serde(rename = "logLevels", default)]
+    pub log_levels: BTreeMap<String, LevelFilter>,
+
+    // Only turns colors off. Output that isn't a terminal is never colored.
+    #[serde(rename = "logColors", default = "default_log_colors")]
+    pub log_colors: bool,
+}
+
+impl Redact for FileConfig {
+    fn redacted(&self) -> Self {
+        let redact = |_: &Secret<String>| Secret::new(REDACTED.to_string());
+
+        FileConfig {
+            discord_token: redact(&self.discord_token),
+            webhooks: self
+                .webhooks
+                .iter()
+                .map(|(name, url)| (name.clone(), redact(url)))
+                .collect(),
+            remote_admin_token: self.remote_admin_token.as_ref().map(redact),
+            http_admin_token: self.http_admin_token.as_ref().map(redact),
+            ..self.clone()
+        }
+    }
 }
 
 impl Merge<EnvironmentConfig> for FileConfig {
@@ -17,21 +139,89 @@ impl Merge<EnvironmentConfig> for FileConfig {
             .clone()
             .unwrap_or(self.discord_token.clone());
 
-        FileConfig { discord_token }
+        // Intents are given as a comma-separated list in the environment
+        let discord_intents = match &other.discord_intents {
+            Some(discord_intents) => discord_intents
+                .split(',')
+                .map(|intent| intent.trim().to_string())
+                .filter(|intent| !intent.is_empty())
+                .collect(),
+            None => self.discord_intents.clone(),
+        };
+
+        FileConfig {
+            discord_token,
+            discord_intents,
+            discord_shards: other.discord_shards.or(self.discord_shards),
+            bot_admins: self.bot_admins.clone(),
+            discord_log_channel: other.discord_log_channel.or(self.discord_log_channel),
+            webhooks: self.webhooks.clone(),
+            console: other.console.or(self.console),
+            admin_socket: self.admin_socket.clone(),
+            remote_admin_address: self.remote_admin_address.clone(),
+            http_admin_address: self.http_admin_address.clone(),
+            http_admin_token: other
+                .http_admin_token
+                .clone()
+                .or(self.http_admin_token.clone()),
+            remote_admin_token: other
+                .remote_admin_token
+                .clone()
+                .or(self.remote_admin_token.clone()),
+            command_test_guilds: self.command_test_guilds.clone(),
+            command_registration_dry_run: self.command_registration_dry_run,
+            log_levels: self.log_levels.clone(),
+            log_colors: self.log_colors,
+        }
+    }
+}
+
+impl Merge<CliConfig> for FileConfig {
+    fn merge(&self, other: &CliConfig) -> Self {
+        FileConfig {
+            discord_token: other
+                .discord_token
+                .clone()
+                .unwrap_or(self.discord_token.clone()),
+            discord_intents: other
+                .discord_intents
+                .clone()
+                .unwrap_or(self.discord_intents.clone()),
+            discord_shards: other.discord_shards.or(self.discord_shards),
+            discord_log_channel: other.discord_log_channel.or(self.discord_log_channel),
+            console: other.console.or(self.console),
+            admin_socket: other.admin_socket.clone().or(self.admin_socket.clone()),
+            ..self.clone()
+        }
     }
 }
 
 impl Default for FileConfig {
     fn default() -> Self {
         FileConfig {
-            discord_token: String::from("Please provide a token"),
+            discord_token: Secret::new(String::from(DEFAULT_DISCORD_TOKEN)),
+            discord_intents: default_discord_intents(),
+            discord_shards: None,
+            bot_admins: Vec::new(),
+            discord_log_channel: None,
+            webhooks: BTreeMap::new(),
+            console: None,
+            admin_socket: None,
+            remote_admin_address: None,
+            http_admin_address: None,
+            http_admin_token: None,
+            remote_admin_token: None,
+            command_test_guilds: Vec::new(),
+            command_registration_dry_run: false,
+            log_levels: BTreeMap::new(),
+            log_colors: default_log_colors(),
         }
     }
 }
 
 impl Display for FileConfig {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let content = match serde_json::to_string(self) {
+        let content = match serde_json::to_string(&self.redacted()) {
             Ok(content) => content,
             Err(error) => {
                 return write!(f, "Unable to serialize config: {}", error);
@@ -0,0 +1,94 @@
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+pub const VERSION_FIELD: &str = "configVersion";
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("The config must be a JSON object")]
+    NotAnObject,
+
+    #[error("{VERSION_FIELD} must be a non-negative integer")]
+    InvalidVersion,
+
+    #[error("Config version {found} is newer than the supported version {supported}, it was probably written by a newer release")]
+    Unsupported { found: u32, supported: u32 },
+
+    #[error("Migration from version {from} to {} ({description}) failed: {reason}", .from + 1)]
+    Failed {
+        from: u32,
+        description: &'static str,
+        reason: String,
+    },
+}
+
+// Migration n upgrades a config from version n to version n + 1, so the current version is the number of migrations
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub description: &'static str,
+    pub migrate: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+impl Migration {
+    pub const fn new(
+        description: &'static str,
+        migrate: fn(&mut Map<String, Value>) -> Result<(), String>,
+    ) -> Self {
+        Self {
+            description,
+            migrate,
+        }
+    }
+}
+
+pub fn current_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32
+}
+
+// Files without a version were written before versioning was introduced, which makes them version 0
+pub fn version_of(config: &Map<String, Value>) -> Result<u32, MigrationError> {
+    match config.get(VERSION_FIELD) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(MigrationError::InvalidVersion),
+    }
+}
+
+pub fn set_version(config: &mut Value, version: u32) {
+    if let Value::Object(fields) = config {
+        fields.insert(VERSION_FIELD.to_string(), Value::from(version));
+    }
+}
+
+// Returns the version the config had, if it had to be migrated
+pub fn migrate(
+    config: &mut Value,
+    migrations: &[Migration],
+) -> Result<Option<u32>, MigrationError> {
+    let fields = match config {
+        Value::Object(fields) => fields,
+        _ => return Err(MigrationError::NotAnObject),
+    };
+
+    let found = version_of(fields)?;
+    let supported = current_version(migrations);
+    if found > supported {
+        return Err(MigrationError::Unsupported { found, supported });
+    }
+    if found == supported {
+        return Ok(None);
+    }
+
+    for (from, migration) in migrations.iter().enumerate().skip(found as usize) {
+        (migration.migrate)(fields).map_err(|reason| MigrationError::Failed {
+            from: from as u32,
+            description: migration.description,
+            reason,
+        })?;
+    }
+
+    fields.insert(VERSION_FIELD.to_string(), Value::from(supported));
+    Ok(Some(found))
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+use serenity::async_trait;
+
+use super::source::{parse_config, ConfigSource, ConfigSourceError};
+
+// Startup waits for the config, so an unreachable server must not block it for long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Fetches the config with a plain GET request. The token is sent as bearer token.
+pub struct HttpConfigSource {
+    url: String,
+    token: Option<String>,
+    client: Client,
+}
+
+impl HttpConfigSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            token: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl ConfigSource for HttpConfigSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn fetch(&self) -> Result<Value, ConfigSourceError> {
+        let mut request = self.client.get(&self.url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = send(request).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ConfigSourceError::NotFound(self.url.clone()));
+        }
+
+        parse_config(&read_body(response).await?)
+    }
+}
+
+// Reads the config from a key of Consul's key/value store
+pub struct ConsulConfigSource {
+    address: String,
+    key: String,
+    token: Option<String>,
+    client: Client,
+}
+
+impl ConsulConfigSource {
+    pub fn new(address: &str, key: &str) -> Self {
+        Self {
+            address: address.trim_end_matches('/').to_string(),
+            key: key.trim_matches('/').to_string(),
+            token: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl ConfigSource for ConsulConfigSource {
+    fn name(&self) -> String {
+        format!("Consul key {} at {}", self.key, self.address)
+    }
+
+    async fn fetch(&self) -> Result<Value, ConfigSourceError> {
+        let url = format!("{}/v1/kv/{}?raw", self.address, self.key);
+        let mut request = self.client.get(url);
+        if let Some(token) = &self.token {
+            request = request.header("X-Consul-Token", token);
+        }
+
+        let response = send(request).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ConfigSourceError::NotFound(self.key.clone()));
+        }
+
+        parse_config(&read_body(response).await?)
+    }
+}
+
+// Reads the config from a key of etcd through its JSON gateway, which encodes keys and values as base64
+pub struct EtcdConfigSource {
+    address: String,
+    key: String,
+    token: Option<String>,
+    client: Client,
+}
+
+impl EtcdConfigSource {
+    pub fn new(address: &str, key: &str) -> Self {
+        Self {
+            address: address.trim_end_matches('/').to_string(),
+            key: key.to_string(),
+            token: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl ConfigSource for EtcdConfigSource {
+    fn name(&self) -> String {
+        format!("etcd key {} at {}", self.key, self.address)
+    }
+
+    async fn fetch(&self) -> Result<Value, ConfigSourceError> {
+        let url = format!("{}/v3/kv/range", self.address);
+        let body = json!({ "key": STANDARD.encode(&self.key) });
+        let mut request = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", token);
+        }
+
+        let response = parse_config(&read_body(send(request).await?).await?)?;
+        let encoded = response
+            .get("kvs")
+            .and_then(|kvs| kvs.get(0))
+            .and_then(|kv| kv.get("value"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| ConfigSourceError::NotFound(self.key.clone()))?;
+        let content = STANDARD.decode(encoded).map_err(|_| {
+            ConfigSourceError::Response(String::from("The value is not valid base64"))
+        })?;
+
+        parse_config(&content)
+    }
+}
+
+// Schemes like consul+http:// select the store, the path of the URL is the key
+pub fn from_url(
+    url: &str,
+    token: Option<String>,
+) -> Result<Box<dyn ConfigSource>, ConfigSourceError> {
+    let (store, url) = match url.split_once('+') {
+        Some((store, url)) if store == "consul" || store == "etcd" => (Some(store), url),
+        _ => (None, url),
+    };
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ConfigSourceError::UnsupportedUrl(url.to_string()));
+    }
+
+    let (address, key) = match url.splitn(4, '/').collect::<Vec<_>>().as_slice() {
+        [scheme, "", authority, key] => (format!("{}//{}", scheme, authority), *key),
+        _ => (url.to_string(), ""),
+    };
+
+    if store.is_some() && key.is_empty() {
+        return Err(ConfigSourceError::UnsupportedUrl(url.to_string()));
+    }
+
+    let source: Box<dyn ConfigSource> = match (store, token) {
+        (Some("consul"), Some(token)) => {
+            Box::new(ConsulConfigSource::new(&address, key).with_token(token))
+        }
+        (Some("consul"), None) => Box::new(ConsulConfigSource::new(&address, key)),
+        (Some("etcd"), Some(token)) => {
+            Box::new(EtcdConfigSource::new(&address, key).with_token(token))
+        }
+        (Some("etcd"), None) => Box::new(EtcdConfigSource::new(&address, key)),
+        (_, Some(token)) => Box::new(HttpConfigSource::new(url).with_token(token)),
+        (_, None) => Box::new(HttpConfigSource::new(url)),
+    };
+
+    Ok(source)
+}
+
+async fn send(request: RequestBuilder) -> Result<Response, ConfigSourceError> {
+    request
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| ConfigSourceError::Request(Box::new(err)))
+}
+
+async fn read_body(response: Response) -> Result<Vec<u8>, ConfigSourceError> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ConfigSourceError::Response(format!(
+            "Server responded with {}",
+            status
+        )));
+    }
+
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| ConfigSourceError::Request(Box::new(err)))?;
+
+    Ok(body.to_vec())
+}
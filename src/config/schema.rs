@@ -0,0 +1,38 @@
+use serde_json::{json, Map, Value};
+
+use super::migration;
+
+pub const SCHEMA_FIELD: &str = "$schema";
+
+// Field descriptions by the name the field has in the config file
+pub type FieldDescriptions = &'static [(&'static str, &'static str)];
+
+// JSON has no comments, so the fields are documented in a JSON schema instead, which editors show when hovering a field
+pub fn generate(app_name: &str, defaults: &Value, descriptions: FieldDescriptions) -> Value {
+    let mut properties = Map::new();
+
+    for (name, description) in descriptions {
+        let mut property = json!({ "description": description });
+        if let Some(default) = defaults.get(name) {
+            property["default"] = default.clone();
+        }
+
+        properties.insert(name.to_string(), property);
+    }
+
+    properties.insert(
+        migration::VERSION_FIELD.to_string(),
+        json!({
+            "description": "Version of the config format, used to upgrade old config files. Do not change it.",
+            "type": "integer",
+        }),
+    );
+    properties.insert(SCHEMA_FIELD.to_string(), json!({ "type": "string" }));
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("{} config", app_name),
+        "type": "object",
+        "properties": properties,
+    })
+}
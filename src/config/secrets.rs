@@ -0,0 +1,189 @@
+use std::{
+    env,
+    fmt::{self, Debug, Display, Formatter},
+    fs, io,
+    path::PathBuf,
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+// Encrypted values are stored as "enc:v1:<base64 of nonce and ciphertext>"
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+const NONCE_LENGTH: usize = 12;
+
+pub const REDACTED: &str = "***";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("The config contains encrypted values, but neither {0} nor {0}_FILE is set")]
+    MissingKey(String),
+
+    #[error("Unable to read the config key from {0}: {1}")]
+    KeyFile(PathBuf, io::Error),
+
+    #[error("The config key must be 32 bytes, encoded as base64")]
+    InvalidKey,
+
+    #[error("Encrypted value is malformed")]
+    Malformed,
+
+    #[error("Unable to decrypt value, the config key is probably wrong")]
+    Decryption,
+
+    #[error("Unable to encrypt value")]
+    Encryption,
+}
+
+pub struct SecretKey(Key<Aes256Gcm>);
+
+impl SecretKey {
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, SecretError> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| SecretError::InvalidKey)?;
+        if bytes.len() != 32 {
+            return Err(SecretError::InvalidKey);
+        }
+
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+
+    // Like other secrets, the key can be given directly or through a file, e.g. LUM_CONFIG_KEY or LUM_CONFIG_KEY_FILE
+    pub fn from_env(variable: &str) -> Result<Option<Self>, SecretError> {
+        if let Ok(encoded) = env::var(variable) {
+            return Self::from_base64(&encoded).map(Some);
+        }
+
+        let path = match env::var(format!("{}_FILE", variable)) {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => return Ok(None),
+        };
+
+        let encoded =
+            fs::read_to_string(&path).map_err(|err| SecretError::KeyFile(path.clone(), err))?;
+        Self::from_base64(&encoded).map(Some)
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, SecretError> {
+        let cipher = Aes256Gcm::new(&self.0);
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| SecretError::Encryption)?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, SecretError> {
+        let encoded = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or(SecretError::Malformed)?;
+        let payload = STANDARD
+            .decode(encoded)
+            .map_err(|_| SecretError::Malformed)?;
+        if payload.len() <= NONCE_LENGTH {
+            return Err(SecretError::Malformed);
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let plaintext = Aes256Gcm::new(&self.0)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretError::Decryption)?;
+
+        String::from_utf8(plaintext).map_err(|_| SecretError::Decryption)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+pub fn contains_encrypted(value: &Value) -> bool {
+    match value {
+        Value::String(string) => is_encrypted(string),
+        Value::Array(values) => values.iter().any(contains_encrypted),
+        Value::Object(fields) => fields.values().any(contains_encrypted),
+        _ => false,
+    }
+}
+
+// Decrypts every encrypted string, no matter in which field it is
+pub fn decrypt_all(value: &mut Value, key: &SecretKey) -> Result<(), SecretError> {
+    match value {
+        Value::String(string) if is_encrypted(string) => *string = key.decrypt(string)?,
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                decrypt_all(value, key)?;
+            }
+        }
+        Value::Object(fields) => {
+            for value in fields.values_mut() {
+                decrypt_all(value, key)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// Implemented by configs, to get a copy that is safe to print or log
+pub trait Redact {
+    fn redacted(&self) -> Self;
+}
+
+// Keeps a value like a token out of logs. It is (de)serialized as the plain value, so it can still be stored in config files.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Display for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
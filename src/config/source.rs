@@ -0,0 +1,61 @@
+use std::error::Error;
+
+use ::log::{info, warn};
+use serde_json::Value;
+use serenity::async_trait;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigSourceError {
+    #[error("Unsupported config source {0}, expected an http(s)://, consul+http(s):// or etcd+http(s):// URL")]
+    UnsupportedUrl(String),
+
+    #[error("Request failed: {0}")]
+    Request(Box<dyn Error + Send + Sync>),
+
+    #[error("Key {0} does not exist")]
+    NotFound(String),
+
+    #[error("Unexpected response: {0}")]
+    Response(String),
+
+    #[error("Unable to parse config: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("The config must be a JSON object")]
+    NotAnObject,
+}
+
+// A config that is fetched from somewhere else than the config file, e.g. a central config server.
+// It is applied on top of the config file and its overlays, like another overlay.
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+pub trait ConfigSource: Send + Sync {
+    // Shown in logs, so it must not contain credentials
+    fn name(&self) -> String;
+    async fn fetch(&self) -> Result<Value, ConfigSourceError>;
+}
+
+pub fn parse_config(content: &[u8]) -> Result<Value, ConfigSourceError> {
+    let config: Value = serde_json::from_slice(content)?;
+    match config {
+        Value::Object(_) => Ok(config),
+        _ => Err(ConfigSourceError::NotAnObject),
+    }
+}
+
+// Sources are tried in order and the first one that responds wins. If none does, the local config is used alone.
+pub async fn fetch_first(sources: &[Box<dyn ConfigSource>]) -> Option<Value> {
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    for source in sources {
+        match source.fetch().await {
+            Ok(config) => {
+                info!("Loaded config from {}", source.name());
+                return Some(config);
+            }
+            Err(err) => warn!("Unable to load config from {}: {}", source.name(), err),
+        }
+    }
+
+    None
+}
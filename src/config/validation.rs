@@ -0,0 +1,202 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    net::SocketAddr,
+};
+
+use serenity::utils::token;
+
+use super::{file_config::DEFAULT_DISCORD_TOKEN, FileConfig};
+use crate::service::{discord::parse_gateway_intents, webhook::parse_webhook_url};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+// The field is the path as it appears in the config file, like webhooks.alerts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl ConfigProblem {
+    pub fn error(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            field: field.to_string(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warning(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            field: field.to_string(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} in {}: {}", self.severity, self.field, self.message)?;
+
+        match &self.hint {
+            Some(hint) => write!(f, " ({})", hint),
+            None => Ok(()),
+        }
+    }
+}
+
+// Implemented by file configs, so ConfigHandler can reject invalid configs before anything is started with them.
+// Hints name environment variables with the env prefix of the config handler, e.g. LUM for LUM_DISCORD_TOKEN.
+pub trait Validate {
+    fn validate(&self, env_prefix: &str) -> Vec<ConfigProblem>;
+}
+
+fn check_address(problems: &mut Vec<ConfigProblem>, field: &str, address: &Option<String>) -> bool {
+    match address {
+        Some(address) if address.parse::<SocketAddr>().is_err() => {
+            problems.push(
+                ConfigProblem::error(field, format!("{} is not a valid address", address))
+                    .with_hint("Use an IP address and port like 127.0.0.1:8080"),
+            );
+            false
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+// Catches what would otherwise only fail once the bot is starting up
+impl Validate for FileConfig {
+    fn validate(&self, env_prefix: &str) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        let token = self.discord_token.expose().trim();
+        if token.is_empty() || token == DEFAULT_DISCORD_TOKEN {
+            problems.push(
+                ConfigProblem::error("discordToken", "No Discord token is configured").with_hint(
+                    format!(
+                        "Copy the token of your bot from https://discord.com/developers/applications or set {}_DISCORD_TOKEN",
+                        env_prefix
+                    ),
+                ),
+            );
+        } else if token::validate(token).is_err() {
+            problems.push(
+                ConfigProblem::error(
+                    "discordToken",
+                    "This does not look like a Discord bot token",
+                )
+                .with_hint("Bot tokens consist of three parts separated by dots"),
+            );
+        }
+
+        if let Err(error) = parse_gateway_intents(&self.discord_intents) {
+            problems.push(
+                ConfigProblem::error("discordIntents", error.to_string())
+                    .with_hint("Use names like GUILDS or GUILD_MESSAGES"),
+            );
+        }
+
+        if self.discord_shards == Some(0) {
+            problems.push(
+                ConfigProblem::error("discordShards", "At least one shard is needed")
+                    .with_hint("Remove the field to use Discord's recommended shard count"),
+            );
+        }
+
+        if self.bot_admins.contains(&0) {
+            problems.push(ConfigProblem::error(
+                "botAdmins",
+                "0 is not a valid user ID",
+            ));
+        }
+
+        if self.discord_log_channel == Some(0) {
+            problems.push(ConfigProblem::error(
+                "discordLogChannel",
+                "0 is not a valid channel ID",
+            ));
+        }
+
+        for (name, url) in self.webhooks.iter() {
+            if parse_webhook_url(url.expose()).is_none() {
+                problems.push(
+                    ConfigProblem::error(
+                        &format!("webhooks.{}", name),
+                        "This is not a valid webhook URL",
+                    )
+                    .with_hint("Expected https://discord.com/api/webhooks/<id>/<token>"),
+                );
+            }
+        }
+
+        if self
+            .admin_socket
+            .as_ref()
+            .is_some_and(|admin_socket| admin_socket.trim().is_empty())
+        {
+            problems.push(
+                ConfigProblem::error("adminSocket", "Path is empty")
+                    .with_hint("Remove the field to disable the admin socket"),
+            );
+        }
+
+        let has_remote_admin = check_address(
+            &mut problems,
+            "remoteAdminAddress",
+            &self.remote_admin_address,
+        );
+        if has_remote_admin
+            && self
+                .remote_admin_token
+                .as_ref()
+                .map_or(true, |token| token.expose().is_empty())
+        {
+            problems.push(ConfigProblem::warning(
+                "remoteAdminToken",
+                "The remote admin API is not started without a token",
+            ));
+        }
+
+        let has_http_admin =
+            check_address(&mut problems, "httpAdminAddress", &self.http_admin_address);
+        if has_http_admin && self.http_admin_token.is_none() {
+            problems.push(ConfigProblem::warning(
+                "httpAdminToken",
+                "Service control over HTTP is disabled without a token",
+            ));
+        }
+
+        if self.command_test_guilds.contains(&0) {
+            problems.push(ConfigProblem::error(
+                "commandTestGuilds",
+                "0 is not a valid guild ID",
+            ));
+        }
+
+        problems
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    process,
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DaemonError {
+    #[error("Another instance is already running with PID {0}")]
+    AlreadyRunning(i32),
+
+    #[error("Unable to fork: {0}")]
+    Fork(io::Error),
+
+    #[error("Unable to create a new session: {0}")]
+    SetSid(io::Error),
+
+    #[error("Unable to redirect standard streams: {0}")]
+    Redirect(io::Error),
+
+    #[error("Unable to write PID file {0}: {1}")]
+    PidFile(PathBuf, io::Error),
+}
+
+// Removes the PID file again when the daemon exits
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Returns the PID stored in the file, if that process is still alive
+pub fn running_pid(pid_file_path: &Path) -> Option<i32> {
+    let pid = fs::read_to_string(pid_file_path)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // Signal 0 only checks whether the process exists
+    match unsafe { libc::kill(pid, 0) } {
+        0 => Some(pid),
+        _ => None,
+    }
+}
+
+// Has to be called before any threads are spawned, so before the tokio runtime and the logger are set up.
+// The working directory is kept, so relative paths in the config keep working.
+pub fn daemonize(pid_file_path: &Path) -> Result<PidFile, DaemonError> {
+    if let Some(pid) = running_pid(pid_file_path) {
+        return Err(DaemonError::AlreadyRunning(pid));
+    }
+
+    fork_and_exit_parent()?;
+
+    if unsafe { libc::setsid() } == -1 {
+        return Err(DaemonError::SetSid(io::Error::last_os_error()));
+    }
+
+    // Forking again makes sure the daemon can never reacquire a controlling terminal
+    fork_and_exit_parent()?;
+
+    redirect_standard_streams().map_err(DaemonError::Redirect)?;
+
+    write_pid_file(pid_file_path)
+        .map_err(|err| DaemonError::PidFile(pid_file_path.to_path_buf(), err))?;
+
+    Ok(PidFile {
+        path: pid_file_path.to_path_buf(),
+    })
+}
+
+fn fork_and_exit_parent() -> Result<(), DaemonError> {
+    match unsafe { libc::fork() } {
+        -1 => Err(DaemonError::Fork(io::Error::last_os_error())),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+fn redirect_standard_streams() -> io::Result<()> {
+    let dev_null = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/null")?;
+
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(dev_null.as_raw_fd(), fd) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+fn write_pid_file(pid_file_path: &Path) -> io::Result<()> {
+    if let Some(parent) = pid_file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(pid_file_path, format!("{}\n", process::id()))
+}
@@ -0,0 +1,5 @@
+pub mod locale;
+pub mod reply;
+
+pub use locale::{LocaleError, Locales};
+pub use reply::{ReplyKind, ReplyStyle};
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serenity::all::GuildId;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Error)]
+pub enum LocaleError {
+    #[error("Unable to read locale file {0}: {1}")]
+    Io(PathBuf, io::Error),
+
+    #[error("Unable to parse locale file {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+}
+
+// Response strings by locale and key. Locales use Discord's names, e.g. "en-US" or "de".
+pub struct Locales {
+    default_locale: String,
+    strings: HashMap<String, HashMap<String, String>>,
+    guild_locales: RwLock<HashMap<GuildId, String>>,
+}
+
+impl Locales {
+    pub fn new(default_locale: &str) -> Self {
+        Self {
+            default_locale: default_locale.to_string(),
+            strings: HashMap::new(),
+            guild_locales: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_strings(mut self, locale: &str, strings: HashMap<String, String>) -> Self {
+        self.strings
+            .entry(locale.to_string())
+            .or_default()
+            .extend(strings);
+        self
+    }
+
+    // Loads every <locale>.json file in the directory. Each file is a flat object of keys to strings.
+    pub fn with_directory(mut self, path: &Path) -> Result<Self, LocaleError> {
+        let entries =
+            fs::read_dir(path).map_err(|error| LocaleError::Io(path.to_path_buf(), error))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|error| LocaleError::Io(path.to_path_buf(), error))?;
+            let file_path = entry.path();
+
+            if file_path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                continue;
+            }
+
+            let locale = match file_path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(locale) => locale.to_string(),
+                None => continue,
+            };
+
+            let content = fs::read_to_string(&file_path)
+                .map_err(|error| LocaleError::Io(file_path.clone(), error))?;
+            let strings: HashMap<String, String> = serde_json::from_str(&content)
+                .map_err(|error| LocaleError::Parse(file_path.clone(), error))?;
+
+            self = self.with_strings(&locale, strings);
+        }
+
+        Ok(self)
+    }
+
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    pub fn locales(&self) -> Vec<&str> {
+        self.strings.keys().map(|locale| locale.as_str()).collect()
+    }
+
+    // Overrides the locale of users for everything sent in this guild
+    pub async fn set_guild_locale(&self, guild_id: GuildId, locale: Option<&str>) {
+        let mut guild_locales = self.guild_locales.write().await;
+
+        match locale {
+            Some(locale) => guild_locales.insert(guild_id, locale.to_string()),
+            None => guild_locales.remove(&guild_id),
+        };
+    }
+
+    pub async fn guild_locale(&self, guild_id: GuildId) -> Option<String> {
+        self.guild_locales.read().await.get(&guild_id).cloned()
+    }
+
+    // A guild's setting wins over the locale of the user's Discord client
+    pub async fn resolve(&self, guild_id: Option<GuildId>, user_locale: Option<&str>) -> String {
+        if let Some(guild_id) = guild_id {
+            if let Some(locale) = self.guild_locale(guild_id).await {
+                return locale;
+            }
+        }
+
+        match user_locale {
+            Some(locale) => locale.to_string(),
+            None => self.default_locale.clone(),
+        }
+    }
+
+    // Falls back to the language without region (de-AT -> de), then to the default locale, then to the key itself
+    pub fn get(&self, locale: &str, key: &str) -> String {
+        let language = locale.split('-').next().unwrap_or(locale);
+
+        [locale, language, self.default_locale.as_str()]
+            .iter()
+            .filter_map(|locale| self.strings.get(*locale))
+            .find_map(|strings| strings.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    // Replaces {name} placeholders with the given arguments
+    pub fn format(&self, locale: &str, key: &str, arguments: &[(&str, &str)]) -> String {
+        let mut string = self.get(locale, key);
+
+        for (name, value) in arguments {
+            string = string.replace(&format!("{{{}}}", name), value);
+        }
+
+        string
+    }
+}
+
+impl Default for Locales {
+    fn default() -> Self {
+        Self::new("en-US")
+    }
+}
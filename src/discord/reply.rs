@@ -0,0 +1,108 @@
+use serenity::all::{
+    Colour, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReplyKind {
+    Success,
+    Error,
+    Warning,
+    Info,
+}
+
+impl ReplyKind {
+    pub fn colour(&self) -> Colour {
+        match self {
+            ReplyKind::Success => Colour::DARK_GREEN,
+            ReplyKind::Error => Colour::RED,
+            ReplyKind::Warning => Colour::ORANGE,
+            ReplyKind::Info => Colour::BLURPLE,
+        }
+    }
+}
+
+// Shared look of all embeds a bot sends, so modules do not each build their own
+#[derive(Debug, Clone)]
+pub struct ReplyStyle {
+    colour: Colour,
+    footer: Option<String>,
+    timestamps: bool,
+}
+
+impl ReplyStyle {
+    pub fn new(bot_name: &str, version: &str) -> Self {
+        Self {
+            colour: Colour::BLURPLE,
+            footer: Some(format!("{} v{}", bot_name, version)),
+            timestamps: true,
+        }
+    }
+
+    // Colour of embeds that are not a success, error, warning or info reply
+    pub fn with_colour(mut self, colour: Colour) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    pub fn with_footer(mut self, footer: Option<&str>) -> Self {
+        self.footer = footer.map(|footer| footer.to_string());
+        self
+    }
+
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    pub fn embed(&self, title: &str, description: &str) -> CreateEmbed {
+        self.apply(CreateEmbed::new().title(title).description(description))
+    }
+
+    pub fn reply(&self, kind: ReplyKind, title: &str, description: &str) -> CreateEmbed {
+        self.embed(title, description).colour(kind.colour())
+    }
+
+    pub fn success(&self, title: &str, description: &str) -> CreateEmbed {
+        self.reply(ReplyKind::Success, title, description)
+    }
+
+    pub fn error(&self, title: &str, description: &str) -> CreateEmbed {
+        self.reply(ReplyKind::Error, title, description)
+    }
+
+    pub fn warning(&self, title: &str, description: &str) -> CreateEmbed {
+        self.reply(ReplyKind::Warning, title, description)
+    }
+
+    pub fn info(&self, title: &str, description: &str) -> CreateEmbed {
+        self.reply(ReplyKind::Info, title, description)
+    }
+
+    // Styles an embed that was built elsewhere. Its own colour is replaced.
+    pub fn apply(&self, embed: CreateEmbed) -> CreateEmbed {
+        let mut embed = embed.colour(self.colour);
+
+        if let Some(footer) = &self.footer {
+            embed = embed.footer(CreateEmbedFooter::new(footer));
+        }
+
+        if self.timestamps {
+            embed = embed.timestamp(Timestamp::now());
+        }
+
+        embed
+    }
+
+    pub fn message(embed: CreateEmbed) -> CreateMessage {
+        CreateMessage::new().embed(embed)
+    }
+
+    pub fn interaction_response(embed: CreateEmbed, ephemeral: bool) -> CreateInteractionResponse {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .ephemeral(ephemeral),
+        )
+    }
+}
@@ -1,11 +1,22 @@
 pub mod arc_observable;
+pub mod dispatch_report;
 pub mod event;
+pub mod event_bus;
+pub mod event_journal;
 pub mod event_repeater;
 pub mod observable;
 pub mod subscriber;
+pub mod subscription_guard;
+pub mod timing;
+pub mod topic_bus;
 
 pub use arc_observable::ArcObservable;
-pub use event::Event;
+pub use dispatch_report::DispatchReport;
+pub use event::{Event, TryDispatchError};
+pub use event_bus::EventBus;
+pub use event_journal::{EventJournal, JournalEntry, JournalError};
 pub use event_repeater::EventRepeater;
 pub use observable::{Observable, ObservableResult};
-pub use subscriber::{Callback, DispatchError, Subscriber};
+pub use subscriber::{Backpressure, Callback, DispatchError, Handler, Subscriber, SubscriberInfo};
+pub use subscription_guard::SubscriptionGuard;
+pub use topic_bus::{TopicBus, TopicMessage};
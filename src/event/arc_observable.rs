@@ -50,12 +50,9 @@ where
         drop(lock);
 
         let value = Arc::clone(&self.value);
-        let dispatch_result = self.on_change.dispatch(value).await;
+        let report = self.on_change.dispatch(value).await;
 
-        match dispatch_result {
-            Ok(_) => ObservableResult::Changed(Ok(())),
-            Err(errors) => ObservableResult::Changed(Err(errors)),
-        }
+        ObservableResult::Changed(report)
     }
 }
 
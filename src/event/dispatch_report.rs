@@ -0,0 +1,93 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{DispatchError, SubscriberInfo};
+
+// What a dispatch reached. Failed subscribers are listed with the error they failed with, so it is clear which one has to be looked at.
+#[derive(Debug)]
+pub struct DispatchReport<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub succeeded: usize,
+    pub failed: Vec<(SubscriberInfo, DispatchError<T>)>,
+    // Closed events don't dispatch to any subscriber
+    pub closed: bool,
+}
+
+impl<T> DispatchReport<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            succeeded: 0,
+            failed: Vec::new(),
+            closed: false,
+        }
+    }
+
+    pub fn closed() -> Self {
+        Self {
+            closed: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        !self.closed && self.failed.is_empty()
+    }
+
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed.len()
+    }
+
+    // For callers that only care whether something failed. Ok holds the number of subscribers the data was dispatched to.
+    pub fn into_result(self) -> Result<usize, Self> {
+        if self.is_success() {
+            Ok(self.succeeded)
+        } else {
+            Err(self)
+        }
+    }
+
+    // Combines the reports of dispatches to multiple events
+    pub fn append(&mut self, mut other: Self) {
+        self.succeeded += other.succeeded;
+        self.failed.append(&mut other.failed);
+        self.closed |= other.closed;
+    }
+}
+
+impl<T> Default for DispatchReport<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Display for DispatchReport<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.closed {
+            return write!(f, "The event is closed");
+        }
+
+        write!(
+            f,
+            "Dispatched to {} of {} subscribers",
+            self.succeeded,
+            self.total()
+        )?;
+
+        for (index, (subscriber, err)) in self.failed.iter().enumerate() {
+            let separator = if index == 0 { ". Failed: " } else { ", " };
+            write!(f, "{}{}: {}", separator, subscriber, err)?;
+        }
+
+        Ok(())
+    }
+}
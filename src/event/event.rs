@@ -1,16 +1,45 @@
 use crate::service::{BoxedError, PinnedBoxedFutureResult};
+use futures::future::join_all;
 use std::{
     any::type_name,
     fmt::{self, Debug, Formatter},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
+use thiserror::Error;
 use tokio::sync::{
+    broadcast::{self, error::RecvError},
     mpsc::{channel, Receiver},
     Mutex,
 };
+use tokio_stream::{wrappers::ReceiverStream, Stream};
 use uuid::Uuid;
 
-use super::{Callback, DispatchError, Subscriber};
+use super::{
+    timing::{debounce, throttle},
+    Backpressure, Callback, DispatchError, DispatchReport, Handler, Subscriber, SubscriptionGuard,
+};
+
+// Shared with the subscription guards, so they can unsubscribe without holding on to the event
+pub(super) type Subscribers<T> = Arc<Mutex<Vec<Arc<Subscriber<T>>>>>;
+
+#[derive(Debug, Error)]
+pub enum TryDispatchError<T>
+where
+    T: Send + Sync + 'static,
+{
+    #[error("The event is being dispatched or subscribed to at the moment")]
+    Busy,
+
+    #[error("The event is closed")]
+    Closed,
+
+    #[error("Failed to dispatch data to {} subscribers", .0.failed.len())]
+    Failed(DispatchReport<T>),
+}
 
 pub struct Event<T>
 where
@@ -19,7 +48,15 @@ where
     pub name: String,
 
     pub uuid: Uuid,
-    subscribers: Mutex<Vec<Subscriber<T>>>,
+    subscribers: Subscribers<T>,
+    // Dispatches don't overlap, so every subscriber receives values in the order they were dispatched
+    dispatching: Mutex<()>,
+
+    // Sticky events keep the last dispatched value and deliver it to subscribers that subscribe later
+    sticky: bool,
+    last_value: Mutex<Option<Arc<T>>>,
+
+    closed: AtomicBool,
 }
 
 impl<T> Event<T>
@@ -33,39 +70,224 @@ where
         Self {
             name: name.into(),
             uuid: Uuid::new_v4(),
-            subscribers: Mutex::new(Vec::new()),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            dispatching: Mutex::new(()),
+            sticky: false,
+            last_value: Mutex::new(None),
+            closed: AtomicBool::new(false),
         }
     }
 
+    pub fn with_sticky(mut self, sticky: bool) -> Self {
+        self.sticky = sticky;
+        self
+    }
+
+    pub fn is_sticky(&self) -> bool {
+        self.sticky
+    }
+
+    pub async fn last_value(&self) -> Option<Arc<T>> {
+        self.last_value.lock().await.clone()
+    }
+
     pub async fn subscriber_count(&self) -> usize {
         let subscribers = self.subscribers.lock().await;
         subscribers.len()
     }
 
+    // For subscribers with a priority. The other subscribe methods use the default priority.
+    // Dropping the returned guard unsubscribes, so subscriptions that have to outlive it are detached.
+    pub async fn subscribe(&self, subscriber: Subscriber<T>) -> SubscriptionGuard<T> {
+        let guard = SubscriptionGuard::new(&self.subscribers, &self.name, subscriber.uuid);
+
+        // Subscribers are kept sorted, so dispatching does not have to sort them
+        let mut subscribers = self.subscribers.lock().await;
+
+        // Dropping the subscriber ends the receivers of channel subscribers right away, like closing would have
+        if self.is_closed() {
+            return guard;
+        }
+
+        // Replayed while holding the subscribers, so no dispatch can happen in between and the subscriber misses nothing
+        let last_value = self.last_value.lock().await.clone();
+        if let Some(last_value) = last_value {
+            if let Err(err) = subscriber.dispatch(last_value).await {
+                if subscriber.log_on_error {
+                    log::error!(
+                        "Event \"{}\" failed to replay its last value to subscriber {}: {}.",
+                        self.name,
+                        subscriber.name,
+                        err
+                    );
+                }
+
+                if subscriber.remove_on_error || subscriber.disconnects_on(&err) {
+                    if subscriber.log_on_error {
+                        log::error!("Subscriber will not be registered to event.");
+                    }
+
+                    return guard;
+                }
+            }
+        }
+
+        let index = subscribers
+            .iter()
+            .position(|subscribed| subscribed.priority < subscriber.priority)
+            .unwrap_or(subscribers.len());
+        subscribers.insert(index, Arc::new(subscriber));
+
+        guard
+    }
+
     pub async fn subscribe_channel<S>(
         &self,
         name: S,
         buffer: usize,
         log_on_error: bool,
         remove_on_error: bool,
-    ) -> (Uuid, Receiver<Arc<T>>)
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
+    where
+        S: Into<String>,
+    {
+        self.subscribe_channel_with_backpressure(
+            name,
+            buffer,
+            Backpressure::Block,
+            log_on_error,
+            remove_on_error,
+        )
+        .await
+    }
+
+    pub async fn subscribe_channel_with_backpressure<S>(
+        &self,
+        name: S,
+        buffer: usize,
+        backpressure: Backpressure,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
+    where
+        S: Into<String>,
+    {
+        let (callback, receiver) = match backpressure {
+            Backpressure::DropOldest => {
+                let (sender, mut broadcast_receiver) = broadcast::channel(buffer);
+                let (relay_sender, receiver) = channel(1);
+
+                // Lagging behind the broadcast channel drops the oldest values. The relay stops when either side is closed.
+                tokio::spawn(async move {
+                    loop {
+                        match broadcast_receiver.recv().await {
+                            Ok(data) => {
+                                if relay_sender.send(data).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                });
+
+                (Callback::Broadcast(sender), receiver)
+            }
+            _ => {
+                let (sender, receiver) = channel(buffer);
+                (Callback::Channel(sender), receiver)
+            }
+        };
+
+        let subscriber = Subscriber::new(name, log_on_error, remove_on_error, callback)
+            .with_backpressure(backpressure);
+        let guard = self.subscribe(subscriber).await;
+
+        (guard, receiver)
+    }
+
+    // For bursty events. The receiver only gets a value once the event was not dispatched for the given delay.
+    pub async fn subscribe_channel_debounced<S>(
+        &self,
+        name: S,
+        delay: Duration,
+        buffer: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
     where
         S: Into<String>,
     {
-        let (sender, receiver) = channel(buffer);
+        let (guard, receiver) = self
+            .subscribe_channel(name, buffer, log_on_error, remove_on_error)
+            .await;
+
+        (guard, debounce(receiver, delay, buffer))
+    }
+
+    // The receiver gets at most one value per interval, but always the latest one
+    pub async fn subscribe_channel_throttled<S>(
+        &self,
+        name: S,
+        interval: Duration,
+        buffer: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
+    where
+        S: Into<String>,
+    {
+        let (guard, receiver) = self
+            .subscribe_channel(name, buffer, log_on_error, remove_on_error)
+            .await;
+
+        (guard, throttle(receiver, interval, buffer))
+    }
+
+    // More receivers can be created with Receiver::resubscribe. Sending fails once all receivers are dropped.
+    pub async fn subscribe_broadcast<S>(
+        &self,
+        name: S,
+        capacity: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, broadcast::Receiver<Arc<T>>)
+    where
+        S: Into<String>,
+    {
+        let (sender, receiver) = broadcast::channel(capacity);
         let subscriber = Subscriber::new(
             name,
             log_on_error,
             remove_on_error,
-            Callback::Channel(sender),
+            Callback::Broadcast(sender),
         );
 
-        let uuid = subscriber.uuid;
+        let guard = self.subscribe(subscriber).await;
 
-        let mut subscribers = self.subscribers.lock().await;
-        subscribers.push(subscriber);
+        (guard, receiver)
+    }
+
+    // Like subscribe_channel, but as a Stream, so StreamExt combinators can be used instead of a recv loop
+    pub async fn subscribe_stream<S>(
+        &self,
+        name: S,
+        buffer: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (
+        SubscriptionGuard<T>,
+        impl Stream<Item = Arc<T>> + Send + Unpin + 'static,
+    )
+    where
+        S: Into<String>,
+    {
+        let (guard, receiver) = self
+            .subscribe_channel(name, buffer, log_on_error, remove_on_error)
+            .await;
 
-        (uuid, receiver)
+        (guard, ReceiverStream::new(receiver))
     }
 
     pub async fn subscribe_async_closure<S>(
@@ -74,7 +296,7 @@ where
         closure: impl Fn(Arc<T>) -> PinnedBoxedFutureResult<()> + Send + Sync + 'static,
         log_on_error: bool,
         remove_on_error: bool,
-    ) -> Uuid
+    ) -> SubscriptionGuard<T>
     where
         S: Into<String>,
     {
@@ -85,12 +307,7 @@ where
             Callback::AsyncClosure(Box::new(closure)),
         );
 
-        let uuid = subscriber.uuid;
-
-        let mut subscribers = self.subscribers.lock().await;
-        subscribers.push(subscriber);
-
-        uuid
+        self.subscribe(subscriber).await
     }
 
     pub async fn subscribe_closure<S>(
@@ -99,7 +316,7 @@ where
         closure: impl Fn(Arc<T>) -> Result<(), BoxedError> + Send + Sync + 'static,
         log_on_error: bool,
         remove_on_error: bool,
-    ) -> Uuid
+    ) -> SubscriptionGuard<T>
     where
         S: Into<String>,
     {
@@ -110,21 +327,70 @@ where
             Callback::Closure(Box::new(closure)),
         );
 
-        let uuid = subscriber.uuid;
+        self.subscribe(subscriber).await
+    }
 
-        let mut subscribers = self.subscribers.lock().await;
-        subscribers.push(subscriber);
+    pub async fn subscribe_closure_mut<S>(
+        &self,
+        name: S,
+        closure: impl FnMut(Arc<T>) -> Result<(), BoxedError> + Send + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        S: Into<String>,
+    {
+        let subscriber = Subscriber::new(
+            name,
+            log_on_error,
+            remove_on_error,
+            Callback::ClosureMut(std::sync::Mutex::new(Box::new(closure))),
+        );
 
-        uuid
+        self.subscribe(subscriber).await
+    }
+
+    pub async fn subscribe_handler<S>(
+        &self,
+        name: S,
+        handler: impl Handler<T> + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        S: Into<String>,
+    {
+        let subscriber = Subscriber::new(
+            name,
+            log_on_error,
+            remove_on_error,
+            Callback::Handler(Arc::new(Mutex::new(Box::new(handler)))),
+        );
+
+        self.subscribe(subscriber).await
     }
 
     pub async fn unsubscribe<UUID>(&self, uuid: &UUID) -> bool
     where
         UUID: AsRef<Uuid>,
     {
-        let uuid = uuid.as_ref();
-
         let mut subscribers = self.subscribers.lock().await;
+        Self::remove_subscriber(&mut subscribers, uuid.as_ref())
+    }
+
+    // For contexts that can't await. Returns None if the subscribers are locked at the moment.
+    pub fn try_unsubscribe<UUID>(&self, uuid: &UUID) -> Option<bool>
+    where
+        UUID: AsRef<Uuid>,
+    {
+        let mut subscribers = self.subscribers.try_lock().ok()?;
+        Some(Self::remove_subscriber(&mut subscribers, uuid.as_ref()))
+    }
+
+    pub(super) fn remove_subscriber(
+        subscribers: &mut Vec<Arc<Subscriber<T>>>,
+        uuid: &Uuid,
+    ) -> bool {
         let index = subscribers
             .iter()
             .position(|subscriber| subscriber.uuid == *uuid);
@@ -138,47 +404,143 @@ where
         }
     }
 
-    pub async fn dispatch(&self, data: Arc<T>) -> Result<(), Vec<DispatchError<T>>> {
-        let mut errors = Vec::new();
+    pub async fn dispatch(&self, data: Arc<T>) -> DispatchReport<T> {
+        let _dispatching = self.dispatching.lock().await;
+        if self.is_closed() {
+            return DispatchReport::closed();
+        }
+
+        // Subscribers can subscribe and unsubscribe while the data is being dispatched
+        let subscribers = {
+            let subscribers = self.subscribers.lock().await;
+            if self.sticky {
+                *self.last_value.lock().await = Some(Arc::clone(&data));
+            }
+
+            subscribers.clone()
+        };
+
+        let mut report = DispatchReport::new();
         let mut subscribers_to_remove = Vec::new();
 
-        let mut subscribers = self.subscribers.lock().await;
-        for (index, subscriber) in subscribers.iter().enumerate() {
-            let data = Arc::clone(&data);
+        // Subscribers with the same priority are dispatched to concurrently, so a slow subscriber only delays those with a lower priority
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for group in subscribers.chunk_by(|a, b| a.priority == b.priority) {
+            let results = join_all(
+                group
+                    .iter()
+                    .map(|subscriber| subscriber.dispatch(Arc::clone(&data))),
+            )
+            .await;
 
-            let result = subscriber.dispatch(data).await;
-            if let Err(err) = result {
-                if subscriber.log_on_error {
-                    log::error!(
-                        "Event \"{}\" failed to dispatch data to subscriber {}: {}.",
-                        self.name,
-                        subscriber.name,
-                        err
-                    );
+            for (subscriber, result) in group.iter().zip(results) {
+                match result {
+                    Ok(()) => report.succeeded += 1,
+                    Err(err) => {
+                        if self.handle_dispatch_error(subscriber, &err) {
+                            subscribers_to_remove.push(subscriber.uuid);
+                        }
+
+                        report.failed.push((subscriber.info(), err));
+                    }
                 }
+            }
+        }
 
-                if subscriber.remove_on_error {
-                    if subscriber.log_on_error {
-                        log::error!("Subscriber will be unregistered from event.");
+        if !subscribers_to_remove.is_empty() {
+            self.subscribers
+                .lock()
+                .await
+                .retain(|subscriber| !subscribers_to_remove.contains(&subscriber.uuid));
+        }
+
+        report
+    }
+
+    // Like dispatch, but returns immediately instead of waiting for locks, full channels or async closures
+    pub fn try_dispatch(&self, data: Arc<T>) -> Result<DispatchReport<T>, TryDispatchError<T>> {
+        let _dispatching = self
+            .dispatching
+            .try_lock()
+            .map_err(|_| TryDispatchError::Busy)?;
+        if self.is_closed() {
+            return Err(TryDispatchError::Closed);
+        }
+
+        let subscribers = {
+            let subscribers = self
+                .subscribers
+                .try_lock()
+                .map_err(|_| TryDispatchError::Busy)?;
+            if self.sticky {
+                let mut last_value = self
+                    .last_value
+                    .try_lock()
+                    .map_err(|_| TryDispatchError::Busy)?;
+                *last_value = Some(Arc::clone(&data));
+            }
+
+            subscribers.clone()
+        };
+
+        let mut report = DispatchReport::new();
+        let mut subscribers_to_remove = Vec::new();
+        for subscriber in subscribers.iter() {
+            match subscriber.try_dispatch(Arc::clone(&data)) {
+                Ok(()) => report.succeeded += 1,
+                Err(err) => {
+                    if self.handle_dispatch_error(subscriber, &err) {
+                        subscribers_to_remove.push(subscriber.uuid);
                     }
 
-                    subscribers_to_remove.push(index);
+                    report.failed.push((subscriber.info(), err));
                 }
-
-                errors.push(err);
             }
         }
 
-        for index in subscribers_to_remove.into_iter().rev() {
-            subscribers.remove(index);
+        // Failing subscribers stay subscribed if the subscribers are locked at the moment
+        for uuid in subscribers_to_remove {
+            self.try_unsubscribe(&uuid);
         }
 
-        if errors.is_empty() {
-            Ok(())
+        if report.failed.is_empty() {
+            Ok(report)
         } else {
-            Err(errors)
+            Err(TryDispatchError::Failed(report))
         }
     }
+
+    // Rejects further dispatches and subscriptions and removes all subscribers. This drops the senders of channel subscribers,
+    // so loops over their receivers end once the remaining values are received. A running dispatch is finished first.
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let _dispatching = self.dispatching.lock().await;
+        self.subscribers.lock().await.clear();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // Logs the error and returns whether the subscriber has to be removed
+    fn handle_dispatch_error(&self, subscriber: &Subscriber<T>, err: &DispatchError<T>) -> bool {
+        if subscriber.log_on_error {
+            log::error!(
+                "Event \"{}\" failed to dispatch data to subscriber {}: {}.",
+                self.name,
+                subscriber.name,
+                err
+            );
+        }
+
+        let remove = subscriber.remove_on_error || subscriber.disconnects_on(err);
+        if remove && subscriber.log_on_error {
+            log::error!("Subscriber will be unregistered from event.");
+        }
+
+        remove
+    }
 }
 
 impl<T> PartialEq for Event<T>
@@ -200,6 +562,8 @@ where
         f.debug_struct(type_name::<Self>())
             .field("uuid", &self.uuid)
             .field("name", &self.name)
+            .field("sticky", &self.sticky)
+            .field("closed", &self.is_closed())
             .field("subscribers", &self.subscribers.blocking_lock().len())
             .finish()
     }
@@ -0,0 +1,195 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use tokio::sync::{broadcast, mpsc::Receiver, RwLock};
+use uuid::Uuid;
+
+use crate::service::{BoxedError, PinnedBoxedFutureResult};
+
+use super::{Backpressure, DispatchReport, Event, Handler, Subscriber, SubscriptionGuard};
+
+// Events by payload type, so modules can talk to each other without holding references to each other's events.
+// The event of a type is created when it is first used.
+#[derive(Default)]
+pub struct EventBus {
+    events: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn event<T>(&self) -> Arc<Event<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        if let Some(event) = self.events.read().await.get(&type_id) {
+            if let Ok(event) = Arc::clone(event).downcast() {
+                return event;
+            }
+        }
+
+        let mut events = self.events.write().await;
+        let event = events
+            .entry(type_id)
+            .or_insert_with(|| Arc::new(Event::<T>::new(type_name::<T>())));
+
+        // Only an Event<T> is ever stored for the TypeId of T
+        Arc::clone(event)
+            .downcast()
+            .unwrap_or_else(|_| unreachable!("EventBus stored an event of the wrong type"))
+    }
+
+    pub async fn publish<T>(&self, payload: T) -> DispatchReport<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.event::<T>().await.dispatch(Arc::new(payload)).await
+    }
+
+    pub async fn subscribe<T>(&self, subscriber: Subscriber<T>) -> SubscriptionGuard<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.event::<T>().await.subscribe(subscriber).await
+    }
+
+    pub async fn subscribe_channel<T, S>(
+        &self,
+        name: S,
+        buffer: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_channel(name, buffer, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_channel_with_backpressure<T, S>(
+        &self,
+        name: S,
+        buffer: usize,
+        backpressure: Backpressure,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, Receiver<Arc<T>>)
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_channel_with_backpressure(
+                name,
+                buffer,
+                backpressure,
+                log_on_error,
+                remove_on_error,
+            )
+            .await
+    }
+
+    pub async fn subscribe_broadcast<T, S>(
+        &self,
+        name: S,
+        capacity: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (SubscriptionGuard<T>, broadcast::Receiver<Arc<T>>)
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_broadcast(name, capacity, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_closure<T, S>(
+        &self,
+        name: S,
+        closure: impl Fn(Arc<T>) -> Result<(), BoxedError> + Send + Sync + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_closure(name, closure, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_async_closure<T, S>(
+        &self,
+        name: S,
+        closure: impl Fn(Arc<T>) -> PinnedBoxedFutureResult<()> + Send + Sync + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_async_closure(name, closure, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_closure_mut<T, S>(
+        &self,
+        name: S,
+        closure: impl FnMut(Arc<T>) -> Result<(), BoxedError> + Send + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_closure_mut(name, closure, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_handler<T, S>(
+        &self,
+        name: S,
+        handler: impl Handler<T> + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<T>
+    where
+        T: Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.event::<T>()
+            .await
+            .subscribe_handler(name, handler, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn unsubscribe<T, UUID>(&self, uuid: &UUID) -> bool
+    where
+        T: Send + Sync + 'static,
+        UUID: AsRef<Uuid>,
+    {
+        self.event::<T>().await.unsubscribe(uuid).await
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{Callback, Event, Subscriber, SubscriptionGuard};
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+
+    #[error("Unable to serialize event: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("The journal file lock is poisoned")]
+    Poisoned,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntry<T> {
+    pub event: String,
+    pub timestamp: String,
+    pub payload: T,
+}
+
+// Same layout as JournalEntry, so recording does not need to clone the payload
+#[derive(Serialize)]
+struct BorrowedJournalEntry<'a, T> {
+    event: &'a str,
+    timestamp: String,
+    payload: &'a T,
+}
+
+// Persists dispatched events as JSON lines to an append-only file, e.g. to audit moderation actions or to see what happened before a crash.
+// Every line is written before the next event is recorded, so at most the line that was being written when the bot crashed is lost.
+pub struct EventJournal<T>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    path: PathBuf,
+    file: Mutex<File>,
+    payload: PhantomData<fn() -> T>,
+}
+
+impl<T> EventJournal<T>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    pub fn open(path: &Path) -> Result<Arc<Self>, JournalError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Arc::new(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            payload: PhantomData,
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, event_name: &str, payload: &T) -> Result<(), JournalError> {
+        let entry = BorrowedJournalEntry {
+            event: event_name,
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            payload,
+        };
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().map_err(|_| JournalError::Poisoned)?;
+        file.write_all(&line)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    // Records every value dispatched by the event. The journal subscribes with the highest priority,
+    // so an event is on disk before any other subscriber reacts to it.
+    pub async fn attach(self: &Arc<Self>, event: &Event<T>) -> SubscriptionGuard<T> {
+        let journal = Arc::clone(self);
+        let event_name = event.name.clone();
+        let subscriber = Subscriber::new(
+            format!("event_journal_{}", self.path.display()),
+            true,
+            false,
+            Callback::Closure(Box::new(move |payload| {
+                journal.record(&event_name, &payload)?;
+                Ok(())
+            })),
+        )
+        .with_priority(i32::MAX);
+
+        event.subscribe(subscriber).await
+    }
+
+    // Lines that can't be parsed, like one cut off by a crash, are skipped
+    pub fn entries(&self) -> Result<Vec<JournalEntry<T>>, JournalError> {
+        let reader = BufReader::new(File::open(&self.path)?);
+
+        let mut entries = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!(
+                    "Skipping line {} of event journal {}: {}",
+                    index + 1,
+                    self.path.display(),
+                    err
+                ),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // Dispatches the recorded payloads again, in the order they were recorded. Entries of other events in the same journal are included.
+    // The event must not be attached to this journal, or the replayed entries are recorded again.
+    pub async fn replay(&self, event: &Event<T>) -> Result<usize, JournalError> {
+        let entries = self.entries()?;
+        let count = entries.len();
+
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for entry in entries {
+            let _ = event.dispatch(Arc::new(entry.payload)).await;
+        }
+
+        Ok(count)
+    }
+}
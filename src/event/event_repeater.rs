@@ -1,14 +1,19 @@
-use log::error;
+use log::{error, warn};
 use std::{
     collections::HashMap,
     sync::{Arc, OnceLock, Weak},
+    time::Duration,
 };
 use thiserror::Error;
-use tokio::{sync::Mutex, task::JoinHandle};
+use tokio::{sync::Mutex, task::JoinHandle, time::timeout};
 use uuid::Uuid;
 
 use super::Event;
 
+// A subscriber of the repeater that does not take values anymore would otherwise keep the detaching caller waiting
+//TODO: Add to config instead of hardcoding duration
+const DETACH_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Error)]
 pub enum AttachError {
     #[error("Tried to attach event {event_name} to EventRepeater {repeater_name} while it was uninitialized. Did you not use EventRepeater<T>::new()?")]
@@ -91,6 +96,32 @@ where
     }
 
     pub async fn attach(&self, event: &Event<T>, buffer: usize) -> Result<(), AttachError> {
+        self.attach_with(event, buffer, |value| value).await
+    }
+
+    // Repeats the values of an event of another type, e.g. to add where a value came from
+    pub async fn attach_mapped<A>(
+        &self,
+        event: &Event<A>,
+        buffer: usize,
+        map: impl Fn(&A) -> T + Send + 'static,
+    ) -> Result<(), AttachError>
+    where
+        A: Send + Sync + 'static,
+    {
+        self.attach_with(event, buffer, move |value| Arc::new(map(&value)))
+            .await
+    }
+
+    async fn attach_with<A>(
+        &self,
+        event: &Event<A>,
+        buffer: usize,
+        map: impl Fn(Arc<A>) -> Arc<T> + Send + 'static,
+    ) -> Result<(), AttachError>
+    where
+        A: Send + Sync + 'static,
+    {
         let weak = match self.weak.get() {
             Some(weak) => weak,
             None => {
@@ -118,24 +149,29 @@ where
             });
         }
 
-        let (uuid, mut receiver) = event
+        let (subscription, mut receiver) = event
             .subscribe_channel(&self.event.name, buffer, true, true)
             .await;
 
+        // Ends once the repeater is detached from the event or the event is closed
         let join_handle = tokio::spawn(async move {
             while let Some(value) = receiver.recv().await {
-                let _ = arc.event.dispatch(value).await;
+                let _ = arc.event.dispatch(map(value)).await;
             }
         });
-        subscriptions.insert(event.uuid, (uuid, join_handle));
+        // The attached events have different types, so the subscriptions are kept by Uuid and ended in detach
+        subscriptions.insert(event.uuid, (subscription.detach(), join_handle));
 
         Ok(())
     }
 
-    pub async fn detach(&self, event: &Event<T>) -> Result<(), DetachError> {
+    pub async fn detach<A>(&self, event: &Event<A>) -> Result<(), DetachError>
+    where
+        A: Send + Sync + 'static,
+    {
         let mut subscriptions = self.subscriptions.lock().await;
 
-        let subscription = match subscriptions.remove(&event.uuid) {
+        let (uuid, mut join_handle) = match subscriptions.remove(&event.uuid) {
             Some(subscription) => subscription,
             None => {
                 return Err(DetachError::NotAttached {
@@ -144,7 +180,27 @@ where
                 })
             }
         };
-        subscription.1.abort();
+
+        drop(subscriptions);
+
+        // Values that were already received are still repeated before the task ends
+        event.unsubscribe(&uuid).await;
+        match timeout(DETACH_TIMEOUT, &mut join_handle).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => error!(
+                "EventRepeater {} failed to finish repeating event {}: {}",
+                self.event.name, event.name, err
+            ),
+            Err(_) => {
+                warn!(
+                    "EventRepeater {} did not finish repeating event {} within {}. Aborting it.",
+                    self.event.name,
+                    event.name,
+                    humantime::format_duration(DETACH_TIMEOUT)
+                );
+                join_handle.abort();
+            }
+        }
 
         Ok(())
     }
@@ -156,6 +212,8 @@ where
             return Err(CloseError::AttachedEvents(self));
         }
 
+        self.event.close().await;
+
         Ok(())
     }
 }
@@ -1,8 +1,8 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::SystemTime};
 
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc::Receiver, Mutex, MutexGuard};
 
-use super::{DispatchError, Event};
+use super::{DispatchReport, Event};
 
 #[derive(Debug)]
 pub enum ObservableResult<T>
@@ -10,7 +10,7 @@ where
     T: Send + Sync + 'static,
 {
     Unchanged,
-    Changed(Result<(), Vec<DispatchError<T>>>),
+    Changed(DispatchReport<T>),
 }
 
 #[derive(Debug)]
@@ -19,6 +19,7 @@ where
     T: Send + Sync + 'static + Clone + PartialEq, //TODO: Try out if we can remove Sync here
 {
     value: Mutex<T>,
+    last_changed: Mutex<SystemTime>,
     on_change: Event<T>,
 }
 
@@ -32,32 +33,107 @@ where
     {
         Self {
             value: Mutex::new(value),
+            last_changed: Mutex::new(SystemTime::now()),
             on_change: Event::new(event_name),
         }
     }
 
+    // Subscribers that subscribe after a change still get the current value, see Event::with_sticky
+    pub fn with_sticky(mut self, sticky: bool) -> Self {
+        self.on_change = self.on_change.with_sticky(sticky);
+        self
+    }
+
     pub async fn get(&self) -> T {
         let lock = self.value.lock().await;
         lock.clone()
     }
 
+    pub async fn last_changed(&self) -> SystemTime {
+        *self.last_changed.lock().await
+    }
+
     pub async fn set(&self, value: T) -> ObservableResult<T> {
         let mut lock = self.value.lock().await;
-        let current_value = lock.clone();
+        self.replace(&mut lock, value).await
+    }
+
+    // The value can't change between reading and writing it, unlike with get and set
+    pub async fn update(&self, update: impl FnOnce(&T) -> T) -> ObservableResult<T> {
+        let mut lock = self.value.lock().await;
+        let value = update(&lock);
+        self.replace(&mut lock, value).await
+    }
+
+    // Like update, but the value stays locked until the future has completed
+    pub async fn update_async<F, Fut>(&self, update: F) -> ObservableResult<T>
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut lock = self.value.lock().await;
+        let value = update(lock.clone()).await;
+        self.replace(&mut lock, value).await
+    }
+
+    // Resolves with the value the observable changes to next. None if the event stopped delivering changes.
+    pub async fn changed(&self) -> Option<T> {
+        let mut receiver = {
+            let lock = self.value.lock().await;
+            self.subscribe_changes(&lock).await
+        };
 
-        if current_value == value {
+        receiver.recv().await.map(|value| (*value).clone())
+    }
+
+    // Resolves immediately if the current value already matches
+    pub async fn wait_for(&self, predicate: impl Fn(&T) -> bool) -> Option<T> {
+        let mut receiver = {
+            let lock = self.value.lock().await;
+            if predicate(&lock) {
+                return Some(lock.clone());
+            }
+
+            self.subscribe_changes(&lock).await
+        };
+
+        while let Some(value) = receiver.recv().await {
+            if predicate(&value) {
+                return Some((*value).clone());
+            }
+        }
+
+        None
+    }
+
+    // Takes the lock so no change can happen while subscribing. The only value a sticky event can replay then is the current one, which is skipped.
+    // The subscription is detached, as dropping the receiver is enough to unsubscribe. The subscriber is removed on the next failed dispatch.
+    async fn subscribe_changes(&self, _lock: &MutexGuard<'_, T>) -> Receiver<Arc<T>> {
+        let replays = self.on_change.is_sticky() && self.on_change.last_value().await.is_some();
+        let name = format!("{}_waiter", self.on_change.name);
+        let (subscription, mut receiver) =
+            self.on_change.subscribe_channel(name, 4, false, true).await;
+        subscription.detach();
+
+        if replays {
+            receiver.recv().await;
+        }
+
+        receiver
+    }
+
+    async fn replace(&self, lock: &mut MutexGuard<'_, T>, value: T) -> ObservableResult<T> {
+        if **lock == value {
             return ObservableResult::Unchanged;
         }
 
-        *lock = value.clone();
+        **lock = value.clone();
+        *self.last_changed.lock().await = SystemTime::now();
 
         let value = Arc::new(value);
-        let dispatch_result = self.on_change.dispatch(value).await;
+        let report = self.on_change.dispatch(value).await;
 
-        match dispatch_result {
-            Ok(_) => ObservableResult::Changed(Ok(())),
-            Err(errors) => ObservableResult::Changed(Err(errors)),
-        }
+        ObservableResult::Changed(report)
     }
 }
 
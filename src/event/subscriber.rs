@@ -1,18 +1,67 @@
-use std::sync::Arc;
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    sync::Arc,
+};
 
+use async_trait::async_trait;
+use log::error;
 use thiserror::Error;
-use tokio::sync::mpsc::{error::SendError, Sender};
+use tokio::{
+    runtime::Handle,
+    spawn,
+    sync::{
+        broadcast,
+        mpsc::{
+            error::{SendError, TrySendError},
+            Sender,
+        },
+        Mutex,
+    },
+};
 use uuid::Uuid;
 
 use crate::service::{BoxedError, PinnedBoxedFutureResult};
 
+// For subscribers that keep state between dispatches, without having to lock it themselves
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+pub trait Handler<T>: Send
+where
+    T: Send + Sync + 'static,
+{
+    async fn handle(&mut self, data: Arc<T>) -> Result<(), BoxedError>;
+}
+
+pub type MutableClosure<T> =
+    std::sync::Mutex<Box<dyn FnMut(Arc<T>) -> Result<(), BoxedError> + Send>>;
+
 pub enum Callback<T>
 where
     T: Send + Sync + 'static,
 {
     Channel(Sender<Arc<T>>),
+    // One subscriber for any number of receivers. Receivers that fall behind skip the oldest values instead of slowing down dispatch.
+    Broadcast(broadcast::Sender<Arc<T>>),
     Closure(Box<dyn Fn(Arc<T>) -> Result<(), BoxedError> + Send + Sync>),
     AsyncClosure(Box<dyn Fn(Arc<T>) -> PinnedBoxedFutureResult<()> + Send + Sync>),
+    // Stateful callbacks are locked while they handle a value, so they never run twice at once
+    ClosureMut(MutableClosure<T>),
+    Handler(Arc<Mutex<Box<dyn Handler<T>>>>),
+}
+
+// What a channel subscriber does when its buffer is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    // Dispatch waits until there is space, which also delays all subscribers after this one
+    #[default]
+    Block,
+    DropNewest,
+    // A Sender can't take values back out of its channel, so Event::subscribe_channel_with_backpressure relays through a broadcast channel for this.
+    // A Channel callback with this policy drops the newest value instead.
+    DropOldest,
+    // The subscriber is removed, which closes its channel once the receiver has read the remaining values
+    Disconnect,
 }
 
 #[derive(Debug, Error)]
@@ -23,11 +72,34 @@ where
     #[error("Failed to send data to channel: {0}")]
     ChannelSend(#[from] SendError<Arc<T>>),
 
+    #[error("Channel buffer is full")]
+    ChannelFull,
+
+    #[error("Failed to send data to broadcast channel: {0}")]
+    BroadcastSend(#[from] broadcast::error::SendError<Arc<T>>),
+
     #[error("Failed to dispatch data to closure: {0}")]
     Closure(BoxedError),
 
     #[error("Failed to dispatch data to async closure: {0}")]
     AsyncClosure(BoxedError),
+
+    #[error("Failed to dispatch data to handler: {0}")]
+    Handler(BoxedError),
+}
+
+// Identifies a subscriber without holding on to its callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberInfo {
+    pub name: String,
+    pub uuid: Uuid,
+    pub priority: i32,
+}
+
+impl Display for SubscriberInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.uuid)
+    }
 }
 
 pub struct Subscriber<T>
@@ -38,6 +110,9 @@ where
     pub log_on_error: bool,
     pub remove_on_error: bool,
     pub callback: Callback<T>,
+    // Subscribers with a higher priority are dispatched to first. Subscribers with the same priority are dispatched to in the order they subscribed.
+    pub priority: i32,
+    pub backpressure: Backpressure,
 
     pub uuid: Uuid,
 }
@@ -55,19 +130,128 @@ where
             log_on_error,
             remove_on_error,
             callback,
+            priority: 0,
+            backpressure: Backpressure::default(),
             uuid: Uuid::new_v4(),
         }
     }
 
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    pub fn info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            name: self.name.clone(),
+            uuid: self.uuid,
+            priority: self.priority,
+        }
+    }
+
+    // Whether the subscriber has to be removed after this error, even if it is not removed on errors
+    pub fn disconnects_on(&self, error: &DispatchError<T>) -> bool {
+        self.backpressure == Backpressure::Disconnect && matches!(error, DispatchError::ChannelFull)
+    }
+
     pub async fn dispatch(&self, data: Arc<T>) -> Result<(), DispatchError<T>> {
         match &self.callback {
-            Callback::Channel(sender) => {
-                sender.send(data).await.map_err(DispatchError::ChannelSend)
-            }
+            Callback::Channel(sender) => match self.backpressure {
+                Backpressure::Block => sender.send(data).await.map_err(DispatchError::ChannelSend),
+                _ => self.try_send(sender, data),
+            },
+            Callback::Broadcast(sender) => sender
+                .send(data)
+                .map(|_| ())
+                .map_err(DispatchError::BroadcastSend),
             Callback::Closure(closure) => closure(data).map_err(DispatchError::Closure),
             Callback::AsyncClosure(closure) => {
                 closure(data).await.map_err(DispatchError::AsyncClosure)
             }
+            Callback::ClosureMut(closure) => Self::call_mut(closure, data),
+            Callback::Handler(handler) => {
+                // Spawned because the futures of handlers are not Sync, but dispatching has to be
+                let handler = Arc::clone(handler);
+                spawn(async move { handler.lock().await.handle(data).await })
+                    .await
+                    .map_err(|err| DispatchError::Handler(Box::new(err)))?
+                    .map_err(DispatchError::Handler)
+            }
+        }
+    }
+
+    // Never waits, so it can be used where awaiting is not possible. Async closures and handlers are spawned, so their errors are only logged.
+    pub fn try_dispatch(&self, data: Arc<T>) -> Result<(), DispatchError<T>> {
+        match &self.callback {
+            Callback::Channel(sender) => self.try_send(sender, data),
+            Callback::Broadcast(sender) => sender
+                .send(data)
+                .map(|_| ())
+                .map_err(DispatchError::BroadcastSend),
+            Callback::Closure(closure) => closure(data).map_err(DispatchError::Closure),
+            Callback::AsyncClosure(closure) => {
+                let handle = Handle::try_current()
+                    .map_err(|err| DispatchError::AsyncClosure(Box::new(err)))?;
+
+                self.spawn_logged(&handle, closure(data));
+                Ok(())
+            }
+            Callback::ClosureMut(closure) => Self::call_mut(closure, data),
+            Callback::Handler(handler) => {
+                let handle =
+                    Handle::try_current().map_err(|err| DispatchError::Handler(Box::new(err)))?;
+
+                let handler = Arc::clone(handler);
+                self.spawn_logged(
+                    &handle,
+                    async move { handler.lock().await.handle(data).await },
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn spawn_logged(
+        &self,
+        handle: &Handle,
+        future: impl Future<Output = Result<(), BoxedError>> + Send + 'static,
+    ) {
+        let name = self.name.clone();
+        let log_on_error = self.log_on_error;
+        handle.spawn(async move {
+            if let Err(err) = future.await {
+                if log_on_error {
+                    error!(
+                        "Subscriber {} failed to handle dispatched data: {}",
+                        name, err
+                    );
+                }
+            }
+        });
+    }
+
+    // A closure that panicked may have left its state half updated, but it is still called, like a Fn closure would be
+    fn call_mut(closure: &MutableClosure<T>, data: Arc<T>) -> Result<(), DispatchError<T>> {
+        let mut closure = closure
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        closure(data).map_err(DispatchError::Closure)
+    }
+
+    // A full buffer is only an error if the subscriber would otherwise have to wait
+    fn try_send(&self, sender: &Sender<Arc<T>>, data: Arc<T>) -> Result<(), DispatchError<T>> {
+        match sender.try_send(data) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => match self.backpressure {
+                Backpressure::Block | Backpressure::Disconnect => Err(DispatchError::ChannelFull),
+                Backpressure::DropNewest | Backpressure::DropOldest => Ok(()),
+            },
+            Err(TrySendError::Closed(data)) => Err(DispatchError::ChannelSend(SendError(data))),
         }
     }
 }
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Weak};
+
+use log::warn;
+use tokio::{runtime::Handle, sync::Mutex};
+use uuid::Uuid;
+
+use super::{event::Subscribers, Event, Subscriber};
+
+// Unsubscribes from the event when dropped, unless it is detached. It does not keep the event alive.
+#[must_use = "the subscription ends when the guard is dropped, use detach to keep it"]
+pub struct SubscriptionGuard<T>
+where
+    T: Send + Sync + 'static,
+{
+    subscribers: Weak<Mutex<Vec<Arc<Subscriber<T>>>>>,
+    event_name: String,
+    uuid: Uuid,
+    detached: bool,
+}
+
+impl<T> SubscriptionGuard<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub(super) fn new(subscribers: &Subscribers<T>, event_name: &str, uuid: Uuid) -> Self {
+        Self {
+            subscribers: Arc::downgrade(subscribers),
+            event_name: event_name.to_string(),
+            uuid,
+            detached: false,
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    // Keeps the subscription for as long as the event exists, or until it is unsubscribed with the returned Uuid
+    pub fn detach(mut self) -> Uuid {
+        self.detached = true;
+        self.uuid
+    }
+}
+
+impl<T> AsRef<Uuid> for SubscriptionGuard<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn as_ref(&self) -> &Uuid {
+        &self.uuid
+    }
+}
+
+impl<T> Drop for SubscriptionGuard<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if self.detached {
+            return;
+        }
+
+        let subscribers = match self.subscribers.upgrade() {
+            Some(subscribers) => subscribers,
+            None => return,
+        };
+
+        if let Ok(mut subscribers) = subscribers.try_lock() {
+            Event::remove_subscriber(&mut subscribers, &self.uuid);
+            return;
+        }
+
+        // The subscribers are locked by someone else, so unsubscribing has to wait for them
+        let uuid = self.uuid;
+        match Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    Event::remove_subscriber(&mut *subscribers.lock().await, &uuid);
+                });
+            }
+            Err(_) => warn!(
+                "Unable to unsubscribe {} from event {} on drop, because there is no Tokio runtime",
+                uuid, self.event_name
+            ),
+        }
+    }
+}
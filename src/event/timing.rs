@@ -0,0 +1,92 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    select, spawn,
+    sync::mpsc::{channel, Receiver},
+    time::{sleep, sleep_until, Instant},
+};
+
+// Forwards a value once no other value was received for the given delay, so a burst of values only results in its last one
+pub fn debounce<T>(mut input: Receiver<Arc<T>>, delay: Duration, buffer: usize) -> Receiver<Arc<T>>
+where
+    T: Send + Sync + 'static,
+{
+    let (sender, output) = channel(buffer);
+
+    spawn(async move {
+        while let Some(mut latest) = input.recv().await {
+            let mut closed = false;
+            loop {
+                select! {
+                    value = input.recv() => match value {
+                        Some(value) => latest = value,
+                        None => {
+                            closed = true;
+                            break;
+                        }
+                    },
+                    _ = sleep(delay) => break,
+                }
+            }
+
+            if sender.send(latest).await.is_err() || closed {
+                break;
+            }
+        }
+    });
+
+    output
+}
+
+// Forwards at most one value per interval. Of the values received during an interval, the last one is forwarded when it is over.
+pub fn throttle<T>(
+    mut input: Receiver<Arc<T>>,
+    interval: Duration,
+    buffer: usize,
+) -> Receiver<Arc<T>>
+where
+    T: Send + Sync + 'static,
+{
+    let (sender, output) = channel(buffer);
+
+    spawn(async move {
+        let mut trailing = None;
+        loop {
+            let value = match trailing.take() {
+                Some(value) => value,
+                None => match input.recv().await {
+                    Some(value) => value,
+                    None => break,
+                },
+            };
+
+            if sender.send(value).await.is_err() {
+                break;
+            }
+
+            let deadline = Instant::now() + interval;
+            let mut closed = false;
+            loop {
+                select! {
+                    value = input.recv() => match value {
+                        Some(value) => trailing = Some(value),
+                        None => {
+                            closed = true;
+                            break;
+                        }
+                    },
+                    _ = sleep_until(deadline) => break,
+                }
+            }
+
+            if closed {
+                if let Some(value) = trailing.take() {
+                    let _ = sender.send(value).await;
+                }
+                break;
+            }
+        }
+    });
+
+    output
+}
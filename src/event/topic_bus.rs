@@ -0,0 +1,175 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{mpsc::Receiver, RwLock};
+use uuid::Uuid;
+
+use crate::service::{BoxedError, PinnedBoxedFutureResult};
+
+use super::{DispatchReport, Event, SubscriptionGuard};
+
+pub const TOPIC_SEPARATOR: char = '.';
+// Matches exactly one segment of a topic
+pub const SINGLE_WILDCARD: &str = "*";
+// Matches any number of segments, including none
+pub const MULTI_WILDCARD: &str = "**";
+
+#[derive(Debug)]
+pub struct TopicMessage<T> {
+    pub topic: String,
+    pub payload: T,
+}
+
+pub fn matches(pattern: &str, topic: &str) -> bool {
+    let pattern = pattern.split(TOPIC_SEPARATOR).collect::<Vec<_>>();
+    let topic = topic.split(TOPIC_SEPARATOR).collect::<Vec<_>>();
+
+    matches_segments(&pattern, &topic)
+}
+
+fn matches_segments(pattern: &[&str], topic: &[&str]) -> bool {
+    match (pattern.first(), topic.first()) {
+        (None, None) => true,
+        (Some(&MULTI_WILDCARD), _) => {
+            matches_segments(&pattern[1..], topic)
+                || (!topic.is_empty() && matches_segments(pattern, &topic[1..]))
+        }
+        (Some(segment), Some(topic_segment)) => {
+            (*segment == SINGLE_WILDCARD || segment == topic_segment)
+                && matches_segments(&pattern[1..], &topic[1..])
+        }
+        _ => false,
+    }
+}
+
+// Events named by dot-separated topics like "service.discord.status", which can be subscribed to with patterns like "service.*.status".
+// There is one event per pattern, so all subscribers of a pattern share it.
+pub struct TopicBus<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub name: String,
+    events: RwLock<HashMap<String, Arc<Event<TopicMessage<T>>>>>,
+}
+
+impl<T> TopicBus<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            events: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn event(&self, pattern: &str) -> Arc<Event<TopicMessage<T>>> {
+        if let Some(event) = self.events.read().await.get(pattern) {
+            return Arc::clone(event);
+        }
+
+        let mut events = self.events.write().await;
+        let event = events
+            .entry(pattern.to_string())
+            .or_insert_with(|| Arc::new(Event::new(format!("{}_{}", self.name, pattern))));
+
+        Arc::clone(event)
+    }
+
+    pub async fn patterns(&self) -> Vec<String> {
+        self.events.read().await.keys().cloned().collect()
+    }
+
+    pub async fn publish(&self, topic: &str, payload: T) -> DispatchReport<TopicMessage<T>> {
+        let message = Arc::new(TopicMessage {
+            topic: topic.to_string(),
+            payload,
+        });
+
+        let events = self
+            .events
+            .read()
+            .await
+            .iter()
+            .filter(|(pattern, _)| matches(pattern, topic))
+            .map(|(_, event)| Arc::clone(event))
+            .collect::<Vec<_>>();
+
+        let mut report = DispatchReport::new();
+
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for event in events {
+            report.append(event.dispatch(Arc::clone(&message)).await);
+        }
+
+        report
+    }
+
+    pub async fn subscribe_channel<S>(
+        &self,
+        pattern: &str,
+        name: S,
+        buffer: usize,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> (
+        SubscriptionGuard<TopicMessage<T>>,
+        Receiver<Arc<TopicMessage<T>>>,
+    )
+    where
+        S: Into<String>,
+    {
+        self.event(pattern)
+            .await
+            .subscribe_channel(name, buffer, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_closure<S>(
+        &self,
+        pattern: &str,
+        name: S,
+        closure: impl Fn(Arc<TopicMessage<T>>) -> Result<(), BoxedError> + Send + Sync + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<TopicMessage<T>>
+    where
+        S: Into<String>,
+    {
+        self.event(pattern)
+            .await
+            .subscribe_closure(name, closure, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn subscribe_async_closure<S>(
+        &self,
+        pattern: &str,
+        name: S,
+        closure: impl Fn(Arc<TopicMessage<T>>) -> PinnedBoxedFutureResult<()> + Send + Sync + 'static,
+        log_on_error: bool,
+        remove_on_error: bool,
+    ) -> SubscriptionGuard<TopicMessage<T>>
+    where
+        S: Into<String>,
+    {
+        self.event(pattern)
+            .await
+            .subscribe_async_closure(name, closure, log_on_error, remove_on_error)
+            .await
+    }
+
+    pub async fn unsubscribe<UUID>(&self, pattern: &str, uuid: &UUID) -> bool
+    where
+        UUID: AsRef<Uuid>,
+    {
+        let event = match self.events.read().await.get(pattern) {
+            Some(event) => Arc::clone(event),
+            None => return false,
+        };
+
+        event.unsubscribe(uuid).await
+    }
+}
@@ -1,12 +1,17 @@
-use crate::service::OverallStatus;
-use ::log::{error, info};
-use bot::Bot;
-use std::time::SystemTime;
+use crate::{run_report::RunReport, service::OverallStatus};
+use ::log::{error, info, warn};
+use bot::{Bot, ExitReason};
+use std::time::{Duration, SystemTime};
 
 pub mod bot;
+pub mod cli;
 pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod discord;
 pub mod event;
 pub mod log;
+pub mod run_report;
 pub mod service;
 
 pub fn is_debug() -> bool {
@@ -19,6 +24,14 @@ pub async fn run(mut bot: Bot) {
         return;
     }
 
+    if let Some(run_report_path) = &bot.run_report_path {
+        match RunReport::load(run_report_path) {
+            Ok(Some(previous_run_report)) => previous_run_report.log_comparison(),
+            Ok(None) => {}
+            Err(error) => warn!("Unable to load report of previous run: {}", error),
+        }
+    }
+
     let now = SystemTime::now();
     bot.start().await;
     match now.elapsed() {
@@ -32,19 +45,36 @@ pub async fn run(mut bot: Bot) {
         }
     };
 
-    if bot.service_manager.overall_status().await != OverallStatus::Healthy {
+    //TODO: Add to config instead of hardcoding duration
+    let overall_readiness = bot
+        .service_manager
+        .wait_for_readiness(Duration::from_secs(30))
+        .await;
+
+    if overall_readiness == OverallStatus::Unhealthy {
         let status_overview = bot.service_manager.status_overview().await;
 
-        error!("{} is not healthy! Some essential services did not start up successfully. {} will now exit ungracefully.\n\n{}",
+        error!("{} is not healthy! Some essential services did not start up successfully or did not become ready in time. {} will now exit ungracefully.\n\n{}",
         bot.name,
         bot.name,
         status_overview);
+
+        save_run_report(&bot, Some(ExitReason::StartupFailed)).await;
         return;
     }
 
+    if overall_readiness == OverallStatus::Degraded {
+        let status_overview = bot.service_manager.status_overview().await;
+
+        warn!(
+            "{} is degraded! Some optional services did not start up successfully. {} will keep running without them.\n\n{}",
+            bot.name, bot.name, status_overview
+        );
+    }
+
     info!("{} is alive", bot.name,);
 
-    //TODO: Add CLI commands
+    save_run_report(&bot, None).await;
 
     let exit_reason = bot.join().await;
     match exit_reason {
@@ -59,8 +89,30 @@ pub async fn run(mut bot: Bot) {
                 status_overview
             );
         }
+        bot::ExitReason::StartupFailed => error!(
+            "{} failed to start up! Attempting to shut down gracefully.",
+            bot.name
+        ),
+        bot::ExitReason::ShutdownRequested => info!(
+            "A shutdown of {} was requested! Attempting to shut down gracefully.",
+            bot.name
+        ),
     }
 
     bot.stop().await;
+    save_run_report(&bot, Some(exit_reason)).await;
+
     info!("Oyasumi 💤");
 }
+
+async fn save_run_report(bot: &Bot, exit_reason: Option<ExitReason>) {
+    let run_report_path = match &bot.run_report_path {
+        Some(run_report_path) => run_report_path,
+        None => return,
+    };
+
+    let run_report = RunReport::new(exit_reason, bot.service_manager.status_snapshot().await);
+    if let Err(error) = run_report.save(run_report_path) {
+        warn!("Unable to save run report: {}", error);
+    }
+}
@@ -1,51 +1,517 @@
-use fern::colors::{Color, ColoredLevelConfig};
-use log::{LevelFilter, SetLoggerError};
+use log::LevelFilter;
 use std::{
-    io,
-    sync::atomic::{AtomicBool, Ordering},
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Write as _},
+    fs::OpenOptions,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, RwLock, RwLockReadGuard,
+    },
     time::SystemTime,
 };
+use thiserror::Error;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Metadata, Subscriber,
+};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::{
+    filter,
+    fmt::{
+        format::Writer, writer::BoxMakeWriter, FmtContext, FormatEvent, FormatFields,
+        FormattedFields,
+    },
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::{SubscriberInitExt, TryInitError},
+    Layer, Registry,
+};
 
 use crate::is_debug;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod capture;
+pub mod system_log;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use capture::{test_setup, CapturedRecord, LogCapture};
+pub use system_log::{SystemLog, UnknownSystemLog};
+
+// Records of the Discord log service itself are not forwarded, so failing to send them can not cause a feedback loop
+pub const DISCORD_LOG_TARGET: &str = "lum::service::discord_log";
+
+const FORWARDED_RECORDS_CAPACITY: usize = 200;
+
+// Dependencies that are too chatty below warnings. Levels from the config are applied on top of these.
+pub const DEFAULT_TARGET_LEVELS: &[(&str, LevelFilter)] = &[
+    ("serenity", LevelFilter::Warn),
+    ("hyper", LevelFilter::Warn),
+    ("tracing", LevelFilter::Warn),
+    ("reqwest", LevelFilter::Warn),
+    ("tungstenite", LevelFilter::Warn),
+    ("rustyline", LevelFilter::Warn),
+];
+
 static IS_LOGGER_SET_UP: AtomicBool = AtomicBool::new(false);
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+static FORWARDED_RECORDS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static LAYERS: Mutex<Vec<BoxedLayer>> = Mutex::new(Vec::new());
+static PRINTER: Mutex<Option<Printer>> = Mutex::new(None);
+static LEVELS: RwLock<Levels> = RwLock::new(Levels {
+    level: LevelFilter::Info,
+    configured: BTreeMap::new(),
+    overrides: BTreeMap::new(),
+    targets: BTreeMap::new(),
+});
+
+// Records of a target are filtered by the level of the most specific target they belong to, or by the overall level
+struct Levels {
+    level: LevelFilter,
+    // Defaults and levels from the config
+    configured: BTreeMap<String, LevelFilter>,
+    // Set at runtime, e.g. from the admin console. They are kept when the config is reloaded.
+    overrides: BTreeMap<String, LevelFilter>,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl Levels {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(name, _)| belongs_to(target, name))
+            .max_by_key(|(name, _)| name.len())
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    // log's max level is only a fast path for disabled records, so it has to let through what any target allows
+    fn max_level(&self) -> LevelFilter {
+        self.targets.values().copied().fold(self.level, Ord::max)
+    }
+}
+
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+pub type Printer = Box<dyn FnMut(String) + Send>;
 
 pub fn is_set_up() -> bool {
     IS_LOGGER_SET_UP.load(Ordering::Relaxed)
 }
 
-pub fn setup() -> Result<(), SetLoggerError> {
-    let colors = ColoredLevelConfig::new()
-        .info(Color::Green)
-        .debug(Color::Magenta)
-        .warn(Color::Yellow)
-        .error(Color::Red)
-        .trace(Color::Cyan);
-
-    fern::Dispatch::new()
-        .format(move |out, message, record| {
-            out.finish(format_args!(
-                "[{} {: <30} {: <5}] {}",
-                humantime::format_rfc3339_seconds(SystemTime::now()),
-                record.target(),
-                colors.color(record.level()),
-                message
-            ))
-        })
-        .level(get_min_log_level())
-        .level_for("serenity", LevelFilter::Warn)
-        .level_for("hyper", LevelFilter::Warn)
-        .level_for("tracing", LevelFilter::Warn)
-        .level_for("reqwest", LevelFilter::Warn)
-        .level_for("tungstenite", LevelFilter::Warn)
-        .chain(io::stdout())
-        .apply()?;
+#[derive(Debug, Error)]
+pub enum SetupError {
+    #[error("Unable to open log file {0}: {1}")]
+    File(PathBuf, io::Error),
+
+    #[error("{0}")]
+    Logger(#[from] TryInitError),
+
+    #[error("Layers have to be added before the logger is set up")]
+    AlreadySetUp,
+
+    #[error("Unable to log to {0}: {1}")]
+    SystemLog(SystemLog, String),
+
+    #[error("Logging to {0} needs the {0} feature and a Unix system")]
+    SystemLogUnavailable(SystemLog),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogOptions {
+    pub level: Option<LevelFilter>,
+    // Colors are only used when stdout is a terminal and NO_COLOR is not set
+    pub colors: bool,
+    // Logs are appended to this file instead of being printed to stdout
+    pub file: Option<PathBuf>,
+    // Takes precedence over both stdout and the file
+    pub system_log: Option<SystemLog>,
+    // Name the system log shows as the source of the logs
+    pub identifier: String,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            level: None,
+            colors: true,
+            file: None,
+            system_log: None,
+            identifier: String::from("lum"),
+        }
+    }
+}
+
+// Adds a tracing layer, like an OpenTelemetry exporter or tokio-console, next to the log output.
+// It receives the events and spans the log levels let through.
+pub fn add_layer<L>(layer: L) -> Result<(), SetupError>
+where
+    L: Layer<Registry> + Send + Sync + 'static,
+{
+    let mut layers = match LAYERS.lock() {
+        Ok(layers) => layers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    // Checked while holding the layers, as setting up takes them
+    if is_set_up() {
+        return Err(SetupError::AlreadySetUp);
+    }
+
+    layers.push(Box::new(layer));
+
+    Ok(())
+}
+
+pub fn setup() -> Result<(), SetupError> {
+    setup_with(LogOptions::default())
+}
+
+// Records of the log crate are passed on to tracing, so both end up in the same output
+pub fn setup_with(options: LogOptions) -> Result<(), SetupError> {
+    let mut layers = match LAYERS.lock() {
+        Ok(layers) => layers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut user_layers = std::mem::take(&mut *layers);
+
+    let output = match options.system_log {
+        Some(system_log) => {
+            user_layers.push(system_log::layer(system_log, &options.identifier)?);
+            None
+        }
+        None => Some(output_layer(&options)?),
+    };
+
+    // An empty Vec of layers is not interested in anything, which would disable all other layers as well
+    let user_layers = (!user_layers.is_empty()).then_some(user_layers);
+
+    // Levels are filtered here instead of by each layer, so they can be changed at runtime
+    tracing_subscriber::registry()
+        .with(user_layers)
+        .with(filter::filter_fn(is_enabled))
+        .with(output)
+        .with(ForwardingLayer)
+        .try_init()?;
+
+    update_levels(|levels| {
+        levels.level = options.level.unwrap_or_else(get_min_log_level);
+        levels.configured = default_target_levels();
+    });
 
     IS_LOGGER_SET_UP.store(true, Ordering::Relaxed);
 
     Ok(())
 }
 
+fn output_layer<S>(options: &LogOptions) -> Result<impl Layer<S>, SetupError>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let use_colors = options.colors
+        && options.file.is_none()
+        && io::stdout().is_terminal()
+        && !is_no_color_set();
+    let writer = match &options.file {
+        Some(file) => {
+            let log_file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .map_err(|err| SetupError::File(file.clone(), err))?;
+            BoxMakeWriter::new(Mutex::new(log_file))
+        }
+        None => BoxMakeWriter::new(|| StdoutWriter),
+    };
+
+    Ok(tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .event_format(LumFormat { colors: use_colors })
+        .with_writer(writer))
+}
+
+// Hands the lines to the printer if one is set
+struct StdoutWriter;
+
+impl Write for StdoutWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut printer = match PRINTER.lock() {
+            Ok(printer) => printer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        match printer.as_mut() {
+            Some(print) => {
+                print(String::from_utf8_lossy(buf).into_owned());
+                Ok(buf.len())
+            }
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+// See https://no-color.org, an empty NO_COLOR does not count
+fn is_no_color_set() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty())
+}
+
+// Formats events like "[time target level] span{fields}: message fields"
+struct LumFormat {
+    colors: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for LumFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        // Records of the log crate carry their real target in fields
+        let normalized_metadata = event.normalized_metadata();
+        let metadata = normalized_metadata
+            .as_ref()
+            .unwrap_or_else(|| event.metadata());
+        let level = match self.colors && COLORS_ENABLED.load(Ordering::Relaxed) {
+            true => colored(metadata.level()),
+            false => metadata.level().to_string(),
+        };
+
+        write!(
+            writer,
+            "[{} {: <30} {: <5}] ",
+            humantime::format_rfc3339_seconds(SystemTime::now()),
+            metadata.target(),
+            level
+        )?;
+
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                write!(writer, "{}", span.name())?;
+
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>() {
+                    if !fields.is_empty() {
+                        write!(writer, "{{{}}}", fields)?;
+                    }
+                }
+
+                write!(writer, ": ")?;
+            }
+        }
+
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+fn colored(level: &Level) -> String {
+    let color = match *level {
+        Level::ERROR => 31,
+        Level::WARN => 33,
+        Level::INFO => 32,
+        Level::DEBUG => 35,
+        Level::TRACE => 36,
+    };
+
+    format!("\x1b[{}m{}\x1b[0m", color, level)
+}
+
+// Buffers warnings and errors until the Discord log service picks them up
+struct ForwardingLayer;
+
+impl<S> Layer<S> for ForwardingLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let normalized_metadata = event.normalized_metadata();
+        let metadata = normalized_metadata
+            .as_ref()
+            .unwrap_or_else(|| event.metadata());
+        if *metadata.level() > Level::WARN || metadata.target().starts_with(DISCORD_LOG_TARGET) {
+            return;
+        }
+
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+
+        forward_record(metadata, &message.0);
+    }
+}
+
+// Collects the message and the fields of an event. Fields added by the log compatibility are left out.
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = match field.name() {
+            "message" if self.0.is_empty() => write!(self.0, "{:?}", value),
+            "message" => write!(self.0, " {:?}", value),
+            name if name.starts_with("log.") => Ok(()),
+            name if self.0.is_empty() => write!(self.0, "{}={:?}", name, value),
+            name => write!(self.0, " {}={:?}", name, value),
+        };
+    }
+}
+
+// Once the buffer is full, the oldest records are dropped
+fn forward_record(metadata: &Metadata, message: &str) {
+    let line = format!(
+        "[{} {} {}] {}",
+        humantime::format_rfc3339_seconds(SystemTime::now()),
+        metadata.target(),
+        metadata.level(),
+        message
+    );
+
+    let mut forwarded_records = match FORWARDED_RECORDS.lock() {
+        Ok(forwarded_records) => forwarded_records,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    if forwarded_records.len() >= FORWARDED_RECORDS_CAPACITY {
+        forwarded_records.pop_front();
+    }
+    forwarded_records.push_back(line);
+}
+
+pub fn take_forwarded_records() -> Vec<String> {
+    let mut forwarded_records = match FORWARDED_RECORDS.lock() {
+        Ok(forwarded_records) => forwarded_records,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    forwarded_records.drain(..).collect()
+}
+
+// Log lines for stdout are passed to the printer instead, e.g. so they don't mess up the line being edited in the console.
+// Logs that go to a file or the system log are not affected.
+pub fn set_printer(printer: Option<Printer>) {
+    let mut current = match PRINTER.lock() {
+        Ok(current) => current,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    *current = printer;
+}
+
+// Turns colors off at runtime, e.g. from the config. Output that was set up without colors stays uncolored.
+pub fn set_colors(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn level() -> LevelFilter {
+    read_levels().level
+}
+
+// The level of records whose target has no level of its own
+pub fn set_global_level(level: LevelFilter) {
+    update_levels(|levels| levels.level = level);
+}
+
+// Overrides the level of a target like serenity or lum::service::discord until it is reset, even across config reloads
+pub fn set_level(target: &str, level: LevelFilter) {
+    update_levels(|levels| {
+        levels.overrides.insert(target.to_string(), level);
+    });
+}
+
+// Returns whether the target had a level set with set_level. It falls back to its configured level.
+pub fn reset_level(target: &str) -> bool {
+    let mut removed = false;
+    update_levels(|levels| removed = levels.overrides.remove(target).is_some());
+
+    removed
+}
+
+// The level records of the target are filtered by
+pub fn level_for(target: &str) -> LevelFilter {
+    read_levels().level_for(target)
+}
+
+// Replaces the levels of targets like serenity or lum::service. Targets that are not given keep their default level.
+pub fn set_target_levels(target_levels: &BTreeMap<String, LevelFilter>) {
+    update_levels(|levels| {
+        levels.configured = default_target_levels();
+        levels.configured.extend(
+            target_levels
+                .iter()
+                .map(|(target, level)| (target.clone(), *level)),
+        );
+    });
+}
+
+pub fn target_levels() -> BTreeMap<String, LevelFilter> {
+    read_levels().targets.clone()
+}
+
+fn default_target_levels() -> BTreeMap<String, LevelFilter> {
+    DEFAULT_TARGET_LEVELS
+        .iter()
+        .map(|(target, level)| (target.to_string(), *level))
+        .collect()
+}
+
+fn is_enabled(metadata: &Metadata) -> bool {
+    as_log_level(metadata.level()) <= read_levels().level_for(metadata.target())
+}
+
+fn as_log_level(level: &Level) -> log::Level {
+    match *level {
+        Level::ERROR => log::Level::Error,
+        Level::WARN => log::Level::Warn,
+        Level::INFO => log::Level::Info,
+        Level::DEBUG => log::Level::Debug,
+        Level::TRACE => log::Level::Trace,
+    }
+}
+
+// Targets are module paths, so lum::service also covers lum::service::discord, but not lum::services
+fn belongs_to(target: &str, name: &str) -> bool {
+    target
+        .strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn read_levels() -> RwLockReadGuard<'static, Levels> {
+    match LEVELS.read() {
+        Ok(levels) => levels,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn update_levels(update: impl FnOnce(&mut Levels)) {
+    let mut levels = match LEVELS.write() {
+        Ok(levels) => levels,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    update(&mut levels);
+
+    let mut targets = levels.configured.clone();
+    targets.extend(
+        levels
+            .overrides
+            .iter()
+            .map(|(target, level)| (target.clone(), *level)),
+    );
+    levels.targets = targets;
+
+    log::set_max_level(levels.max_level());
+}
+
 fn get_min_log_level() -> LevelFilter {
     if is_debug() {
         LevelFilter::Debug
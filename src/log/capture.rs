@@ -0,0 +1,140 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use log::Level;
+use tracing::{subscriber::DefaultGuard, Event, Subscriber};
+use tracing_log::{LogTracer, NormalizeEvent};
+use tracing_subscriber::{layer::Context, layer::SubscriberExt, Layer};
+
+use super::{as_log_level, MessageVisitor};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl Display for CapturedRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} {}] {}", self.target, self.level, self.message)
+    }
+}
+
+// Records everything logged on the thread of the test until it is dropped.
+// Tasks of a current-thread runtime, like the one of #[tokio::test], are captured as well. Tasks on other threads are not.
+pub struct LogCapture {
+    records: Arc<Mutex<Vec<CapturedRecord>>>,
+    _guard: DefaultGuard,
+}
+
+// Tests run in parallel, so each test gets its own capture instead of sharing the global logger
+pub fn test_setup() -> LogCapture {
+    // Fails if the log crate already has a logger, which is fine as long as it is lum's, which forwards to tracing as well.
+    // Its levels are kept then, so only what it lets through is captured.
+    let _ = LogTracer::init();
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let subscriber = tracing_subscriber::registry().with(CaptureLayer {
+        records: Arc::clone(&records),
+    });
+
+    LogCapture {
+        records,
+        _guard: tracing::subscriber::set_default(subscriber),
+    }
+}
+
+impl LogCapture {
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.lock().clone()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    // Whether a record of the level contains the pattern
+    pub fn is_logged(&self, level: Level, pattern: &str) -> bool {
+        self.lock()
+            .iter()
+            .any(|record| record.level == level && record.message.contains(pattern))
+    }
+
+    #[track_caller]
+    pub fn assert_logged(&self, level: Level, pattern: &str) {
+        if !self.is_logged(level, pattern) {
+            panic!(
+                "Expected a {} record containing \"{}\", got:\n{}",
+                level,
+                pattern,
+                self.formatted_records()
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_not_logged(&self, level: Level, pattern: &str) {
+        if self.is_logged(level, pattern) {
+            panic!(
+                "Expected no {} record containing \"{}\", got:\n{}",
+                level,
+                pattern,
+                self.formatted_records()
+            );
+        }
+    }
+
+    fn formatted_records(&self) -> String {
+        let records = self.lock();
+        if records.is_empty() {
+            return String::from("No records");
+        }
+
+        records
+            .iter()
+            .map(|record| record.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // A test that panicked while holding the records poisons them, which should not hide what was logged
+    fn lock(&self) -> MutexGuard<'_, Vec<CapturedRecord>> {
+        match self.records.lock() {
+            Ok(records) => records,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+struct CaptureLayer {
+    records: Arc<Mutex<Vec<CapturedRecord>>>,
+}
+
+impl<S> Layer<S> for CaptureLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let normalized_metadata = event.normalized_metadata();
+        let metadata = normalized_metadata
+            .as_ref()
+            .unwrap_or_else(|| event.metadata());
+
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+
+        let record = CapturedRecord {
+            level: as_log_level(metadata.level()),
+            target: metadata.target().to_string(),
+            message: message.0,
+        };
+
+        match self.records.lock() {
+            Ok(mut records) => records.push(record),
+            Err(poisoned) => poisoned.into_inner().push(record),
+        }
+    }
+}
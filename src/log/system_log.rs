@@ -0,0 +1,135 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[cfg(all(unix, feature = "syslog"))]
+use std::sync::Mutex;
+#[cfg(all(unix, feature = "syslog"))]
+use syslog::{Facility, Formatter3164, Logger, LoggerBackend};
+#[cfg(all(unix, feature = "syslog"))]
+use tracing::{Event, Level, Subscriber};
+#[cfg(all(unix, feature = "syslog"))]
+use tracing_log::NormalizeEvent;
+#[cfg(all(unix, feature = "syslog"))]
+use tracing_subscriber::{layer::Context, Layer};
+
+#[cfg(all(unix, feature = "syslog"))]
+use super::MessageVisitor;
+use super::{BoxedLayer, SetupError};
+
+#[derive(Debug, Error)]
+#[error("Unknown system log {0}, expected journald or syslog")]
+pub struct UnknownSystemLog(pub String);
+
+// For running as a system service, so logs end up in journalctl instead of files nobody looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemLog {
+    Journald,
+    Syslog,
+}
+
+impl Display for SystemLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemLog::Journald => write!(f, "journald"),
+            SystemLog::Syslog => write!(f, "syslog"),
+        }
+    }
+}
+
+impl FromStr for SystemLog {
+    type Err = UnknownSystemLog;
+
+    fn from_str(system_log: &str) -> Result<Self, Self::Err> {
+        match system_log.to_lowercase().as_str() {
+            "journald" => Ok(SystemLog::Journald),
+            "syslog" => Ok(SystemLog::Syslog),
+            _ => Err(UnknownSystemLog(system_log.to_string())),
+        }
+    }
+}
+
+// Both sinks are behind features, so a system log that was not compiled in is only noticed when setting up the logger
+pub(super) fn layer(system_log: SystemLog, identifier: &str) -> Result<BoxedLayer, SetupError> {
+    match system_log {
+        // journald maps the levels to priorities itself, from err for errors to debug for traces
+        #[cfg(all(unix, feature = "journald"))]
+        SystemLog::Journald => {
+            let layer = tracing_journald::layer()
+                .map_err(|err| SetupError::SystemLog(system_log, err.to_string()))?
+                .with_syslog_identifier(identifier.to_string());
+
+            Ok(Box::new(layer))
+        }
+        #[cfg(all(unix, feature = "syslog"))]
+        SystemLog::Syslog => Ok(Box::new(SyslogLayer::connect(identifier)?)),
+        #[allow(unreachable_patterns)]
+        _ => {
+            let _ = identifier;
+            Err(SetupError::SystemLogUnavailable(system_log))
+        }
+    }
+}
+
+// Sends events to the local syslog daemon through its Unix socket
+#[cfg(all(unix, feature = "syslog"))]
+struct SyslogLayer {
+    logger: Mutex<Logger<LoggerBackend, Formatter3164>>,
+}
+
+#[cfg(all(unix, feature = "syslog"))]
+impl SyslogLayer {
+    fn connect(identifier: &str) -> Result<Self, SetupError> {
+        let formatter = Formatter3164 {
+            facility: Facility::LOG_DAEMON,
+            hostname: None,
+            process: identifier.to_string(),
+            pid: std::process::id(),
+        };
+
+        let logger = syslog::unix(formatter)
+            .map_err(|err| SetupError::SystemLog(SystemLog::Syslog, err.to_string()))?;
+
+        Ok(Self {
+            logger: Mutex::new(logger),
+        })
+    }
+}
+
+#[cfg(all(unix, feature = "syslog"))]
+impl<S> Layer<S> for SyslogLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let normalized_metadata = event.normalized_metadata();
+        let metadata = normalized_metadata
+            .as_ref()
+            .unwrap_or_else(|| event.metadata());
+
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let message = format!("{}: {}", metadata.target(), message.0);
+
+        let mut logger = match self.logger.lock() {
+            Ok(logger) => logger,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        // Syslog has no level below debug, so traces are sent as debug messages
+        let result = match *metadata.level() {
+            Level::ERROR => logger.err(message),
+            Level::WARN => logger.warning(message),
+            Level::INFO => logger.info(message),
+            Level::DEBUG | Level::TRACE => logger.debug(message),
+        };
+
+        // Logging the failure would only end up here again
+        if let Err(err) = result {
+            eprintln!("Unable to send log message to syslog: {}", err);
+        }
+    }
+}
@@ -1,47 +1,452 @@
-use std::sync::Arc;
+use std::{io, path::PathBuf, process::ExitCode};
 
 use ::log::{error, warn};
 use lum::{
     bot::Bot,
-    config::{ConfigHandler, FileConfig},
-    log,
-    service::{discord::DiscordService, Service},
+    cli::{Cli, CliCommand},
+    config::{
+        file_config, CliConfig, ConfigHandler, EnvironmentConfig, FileConfig, Redact, SecretKey,
+        Severity, Validate,
+    },
+    log::{self, LogOptions},
+    service::{
+        component::ComponentService,
+        config_watcher::ConfigWatcherService,
+        console::ConsoleService,
+        discord::{self, DiscordService},
+        discord_log::DiscordLogService,
+        permission::PermissionService,
+        webhook::WebhookService,
+        ServiceHandle,
+    },
 };
-use tokio::sync::Mutex;
+use serenity::all::{ChannelId, GatewayIntents, UserId};
+
+#[cfg(feature = "remote-config")]
+use lum::config::{remote, source};
+#[cfg(unix)]
+use lum::daemon::{self, PidFile};
+#[cfg(feature = "poise")]
+use lum::service::command_registration::CommandRegistration;
+#[cfg(feature = "http-admin")]
+use lum::service::http_admin::HttpAdminService;
+#[cfg(feature = "remote-admin")]
+use lum::service::remote_admin::RemoteAdminService;
+#[cfg(feature = "voice")]
+use lum::service::voice::VoiceService;
+#[cfg(unix)]
+use lum::service::{
+    admin::AdminRequest,
+    admin_socket::{send_request, AdminSocketService},
+};
+#[cfg(feature = "poise")]
+use serenity::all::GuildId;
 
 const BOT_NAME: &str = "Lum";
 
-#[tokio::main]
-async fn main() {
-    setup_logger();
+type LumConfigHandler = ConfigHandler<FileConfig, EnvironmentConfig, CliConfig>;
+
+fn main() -> ExitCode {
+    let mut config_handler: LumConfigHandler = ConfigHandler::new(BOT_NAME.to_lowercase().as_str())
+        .with_migrations(file_config::MIGRATIONS)
+        .with_field_descriptions(file_config::FIELD_DESCRIPTIONS);
+
+    let cli = Cli::from_env(&config_handler.env_prefix());
+    if let Some(config_path) = &cli.config {
+        config_handler = config_handler.with_config_file_path(config_path.clone());
+    }
+    let overrides = cli.overrides();
+    if !overrides.is_empty() {
+        config_handler = config_handler.with_cli_config(overrides);
+    }
+
+    match cli.command {
+        Some(CliCommand::CheckConfig) => return check_config(&config_handler),
+        Some(CliCommand::GenerateConfigKey) => {
+            println!("{}", SecretKey::generate().to_base64());
+            return ExitCode::SUCCESS;
+        }
+        Some(CliCommand::EncryptSecret) => return encrypt_secret(&config_handler),
+        _ => {}
+    }
+
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut log_options = LogOptions {
+        level: cli.log_level,
+        colors: !cli.no_color,
+        file: None,
+        system_log: cli.system_log,
+        identifier: BOT_NAME.to_lowercase(),
+    };
+
+    // Forking is only safe as long as no other threads exist, so it happens before the runtime is built
+    #[cfg(unix)]
+    let _pid_file = match cli.daemon && cli.command.is_none() {
+        true => match daemonize(&config_handler) {
+            Ok((pid_file, log_file)) => {
+                log_options.file = Some(log_file);
+                Some(pid_file)
+            }
+            Err(code) => return code,
+        },
+        false => None,
+    };
+
+    #[cfg(not(unix))]
+    if cli.daemon {
+        eprintln!("Daemon mode is only available on Unix");
+        return ExitCode::FAILURE;
+    }
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!(
+                "Error creating the async runtime: {}\n{} will exit.",
+                err, BOT_NAME
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    runtime.block_on(async {
+        match cli.command {
+            Some(CliCommand::Status) => print_status(&config_handler).await,
+            _ => run(config_handler, log_options, cli.daemon).await,
+        }
+    })
+}
+
+async fn run(config_handler: LumConfigHandler, log_options: LogOptions, daemon: bool) -> ExitCode {
+    setup_logger(log_options);
 
     if lum::is_debug() {
         warn!("THIS IS A DEBUG RELEASE!");
     }
 
-    let config_handler = ConfigHandler::new(BOT_NAME.to_lowercase().as_str());
-    let config = match config_handler.load_config() {
+    #[cfg(feature = "remote-config")]
+    let config_handler = match fetch_remote_config(config_handler).await {
+        Ok(config_handler) => config_handler,
+        Err(exit_code) => return exit_code,
+    };
+
+    let mut config = match config_handler.load_config() {
         Ok(config) => config,
         Err(err) => {
             error!(
                 "Error reading config file: {}\n{} will exit.",
                 err, BOT_NAME
             );
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
-    let bot = Bot::builder(BOT_NAME)
-        .with_services(initialize_services(&config))
+    // The logger is set up before the config is loaded, so errors while loading it are logged
+    log::set_target_levels(&config.log_levels);
+    log::set_colors(config.log_colors);
+
+    let config_watcher = ConfigWatcherService::new(config_handler.clone(), config.clone());
+    config_watcher
+        .on_config_changed
+        .subscribe_closure(
+            "main_config_changed",
+            |change| {
+                log::set_target_levels(&change.new.log_levels);
+                log::set_colors(change.new.log_colors);
+
+                let restart_fields = change
+                    .changed_fields()
+                    .into_iter()
+                    .filter(|field| field != "logLevels" && field != "logColors")
+                    .collect::<Vec<_>>();
+                if !restart_fields.is_empty() {
+                    warn!(
+                        "Restart {} to apply the changes to {}",
+                        BOT_NAME,
+                        restart_fields.join(", ")
+                    );
+                }
+                Ok(())
+            },
+            true,
+            false,
+        )
         .await
-        .build()
-        .await;
+        .detach();
+
+    // Without a terminal, there is nobody to type into the console
+    if daemon {
+        config.console = Some(false);
+    }
+
+    let permission_overrides_path = match config_handler.get_permission_overrides_file_path() {
+        Ok(permission_overrides_path) => permission_overrides_path,
+        Err(err) => {
+            error!(
+                "Error getting permission overrides path: {}\n{} will exit.",
+                err, BOT_NAME
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let discord_intents = match discord::parse_gateway_intents(&config.discord_intents) {
+        Ok(discord_intents) => discord_intents,
+        Err(err) => {
+            error!(
+                "Error parsing Discord gateway intents: {}\n{} will exit.",
+                err, BOT_NAME
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let console_history_path = match config_handler.get_console_history_file_path() {
+        Ok(console_history_path) => Some(console_history_path),
+        Err(err) => {
+            warn!(
+                "Error getting console history path: {}\nThe console history will not be saved.",
+                err
+            );
+            None
+        }
+    };
+
+    let mut services = initialize_services(
+        &config,
+        discord_intents,
+        permission_overrides_path,
+        console_history_path,
+    );
+    services.push(ServiceHandle::new(config_watcher));
+
+    let bot_builder = match Bot::builder(BOT_NAME)
+        .with_config(config)
+        .with_services(services)
+        .await
+    {
+        Ok(bot_builder) => bot_builder,
+        Err(err) => {
+            error!(
+                "Error registering services: {}\n{} will exit.",
+                err, BOT_NAME
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let bot_builder = match config_handler.get_run_report_file_path() {
+        Ok(run_report_path) => bot_builder.with_run_report_path(run_report_path),
+        Err(err) => {
+            warn!(
+                "Error getting run report path: {}\nThe report of this run will not be saved.",
+                err
+            );
+            bot_builder
+        }
+    };
+
+    let bot = bot_builder.build().await;
 
     lum::run(bot).await;
+
+    ExitCode::SUCCESS
+}
+
+// LUM_CONFIG_SOURCE is a comma-separated list of URLs like https://config.example.com/lum.json,
+// consul+http://consul:8500/lum/config or etcd+http://etcd:2379/lum/config, tried in order
+#[cfg(feature = "remote-config")]
+async fn fetch_remote_config(
+    config_handler: LumConfigHandler,
+) -> Result<LumConfigHandler, ExitCode> {
+    let variable = format!("{}_CONFIG_SOURCE", config_handler.env_prefix());
+    let urls = match std::env::var(&variable) {
+        Ok(urls) => urls,
+        Err(_) => return Ok(config_handler),
+    };
+    let token = std::env::var(format!("{}_TOKEN", variable)).ok();
+
+    let mut sources = Vec::new();
+    for url in urls.split(',').map(str::trim).filter(|url| !url.is_empty()) {
+        match remote::from_url(url, token.clone()) {
+            Ok(source) => sources.push(source),
+            Err(err) => {
+                error!("Invalid {}: {}", variable, err);
+                return Err(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    match source::fetch_first(&sources).await {
+        Some(remote_config) => Ok(config_handler.with_remote_config(remote_config)),
+        None => {
+            warn!("No remote config could be loaded, only the local config is used");
+            Ok(config_handler)
+        }
+    }
 }
 
-fn setup_logger() {
-    if let Err(error) = log::setup() {
+// Errors are printed to the terminal, as the logger is not set up yet and the daemon can not report them anymore
+#[cfg(unix)]
+fn daemonize(config_handler: &LumConfigHandler) -> Result<(PidFile, PathBuf), ExitCode> {
+    if let Err(err) = config_handler.load_config() {
+        eprintln!(
+            "Error reading config file: {}\n{} will exit.",
+            err, BOT_NAME
+        );
+        return Err(ExitCode::FAILURE);
+    }
+
+    let paths = config_handler
+        .get_pid_file_path()
+        .and_then(|pid_file_path| Ok((pid_file_path, config_handler.get_log_file_path()?)));
+    let (pid_file_path, log_file_path) = match paths {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!(
+                "Error getting daemon file paths: {}\n{} will exit.",
+                err, BOT_NAME
+            );
+            return Err(ExitCode::FAILURE);
+        }
+    };
+
+    if let Some(pid) = daemon::running_pid(&pid_file_path) {
+        eprintln!(
+            "{} is already running with PID {}\n{} will exit.",
+            BOT_NAME, pid, BOT_NAME
+        );
+        return Err(ExitCode::FAILURE);
+    }
+
+    println!(
+        "{} is going to run in the background. PID file: {}, log file: {}",
+        BOT_NAME,
+        pid_file_path.display(),
+        log_file_path.display()
+    );
+
+    match daemon::daemonize(&pid_file_path) {
+        Ok(pid_file) => Ok((pid_file, log_file_path)),
+        Err(err) => {
+            eprintln!("Error starting daemon: {}\n{} will exit.", err, BOT_NAME);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn encrypt_secret(config_handler: &LumConfigHandler) -> ExitCode {
+    let key = match config_handler.get_secret_key() {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            eprintln!(
+                "Set {} to a key from generate-config-key first",
+                config_handler.config_key_variable()
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut secret = String::new();
+    if let Err(err) = io::stdin().read_line(&mut secret) {
+        eprintln!("Unable to read the secret from stdin: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    match key.encrypt(secret.trim_end_matches(['\r', '\n'])) {
+        Ok(encrypted) => {
+            println!("{}", encrypted);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Runs before the logger is set up, so the report is printed directly
+fn check_config(config_handler: &LumConfigHandler) -> ExitCode {
+    let config = match config_handler.load_unvalidated_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Config is invalid: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match serde_json::to_string_pretty(&config.redacted()) {
+        Ok(redacted) => println!("{}\n", redacted),
+        Err(err) => eprintln!("Unable to print config: {}", err),
+    }
+
+    let problems = config.validate(&config_handler.env_prefix());
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+
+    if problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error)
+    {
+        println!("Config is invalid");
+        return ExitCode::FAILURE;
+    }
+
+    println!("Config is valid");
+    ExitCode::SUCCESS
+}
+
+#[cfg(unix)]
+async fn print_status(config_handler: &LumConfigHandler) -> ExitCode {
+    let config = match config_handler.load_unvalidated_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Error reading config file: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let admin_socket = match &config.admin_socket {
+        Some(admin_socket) => PathBuf::from(admin_socket),
+        None => {
+            eprintln!("No adminSocket is configured, so the running instance can not be reached");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let request = AdminRequest::new("status", Vec::new());
+    match send_request(&admin_socket, &request).await {
+        Ok(response) if response.success => {
+            println!("{}", response.output);
+            ExitCode::SUCCESS
+        }
+        Ok(response) => {
+            eprintln!("{}", response.output);
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn print_status(_config_handler: &LumConfigHandler) -> ExitCode {
+    eprintln!("The status command needs the admin socket, which is only available on Unix");
+    ExitCode::FAILURE
+}
+
+fn setup_logger(options: LogOptions) {
+    if let Err(error) = log::setup_with(options) {
         panic!(
             "Error setting up the Logger: {}\n{} will exit.",
             error, BOT_NAME
@@ -49,11 +454,109 @@ fn setup_logger() {
     }
 }
 
-fn initialize_services(config: &FileConfig) -> Vec<Arc<Mutex<dyn Service>>> {
+fn initialize_services(
+    config: &FileConfig,
+    discord_intents: GatewayIntents,
+    permission_overrides_path: PathBuf,
+    console_history_path: Option<PathBuf>,
+) -> Vec<ServiceHandle> {
     //TODO: Add services
     //...
 
-    let discord_service = DiscordService::new(config.discord_token.as_str());
+    let bot_admins = config.bot_admins.iter().copied().map(UserId::new).collect();
+    let permission_service = PermissionService::new(bot_admins, permission_overrides_path);
+
+    let mut discord_service =
+        DiscordService::new(config.discord_token.expose()).with_intents(discord_intents);
+    if let Some(discord_shards) = config.discord_shards {
+        discord_service = discord_service.with_shards(discord_shards);
+    }
+
+    #[cfg(feature = "poise")]
+    {
+        let test_guilds = config
+            .command_test_guilds
+            .iter()
+            .copied()
+            .map(GuildId::new)
+            .collect();
+        discord_service = discord_service.with_command_registration(
+            CommandRegistration::new()
+                .with_test_guilds(test_guilds)
+                .with_dry_run(config.command_registration_dry_run),
+        );
+    }
+
+    let mut services = vec![
+        ServiceHandle::new(permission_service),
+        ServiceHandle::new(discord_service),
+        ServiceHandle::new(ComponentService::new()),
+    ];
+
+    if let Some(discord_log_channel) = config.discord_log_channel {
+        services.push(ServiceHandle::new(DiscordLogService::new(ChannelId::new(
+            discord_log_channel,
+        ))));
+    }
+
+    if ConsoleService::is_enabled(config.console) {
+        let mut console_service = ConsoleService::new();
+        if let Some(console_history_path) = console_history_path {
+            console_service = console_service.with_history_file(console_history_path);
+        }
+        services.push(ServiceHandle::new(console_service));
+    }
+
+    #[cfg(unix)]
+    if let Some(admin_socket) = &config.admin_socket {
+        services.push(ServiceHandle::new(AdminSocketService::new(PathBuf::from(
+            admin_socket,
+        ))));
+    }
+
+    #[cfg(feature = "remote-admin")]
+    if let (Some(address), Some(token)) = (&config.remote_admin_address, &config.remote_admin_token)
+    {
+        match address.parse() {
+            Ok(address) => services.push(ServiceHandle::new(RemoteAdminService::new(
+                address,
+                token.expose(),
+            ))),
+            Err(err) => warn!(
+                "Invalid remote admin address {}: {}\nThe remote admin API will not be available.",
+                address, err
+            ),
+        }
+    }
+
+    #[cfg(feature = "http-admin")]
+    if let Some(address) = &config.http_admin_address {
+        match address.parse() {
+            Ok(address) => {
+                let mut http_admin_service = HttpAdminService::new(address);
+                if let Some(token) = &config.http_admin_token {
+                    http_admin_service = http_admin_service.with_token(token.expose());
+                }
+                services.push(ServiceHandle::new(http_admin_service));
+            }
+            Err(err) => warn!(
+                "Invalid HTTP admin address {}: {}\nThe HTTP admin API will not be available.",
+                address, err
+            ),
+        }
+    }
+
+    if !config.webhooks.is_empty() {
+        let webhook_urls = config
+            .webhooks
+            .iter()
+            .map(|(name, url)| (name.clone(), url.expose().clone()))
+            .collect();
+        services.push(ServiceHandle::new(WebhookService::new(webhook_urls)));
+    }
+
+    #[cfg(feature = "voice")]
+    services.push(ServiceHandle::new(VoiceService::new()));
 
-    vec![Arc::new(Mutex::new(discord_service))]
+    services
 }
@@ -0,0 +1,77 @@
+use std::{fs, io, path::Path, time::SystemTime};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{bot::ExitReason, service::StatusSnapshot};
+
+#[derive(Debug, Error)]
+pub enum RunReportError {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+
+    #[error("Unable to serialize or deserialize run report: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+// Written on startup without an exit reason and overwritten on shutdown, so a missing exit reason means the run did not end gracefully
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub exit_reason: Option<ExitReason>,
+    pub written_at: SystemTime,
+    pub status: StatusSnapshot,
+}
+
+impl RunReport {
+    pub fn new(exit_reason: Option<ExitReason>, status: StatusSnapshot) -> Self {
+        Self {
+            exit_reason,
+            written_at: SystemTime::now(),
+            status,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>, RunReportError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let report_json = fs::read_to_string(path)?;
+        let report = serde_json::from_str(&report_json)?;
+
+        Ok(Some(report))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), RunReportError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let report_json = serde_json::to_string_pretty(self)?;
+        fs::write(path, report_json)?;
+
+        Ok(())
+    }
+
+    pub fn log_comparison(&self) {
+        let written_at = humantime::format_rfc3339_seconds(self.written_at);
+
+        match self.exit_reason {
+            Some(exit_reason) => info!(
+                "Previous run exited at {} ({}) with overall status {}",
+                written_at, exit_reason, self.status.overall_status
+            ),
+            None => warn!(
+                "Previous run did not shut down gracefully. Its last known overall status from {} was {}",
+                written_at, self.status.overall_status
+            ),
+        }
+
+        for service in self.status.services.iter() {
+            if service.status.is_failure() {
+                warn!("{} previously failed: {}", service.name, service.status);
+            }
+        }
+    }
+}
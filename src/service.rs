@@ -1,13 +1,63 @@
+pub mod admin;
+#[cfg(unix)]
+pub mod admin_socket;
+pub mod bulk;
+pub mod circuit_breaker;
+#[cfg(feature = "poise")]
+pub mod command_registration;
+pub mod command_registry;
+#[cfg(feature = "poise")]
+pub mod commands;
+pub mod component;
+pub mod config_registry;
+pub mod config_watcher;
+pub mod console;
+pub mod context;
 pub mod discord;
+mod discord_admin;
+pub mod discord_data;
+pub mod discord_handlers;
+pub mod discord_log;
+#[cfg(feature = "event-bridge")]
+pub mod event_bridge;
+pub mod health_monitor;
+#[cfg(feature = "http-admin")]
+pub mod http_admin;
+pub mod metrics;
+pub mod modal;
+pub mod pause_token;
+pub mod permission;
+#[cfg(feature = "remote-admin")]
+pub mod remote_admin;
 pub mod service; // Will be fixed when lum gets seperated into multiple workspaces
+pub mod service_handle;
+mod service_index;
 pub mod service_manager;
+pub mod snapshot;
+pub mod supervisor;
 pub mod taskchain;
 pub mod types;
+#[cfg(feature = "voice")]
+pub mod voice;
+pub mod webhook;
 
-pub use service::{Service, ServiceInfo};
+pub use circuit_breaker::CircuitBreaker;
+pub use command_registry::CommandRegistry;
+pub use config_registry::ConfigRegistry;
+pub use context::{DependencyError, ServiceContext, ServiceDependency};
+pub use health_monitor::HealthMonitor;
+pub use metrics::ServiceMetrics;
+pub use pause_token::PauseToken;
+pub use service::{is_valid_service_id, Service, ServiceInfo};
+pub use service_handle::ServiceHandle;
 pub use service_manager::{ServiceManager, ServiceManagerBuilder};
+pub use snapshot::{BackgroundTaskSnapshot, ServiceInfoSnapshot, StatusSnapshot};
+pub use supervisor::Supervisor;
 pub use taskchain::Taskchain;
 pub use types::{
-    BoxedError, LifetimedPinnedBoxedFuture, LifetimedPinnedBoxedFutureResult, OverallStatus,
-    PinnedBoxedFuture, PinnedBoxedFutureResult, Priority, ShutdownError, StartupError, Status,
+    BoxedError, BuildError, CircuitBreakerResetError, DrainError, LifetimedPinnedBoxedFuture,
+    LifetimedPinnedBoxedFutureResult, OverallStatus, PauseError, PinnedBoxedFuture,
+    PinnedBoxedFutureResult, Priority, Readiness, RegistrationError, RemovalError,
+    ReplacementError, RestartError, ResumeError, ShutdownError, StartupError, StartupPhase, Status,
+    TaskRestartPolicy, WaitError,
 };
@@ -0,0 +1,469 @@
+use std::str::FromStr;
+
+use ::log::LevelFilter;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    command_registry::BUILTIN_COMMANDS, CircuitBreakerResetError, RestartError, ServiceManager,
+    ShutdownError, StartupError, StatusSnapshot,
+};
+use crate::log;
+
+pub const HELP: &str = "Commands:
+  help                 Show this help
+  status               Show the status overview
+  services             List all services
+  start <service id>   Start a service
+  stop <service id>    Stop a service
+  restart <service id> Restart a service
+  reset <service id>   Re-enable a service disabled by its circuit breaker
+  complete [prefix]    List service IDs starting with the prefix
+  loglevel [level]     Show or set the log level (off, error, warn, info, debug, trace)
+  loglevel <target> <level|reset>
+                       Set the log level of a target like serenity, or reset it to the configured one
+  reload               Ask services to reload their configuration
+  quit                 Shut down gracefully
+
+Service IDs may be shortened to any unique prefix, with or without lum_builtin_.";
+
+const BUILTIN_PREFIX: &str = "lum_builtin_";
+const LOG_LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+
+// Shared by all admin frontends (console, socket, ...), so they support the same commands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminRequest {
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+}
+
+impl AdminRequest {
+    pub fn new(command: &str, arguments: Vec<String>) -> Self {
+        Self {
+            command: command.to_string(),
+            arguments,
+        }
+    }
+
+    // Parses a line like "restart lum_builtin_discord"
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let command = parts.next()?;
+
+        Some(Self::new(command, parts.map(str::to_string).collect()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminResponse {
+    pub success: bool,
+    pub output: String,
+}
+
+impl AdminResponse {
+    pub fn success(output: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            output: output.into(),
+        }
+    }
+
+    pub fn failure(output: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            output: output.into(),
+        }
+    }
+}
+
+// Compares in constant time, so tokens can not be guessed byte by byte from response times
+pub fn tokens_match(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    if expected.len() != given.len() {
+        return false;
+    }
+
+    expected
+        .iter()
+        .zip(given.iter())
+        .fold(0, |difference, (expected, given)| {
+            difference | (expected ^ given)
+        })
+        == 0
+}
+
+// The caller is the service that received the request. It can not stop or restart itself, as it would wait on its own task.
+pub async fn execute(
+    service_manager: &ServiceManager,
+    caller_id: &str,
+    request: &AdminRequest,
+) -> AdminResponse {
+    let command = request.command.to_lowercase();
+    let argument = request.arguments.first().map(|argument| argument.as_str());
+
+    match (command.as_str(), argument) {
+        ("help", _) => {
+            let service_commands = service_manager.command_registry.help().await;
+            match service_commands.is_empty() {
+                true => AdminResponse::success(HELP),
+                false => AdminResponse::success(format!(
+                    "{}\n\nService commands:\n{}",
+                    HELP, service_commands
+                )),
+            }
+        }
+        ("status", _) => AdminResponse::success(service_manager.status_overview().await),
+        ("services", _) => {
+            let snapshot = service_manager.status_snapshot().await;
+            let services = snapshot
+                .services
+                .iter()
+                .map(|service| {
+                    format!(
+                        "{} ({}): {}, {}",
+                        service.id, service.name, service.status, service.readiness
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            AdminResponse::success(services)
+        }
+        ("complete", prefix) => {
+            let service_ids = complete_service_ids(service_manager, prefix.unwrap_or("")).await;
+            AdminResponse::success(service_ids.join("\n"))
+        }
+        ("start" | "stop" | "restart" | "reset", Some(input)) => {
+            let service_id = match resolve_service_id(service_manager, input).await {
+                Ok(service_id) => service_id,
+                Err(message) => return AdminResponse::failure(message),
+            };
+
+            if command != "reset" && service_id == caller_id {
+                return AdminResponse::failure(format!("{} can not control itself", caller_id));
+            }
+
+            control_service(service_manager, &command, &service_id).await
+        }
+        ("loglevel", _) => log_level(&request.arguments),
+        ("reload", _) => {
+            service_manager.request_reload().await;
+            AdminResponse::success("Reload requested")
+        }
+        ("quit" | "exit", _) => {
+            service_manager.request_shutdown();
+            AdminResponse::success("Shutting down...")
+        }
+        ("start" | "stop" | "restart" | "reset", None) => {
+            AdminResponse::failure(format!("Usage: {} <service id>", command))
+        }
+        _ => match service_manager.command_registry.get(&command).await {
+            Some(service_command) => service_command.run(request.arguments.clone()).await,
+            None => AdminResponse::failure(format!(
+                "Unknown command {}. Type help for a list of commands.",
+                command
+            )),
+        },
+    }
+}
+
+fn log_level(arguments: &[String]) -> AdminResponse {
+    let parse_level = |level: &str| {
+        LevelFilter::from_str(level).map_err(|_| format!("Unknown log level {}", level))
+    };
+
+    match arguments {
+        [] => {
+            let target_levels = log::target_levels()
+                .iter()
+                .map(|(target, level)| format!("\n  {}: {}", target, level))
+                .collect::<String>();
+
+            AdminResponse::success(format!(
+                "Log level is {}. Levels by target:{}",
+                log::level(),
+                target_levels
+            ))
+        }
+        [level] => match parse_level(level) {
+            Ok(level) => {
+                log::set_global_level(level);
+                AdminResponse::success(format!("Log level set to {}", level))
+            }
+            Err(message) => AdminResponse::failure(message),
+        },
+        [target, reset] if reset.eq_ignore_ascii_case("reset") => match log::reset_level(target) {
+            true => AdminResponse::success(format!(
+                "Log level of {} reset to {}",
+                target,
+                log::level_for(target)
+            )),
+            false => AdminResponse::failure(format!("No log level was set for {}", target)),
+        },
+        [target, level] => match parse_level(level) {
+            Ok(level) => {
+                log::set_level(target, level);
+                AdminResponse::success(format!("Log level of {} set to {}", target, level))
+            }
+            Err(message) => AdminResponse::failure(message),
+        },
+        _ => AdminResponse::failure("Usage: loglevel [target] [level|reset]"),
+    }
+}
+
+// Returns all managed service IDs that start with the prefix, so frontends can offer completions
+pub async fn complete_service_ids(service_manager: &ServiceManager, prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    let builtin_prefix = format!("{}{}", BUILTIN_PREFIX, prefix);
+
+    let mut service_ids = service_manager
+        .status_snapshot()
+        .await
+        .services
+        .into_iter()
+        .map(|service| service.id)
+        .filter(|service_id| {
+            service_id.starts_with(&prefix) || service_id.starts_with(&builtin_prefix)
+        })
+        .collect::<Vec<_>>();
+    service_ids.sort();
+
+    service_ids
+}
+
+// Completes the word being typed, based on the words before it
+pub async fn completions(
+    service_manager: &ServiceManager,
+    preceding_words: &[String],
+    word: &str,
+) -> Vec<String> {
+    let mut candidates = match preceding_words {
+        [] => {
+            let mut commands = BUILTIN_COMMANDS
+                .iter()
+                .map(|command| command.to_string())
+                .collect::<Vec<_>>();
+            commands.extend(service_manager.command_registry.names().await);
+            commands
+        }
+        [command] => match command.to_lowercase().as_str() {
+            "start" | "stop" | "restart" | "reset" | "complete" => {
+                return complete_service_ids(service_manager, word).await
+            }
+            // The first argument is either the level or a target
+            "loglevel" => LOG_LEVELS
+                .iter()
+                .map(|level| level.to_string())
+                .chain(log::target_levels().into_keys())
+                .collect(),
+            _ => Vec::new(),
+        },
+        [command, _] if command.eq_ignore_ascii_case("loglevel") => LOG_LEVELS
+            .iter()
+            .chain(["reset"].iter())
+            .map(|level| level.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let word = word.to_lowercase();
+    candidates.retain(|candidate| candidate.starts_with(&word));
+    candidates.sort();
+    candidates
+}
+
+// An exact ID always wins. Otherwise, the input has to be a unique prefix of a managed service ID.
+pub async fn resolve_service_id(
+    service_manager: &ServiceManager,
+    input: &str,
+) -> Result<String, String> {
+    let candidates = complete_service_ids(service_manager, input).await;
+
+    let input = input.to_lowercase();
+    let builtin_input = format!("{}{}", BUILTIN_PREFIX, input);
+    if let Some(service_id) = candidates
+        .iter()
+        .find(|service_id| **service_id == input || **service_id == builtin_input)
+    {
+        return Ok(service_id.clone());
+    }
+
+    match candidates.len() {
+        0 => Err(format!(
+            "Unknown service {}. Type services for a list of services.",
+            input
+        )),
+        1 => Ok(candidates[0].clone()),
+        _ => Err(format!(
+            "Service {} is ambiguous, it could be any of: {}",
+            input,
+            candidates.join(", ")
+        )),
+    }
+}
+
+async fn control_service(
+    service_manager: &ServiceManager,
+    command: &str,
+    service_id: &str,
+) -> AdminResponse {
+    let result = match command {
+        "start" => match service_manager.get_service_by_id(service_id).await {
+            Some(service) => service_manager
+                .start_service(service)
+                .await
+                .map_err(|error| explain_startup_error(&error)),
+            None => Err(format!("Unknown service {}", service_id)),
+        },
+        "stop" => match service_manager.get_service_by_id(service_id).await {
+            Some(service) => service_manager
+                .stop_service(service)
+                .await
+                .map_err(|error| explain_shutdown_error(&error)),
+            None => Err(format!("Unknown service {}", service_id)),
+        },
+        "restart" => service_manager
+            .restart_service(service_id)
+            .await
+            .map_err(|error| explain_restart_error(&error)),
+        _ => service_manager
+            .reset_circuit_breaker(service_id)
+            .await
+            .map_err(|error| explain_reset_error(&error)),
+    };
+
+    let (done, failed) = match command {
+        "start" => ("Started", "start"),
+        "stop" => ("Stopped", "stop"),
+        "restart" => ("Restarted", "restart"),
+        _ => ("Reset the circuit breaker of", "reset"),
+    };
+
+    match result {
+        Ok(()) => AdminResponse::success(format!("{} {}", done, service_id)),
+        Err(explanation) => AdminResponse::failure(format!(
+            "Unable to {} {}: {}",
+            failed, service_id, explanation
+        )),
+    }
+}
+
+fn explain_startup_error(error: &StartupError) -> String {
+    match error {
+        StartupError::ServiceNotStopped(_) => {
+            "it is not stopped. Use restart to bring it up again.".to_string()
+        }
+        StartupError::ServiceDisabled(service_id) => format!(
+            "it was disabled by its circuit breaker. Use reset {} first.",
+            service_id
+        ),
+        StartupError::BackgroundTaskAlreadyRunning(service_id) => format!(
+            "its background task is still running. Try stop {} first.",
+            service_id
+        ),
+        StartupError::MissingDependency(_, dependency) => {
+            format!("it depends on {}, which is not registered", dependency)
+        }
+        StartupError::DependencyNotStarted(_, dependency) => format!(
+            "it depends on {}, which is not running. Start {} first.",
+            dependency, dependency
+        ),
+        StartupError::FailedToStartService(_) => {
+            "the service failed to start. Check the log for details.".to_string()
+        }
+        error => error.to_string(),
+    }
+}
+
+fn explain_shutdown_error(error: &ShutdownError) -> String {
+    match error {
+        ShutdownError::ServiceNotStarted(_) => "it is not running".to_string(),
+        ShutdownError::FailedToStopService(_) => {
+            "the service failed to stop. Check the log for details.".to_string()
+        }
+        ShutdownError::BackgroundTaskNotStopped(_) => {
+            "its background task did not stop after being aborted".to_string()
+        }
+        error => error.to_string(),
+    }
+}
+
+fn explain_restart_error(error: &RestartError) -> String {
+    match error {
+        RestartError::ServiceDisabled(service_id) => format!(
+            "it was disabled by its circuit breaker. Use reset {} first.",
+            service_id
+        ),
+        RestartError::Shutdown(error) => {
+            format!("stopping failed, {}", explain_shutdown_error(error))
+        }
+        RestartError::Startup(error) => {
+            format!("starting failed, {}", explain_startup_error(error))
+        }
+        error => error.to_string(),
+    }
+}
+
+fn explain_reset_error(error: &CircuitBreakerResetError) -> String {
+    match error {
+        CircuitBreakerResetError::ServiceNotDisabled(_) => {
+            "it is not disabled by its circuit breaker".to_string()
+        }
+        error => error.to_string(),
+    }
+}
+
+// Typed protocol of the remote admin API. It lives here so dashboards and other clients can reuse it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteAdminRequest {
+    Authenticate { token: String },
+    Status,
+    Start { service_id: String },
+    Stop { service_id: String },
+    Restart { service_id: String },
+    ReloadConfig,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteAdminResponse {
+    Authenticated,
+    Status { snapshot: StatusSnapshot },
+    Done { message: String },
+    Error { message: String },
+}
+
+impl From<AdminResponse> for RemoteAdminResponse {
+    fn from(response: AdminResponse) -> Self {
+        match response.success {
+            true => RemoteAdminResponse::Done {
+                message: response.output,
+            },
+            false => RemoteAdminResponse::Error {
+                message: response.output,
+            },
+        }
+    }
+}
+
+impl RemoteAdminRequest {
+    // Requests other than Authenticate and Status map onto the shared admin commands
+    pub fn to_admin_request(&self) -> Option<AdminRequest> {
+        match self {
+            RemoteAdminRequest::Authenticate { .. } | RemoteAdminRequest::Status => None,
+            RemoteAdminRequest::Start { service_id } => {
+                Some(AdminRequest::new("start", vec![service_id.clone()]))
+            }
+            RemoteAdminRequest::Stop { service_id } => {
+                Some(AdminRequest::new("stop", vec![service_id.clone()]))
+            }
+            RemoteAdminRequest::Restart { service_id } => {
+                Some(AdminRequest::new("restart", vec![service_id.clone()]))
+            }
+            RemoteAdminRequest::ReloadConfig => Some(AdminRequest::new("reload", Vec::new())),
+            RemoteAdminRequest::Shutdown => Some(AdminRequest::new("quit", Vec::new())),
+        }
+    }
+}
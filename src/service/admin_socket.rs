@@ -0,0 +1,183 @@
+use std::{
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+};
+
+use log::{info, warn};
+use serenity::async_trait;
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    select, spawn,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    admin::{execute, AdminRequest, AdminResponse},
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service, ServiceContext, ServiceInfo,
+    ServiceManager,
+};
+
+#[derive(Debug, Error)]
+pub enum AdminSocketError {
+    #[error("Unable to connect to admin socket {0}: {1}")]
+    Connect(PathBuf, io::Error),
+
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+
+    #[error("Invalid response: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Admin socket closed the connection without responding")]
+    Closed,
+}
+
+// Client side of the protocol, used by the CLI to talk to a running instance
+pub async fn send_request(
+    path: &Path,
+    request: &AdminRequest,
+) -> Result<AdminResponse, AdminSocketError> {
+    let stream = UnixStream::connect(path)
+        .await
+        .map_err(|error| AdminSocketError::Connect(path.to_path_buf(), error))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut request = serde_json::to_string(request)?;
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    let response = lines.next_line().await?.ok_or(AdminSocketError::Closed)?;
+
+    Ok(serde_json::from_str(&response)?)
+}
+
+// Only the user running the bot may administer it
+const SOCKET_PERMISSIONS: u32 = 0o600;
+
+// Admin commands over a Unix socket, one JSON request per line, e.g. {"command":"restart","arguments":["lum_builtin_discord"]}.
+// Each request is answered with one JSON response line.
+pub struct AdminSocketService {
+    info: ServiceInfo,
+    path: PathBuf,
+    listener: Option<Arc<UnixListener>>,
+    service_manager: Option<Weak<ServiceManager>>,
+}
+
+impl AdminSocketService {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_admin_socket", "Admin Socket", Priority::Low),
+            path,
+            listener: None,
+            service_manager: None,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+) -> Result<(), BoxedError> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<AdminRequest>(&line) {
+            Ok(request) => match service_manager.upgrade() {
+                Some(service_manager) => execute(&service_manager, &own_id, &request).await,
+                None => return Ok(()),
+            },
+            Err(error) => AdminResponse::failure(format!("Invalid request: {}", error)),
+        };
+
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+async fn accept_connections(
+    listener: Arc<UnixListener>,
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError> {
+    loop {
+        let (stream, _) = select! {
+            _ = cancellation_token.cancelled() => return Ok(()),
+            connection = listener.accept() => connection?,
+        };
+
+        let service_manager = Weak::clone(&service_manager);
+        let own_id = own_id.clone();
+        spawn(async move {
+            if let Err(error) = handle_connection(stream, service_manager, own_id).await {
+                warn!("Admin socket connection failed: {}", error);
+            }
+        });
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for AdminSocketService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        // A socket file left behind by a previous run would make binding fail
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+
+        let listener = UnixListener::bind(&self.path)?;
+        fs::set_permissions(&self.path, fs::Permissions::from_mode(SOCKET_PERMISSIONS))?;
+        info!("Listening for admin commands on {}", self.path.display());
+
+        self.listener = Some(Arc::new(listener));
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.listener = None;
+        self.service_manager = None;
+
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let listener = Arc::clone(self.listener.as_ref()?);
+        let service_manager = Weak::clone(self.service_manager.as_ref()?);
+        let own_id = self.info.id.clone();
+
+        Some(Box::pin(async move {
+            accept_connections(listener, service_manager, own_id, cancellation_token).await
+        }))
+    }
+}
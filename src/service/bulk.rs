@@ -0,0 +1,197 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{info, warn};
+use serenity::{
+    all::{ChannelId, CreateMessage, EditMessage, GuildId, MessageId, RoleId, StatusCode, UserId},
+    http::{Http, HttpError},
+    Error,
+};
+use thiserror::Error;
+use tokio::{sync::mpsc::Receiver, time::sleep};
+use uuid::Uuid;
+
+use crate::event::Event;
+
+#[derive(Debug, Error)]
+pub enum BulkError {
+    #[error("Discord client has not been started")]
+    NotStarted,
+
+    #[error("Bulk queue is closed")]
+    QueueClosed,
+}
+
+#[derive(Debug, Clone)]
+pub enum BulkAction {
+    SendMessage {
+        channel_id: ChannelId,
+        message: CreateMessage,
+    },
+    DirectMessage {
+        user_id: UserId,
+        message: CreateMessage,
+    },
+    EditMessage {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        edit: EditMessage,
+    },
+    AddRole {
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    },
+    RemoveRole {
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    },
+}
+
+impl BulkAction {
+    async fn execute(&self, http: &Http) -> Result<(), Error> {
+        match self {
+            BulkAction::SendMessage {
+                channel_id,
+                message,
+            } => {
+                channel_id.send_message(http, message.clone()).await?;
+            }
+            BulkAction::DirectMessage { user_id, message } => {
+                user_id.direct_message(http, message.clone()).await?;
+            }
+            BulkAction::EditMessage {
+                channel_id,
+                message_id,
+                edit,
+            } => {
+                channel_id
+                    .edit_message(http, *message_id, edit.clone())
+                    .await?;
+            }
+            BulkAction::AddRole {
+                guild_id,
+                user_id,
+                role_id,
+            } => {
+                http.add_member_role(*guild_id, *user_id, *role_id, None)
+                    .await?;
+            }
+            BulkAction::RemoveRole {
+                guild_id,
+                user_id,
+                role_id,
+            } => {
+                http.remove_member_role(*guild_id, *user_id, *role_id, None)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkProgress {
+    pub job_id: Uuid,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub rate_limited: u32,
+}
+
+impl BulkProgress {
+    pub fn is_finished(&self) -> bool {
+        self.succeeded + self.failed >= self.total
+    }
+}
+
+pub struct BulkJob {
+    pub id: Uuid,
+    pub actions: Vec<BulkAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkSettings {
+    pub delay: Duration,
+    pub max_retries: u32,
+}
+
+fn is_rate_limited(error: &Error) -> bool {
+    match error {
+        Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            response.status_code == StatusCode::TOO_MANY_REQUESTS
+        }
+        _ => false,
+    }
+}
+
+// Works through queued jobs one action at a time. Serenity already waits for known rate limits, so this only backs off when Discord still answers with 429.
+pub async fn run_bulk_worker(
+    http: Arc<Http>,
+    mut receiver: Receiver<BulkJob>,
+    on_progress: Arc<Event<BulkProgress>>,
+    settings: BulkSettings,
+) {
+    while let Some(job) = receiver.recv().await {
+        let mut progress = BulkProgress {
+            job_id: job.id,
+            total: job.actions.len(),
+            succeeded: 0,
+            failed: 0,
+            rate_limited: 0,
+        };
+
+        info!(
+            "Starting bulk job {} with {} actions",
+            job.id, progress.total
+        );
+
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for action in job.actions.iter() {
+            let mut attempt = 0;
+
+            loop {
+                match action.execute(&http).await {
+                    Ok(()) => {
+                        progress.succeeded += 1;
+                        break;
+                    }
+                    Err(error) if is_rate_limited(&error) && attempt < settings.max_retries => {
+                        attempt += 1;
+                        progress.rate_limited += 1;
+
+                        let backoff =
+                            settings.delay.max(Duration::from_secs(1)) * 2u32.pow(attempt);
+                        warn!(
+                            "Bulk job {} was rate limited. Retrying in {} ({}/{}).",
+                            job.id,
+                            humantime::format_duration(backoff),
+                            attempt,
+                            settings.max_retries
+                        );
+                        sleep(backoff).await;
+                    }
+                    Err(error) => {
+                        warn!("Bulk job {} failed an action: {}", job.id, error);
+                        progress.failed += 1;
+                        break;
+                    }
+                }
+            }
+
+            let _ = on_progress.dispatch(Arc::new(progress.clone())).await;
+            sleep(settings.delay).await;
+        }
+
+        info!(
+            "Finished bulk job {}: {} succeeded, {} failed",
+            job.id, progress.succeeded, progress.failed
+        );
+
+        // Jobs without actions still report once, so subscribers see them finish
+        if progress.total == 0 {
+            let _ = on_progress.dispatch(Arc::new(progress)).await;
+        }
+    }
+}
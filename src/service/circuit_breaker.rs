@@ -0,0 +1,58 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex;
+
+pub struct CircuitBreaker {
+    pub failure_threshold: u32,
+    pub window: Duration,
+
+    failures: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, window: Duration) -> Self {
+        Self {
+            failure_threshold,
+            window,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Returns true if the failure tripped the circuit breaker of the service
+    pub async fn record_failure(&self, service_id: &str) -> bool {
+        let now = Instant::now();
+
+        let mut failures = self.failures.lock().await;
+        let service_failures = failures.entry(service_id.to_string()).or_default();
+        service_failures.push_back(now);
+
+        while let Some(oldest) = service_failures.front() {
+            if now.duration_since(*oldest) <= self.window {
+                break;
+            }
+
+            service_failures.pop_front();
+        }
+
+        service_failures.len() >= self.failure_threshold as usize
+    }
+
+    pub async fn failure_count(&self, service_id: &str) -> usize {
+        let failures = self.failures.lock().await;
+
+        match failures.get(service_id) {
+            Some(service_failures) => service_failures
+                .iter()
+                .filter(|failure| failure.elapsed() <= self.window)
+                .count(),
+            None => 0,
+        }
+    }
+
+    pub async fn reset(&self, service_id: &str) {
+        self.failures.lock().await.remove(service_id);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::{self, Display, Formatter};
+
+use log::info;
+use serde_json::Value;
+use serenity::{
+    all::{Command, CommandId, CreateCommand, GuildId},
+    http::Http,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CommandRegistrationError {
+    #[error("Discord request failed: {0}")]
+    Discord(#[from] serenity::Error),
+
+    #[error("Unable to compare commands: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+// Without test guilds, commands are registered globally. Global commands can take a while to show up, so test guilds are useful during development.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandRegistration {
+    pub dry_run: bool,
+    pub test_guilds: Vec<GuildId>,
+}
+
+impl CommandRegistration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Only logs what would be changed
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_test_guilds(mut self, test_guilds: Vec<GuildId>) -> Self {
+        self.test_guilds = test_guilds;
+        self
+    }
+
+    pub fn is_test_guild(&self, guild_id: GuildId) -> bool {
+        self.test_guilds.contains(&guild_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandScope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Display for CommandScope {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CommandScope::Global => write!(f, "global"),
+            CommandScope::Guild(guild_id) => write!(f, "guild {}", guild_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistrationPlan {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl RegistrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
+
+impl Display for RegistrationPlan {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} created [{}], {} updated [{}], {} deleted [{}], {} unchanged",
+            self.created.len(),
+            self.created.join(", "),
+            self.updated.len(),
+            self.updated.join(", "),
+            self.deleted.len(),
+            self.deleted.join(", "),
+            self.unchanged
+        )
+    }
+}
+
+// Slash commands and context menu commands may share a name, so the type is part of the key
+fn command_key(command: &Value) -> (String, u64) {
+    let name = command
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let kind = command.get("type").and_then(Value::as_u64).unwrap_or(1);
+
+    (name, kind)
+}
+
+fn is_empty_value(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => true,
+        Some(Value::Array(array)) => array.is_empty(),
+        Some(Value::Object(object)) => object.is_empty(),
+        _ => false,
+    }
+}
+
+// Discord fills in fields that were not sent, so only the fields of the desired command are compared
+fn is_equivalent(desired: &Value, existing: Option<&Value>) -> bool {
+    if is_empty_value(Some(desired)) {
+        return is_empty_value(existing);
+    }
+
+    match (desired, existing) {
+        (Value::Object(desired), Some(Value::Object(existing))) => desired
+            .iter()
+            .all(|(key, value)| is_equivalent(value, existing.get(key))),
+        (Value::Array(desired), Some(Value::Array(existing))) => {
+            desired.len() == existing.len()
+                && desired
+                    .iter()
+                    .zip(existing.iter())
+                    .all(|(desired, existing)| is_equivalent(desired, Some(existing)))
+        }
+        (desired, Some(existing)) => desired == existing,
+        (_, None) => false,
+    }
+}
+
+enum Change {
+    Create(CreateCommand),
+    Update(CommandId, CreateCommand),
+    Delete(CommandId),
+}
+
+// Creates, updates and deletes commands of the scope one by one, so commands that did not change keep their IDs and permissions
+pub async fn sync_commands(
+    http: &Http,
+    desired: Vec<CreateCommand>,
+    scope: CommandScope,
+    dry_run: bool,
+) -> Result<RegistrationPlan, CommandRegistrationError> {
+    let existing = match scope {
+        CommandScope::Global => Command::get_global_commands_with_localizations(http).await?,
+        CommandScope::Guild(guild_id) => guild_id.get_commands_with_localizations(http).await?,
+    };
+
+    let mut existing = existing
+        .into_iter()
+        .map(|command| serde_json::to_value(&command).map(|value| (value, command)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut plan = RegistrationPlan::default();
+    let mut changes = Vec::new();
+
+    for command in desired {
+        let value = serde_json::to_value(&command)?;
+        let key = command_key(&value);
+
+        let position = existing
+            .iter()
+            .position(|(existing, _)| command_key(existing) == key);
+
+        match position {
+            Some(position) => {
+                let (existing_value, existing_command) = existing.remove(position);
+                if is_equivalent(&value, Some(&existing_value)) {
+                    plan.unchanged += 1;
+                } else {
+                    plan.updated.push(key.0);
+                    changes.push(Change::Update(existing_command.id, command));
+                }
+            }
+            None => {
+                plan.created.push(key.0);
+                changes.push(Change::Create(command));
+            }
+        }
+    }
+
+    for (_, command) in existing {
+        plan.deleted.push(command.name);
+        changes.push(Change::Delete(command.id));
+    }
+
+    if dry_run {
+        info!("Dry run of {} command registration: {}", scope, plan);
+        return Ok(plan);
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    for change in changes {
+        match (change, scope) {
+            (Change::Create(command), CommandScope::Global) => {
+                Command::create_global_command(http, command).await?;
+            }
+            (Change::Create(command), CommandScope::Guild(guild_id)) => {
+                guild_id.create_command(http, command).await?;
+            }
+            (Change::Update(command_id, command), CommandScope::Global) => {
+                Command::edit_global_command(http, command_id, command).await?;
+            }
+            (Change::Update(command_id, command), CommandScope::Guild(guild_id)) => {
+                guild_id.edit_command(http, command_id, command).await?;
+            }
+            (Change::Delete(command_id), CommandScope::Global) => {
+                Command::delete_global_command(http, command_id).await?;
+            }
+            (Change::Delete(command_id), CommandScope::Guild(guild_id)) => {
+                guild_id.delete_command(http, command_id).await?;
+            }
+        }
+    }
+
+    info!("Registered {} commands: {}", scope, plan);
+
+    Ok(plan)
+}
@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin, sync::Arc};
+
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use super::admin::AdminResponse;
+
+pub type AdminCommandFuture = Pin<Box<dyn Future<Output = AdminResponse> + Send>>;
+pub type AdminCommandHandler = Arc<dyn Fn(Vec<String>) -> AdminCommandFuture + Send + Sync>;
+
+// Names of the commands every admin frontend supports out of the box
+pub const BUILTIN_COMMANDS: &[&str] = &[
+    "help", "status", "services", "complete", "start", "stop", "restart", "reset", "loglevel",
+    "reload", "quit", "exit",
+];
+
+#[derive(Debug, Error)]
+pub enum CommandRegistryError {
+    #[error("Command name \"{0}\" is invalid. Names must not be empty and may only contain lowercase letters, digits, dashes and underscores")]
+    InvalidName(String),
+
+    #[error("Command {0} is built in and can not be overridden")]
+    Builtin(String),
+
+    #[error("Command {0} is already registered by service {1}")]
+    AlreadyRegistered(String, String),
+}
+
+#[derive(Clone)]
+pub struct AdminCommand {
+    pub name: String,
+    pub usage: String,
+    pub help: String,
+    pub owner: String,
+    handler: AdminCommandHandler,
+}
+
+impl AdminCommand {
+    pub async fn run(&self, arguments: Vec<String>) -> AdminResponse {
+        (self.handler)(arguments).await
+    }
+}
+
+// Commands contributed by services. Every admin frontend falls back to this registry for commands it does not know.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: RwLock<BTreeMap<String, AdminCommand>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The usage lists the arguments, like "<guild id> [limit]". The owner is the ID of the registering service.
+    pub async fn register(
+        &self,
+        owner: &str,
+        name: &str,
+        usage: &str,
+        help: &str,
+        handler: impl Fn(Vec<String>) -> AdminCommandFuture + Send + Sync + 'static,
+    ) -> Result<(), CommandRegistryError> {
+        let name = name.to_lowercase();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+        {
+            return Err(CommandRegistryError::InvalidName(name));
+        }
+
+        if BUILTIN_COMMANDS.contains(&name.as_str()) {
+            return Err(CommandRegistryError::Builtin(name));
+        }
+
+        let mut commands = self.commands.write().await;
+        if let Some(command) = commands.get(&name) {
+            return Err(CommandRegistryError::AlreadyRegistered(
+                name,
+                command.owner.clone(),
+            ));
+        }
+
+        commands.insert(
+            name.clone(),
+            AdminCommand {
+                name,
+                usage: usage.to_string(),
+                help: help.to_string(),
+                owner: owner.to_string(),
+                handler: Arc::new(handler),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub async fn unregister(&self, name: &str) -> Option<AdminCommand> {
+        self.commands.write().await.remove(&name.to_lowercase())
+    }
+
+    // The ServiceManager calls this when a service stops
+    pub async fn unregister_owner(&self, owner: &str) -> Vec<AdminCommand> {
+        let mut commands = self.commands.write().await;
+        let names = commands
+            .values()
+            .filter(|command| command.owner == owner)
+            .map(|command| command.name.clone())
+            .collect::<Vec<_>>();
+
+        names
+            .iter()
+            .filter_map(|name| commands.remove(name))
+            .collect()
+    }
+
+    pub async fn get(&self, name: &str) -> Option<AdminCommand> {
+        self.commands
+            .read()
+            .await
+            .get(&name.to_lowercase())
+            .cloned()
+    }
+
+    pub async fn names(&self) -> Vec<String> {
+        self.commands.read().await.keys().cloned().collect()
+    }
+
+    pub async fn help(&self) -> String {
+        let commands = self.commands.read().await;
+
+        commands
+            .values()
+            .map(|command| {
+                let usage = match command.usage.is_empty() {
+                    true => command.name.clone(),
+                    false => format!("{} {}", command.name, command.usage),
+                };
+                format!("  {:<20} {} ({})", usage, command.help, command.owner)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,269 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use poise::{
+    builtins::create_application_commands,
+    serenity_prelude::{CommandInteraction, Context, FullEvent, Message, User},
+    ApplicationContext, BoxFuture, ContextMenuCommandAction, Framework, FrameworkContext,
+    FrameworkError, FrameworkOptions, PrefixFrameworkOptions,
+};
+use tokio::sync::Mutex;
+
+use super::{
+    command_registration::{sync_commands, CommandRegistration, CommandScope},
+    BoxedError, Service, ServiceManager,
+};
+use crate::discord::Locales;
+
+pub type Command = poise::Command<CommandData, BoxedError>;
+pub type CommandContext<'a> = poise::Context<'a, CommandData, BoxedError>;
+
+pub type CommandFactory = Arc<dyn Fn() -> Vec<Command> + Send + Sync>;
+
+pub struct ContextMenuEvent<T> {
+    pub context: Context,
+    pub interaction: CommandInteraction,
+    pub target: T,
+}
+
+pub type ContextMenuFuture = Pin<Box<dyn Future<Output = Result<(), BoxedError>> + Send>>;
+
+type UserContextMenuHandler =
+    Arc<dyn Fn(ContextMenuEvent<User>) -> ContextMenuFuture + Send + Sync>;
+type MessageContextMenuHandler =
+    Arc<dyn Fn(ContextMenuEvent<Message>) -> ContextMenuFuture + Send + Sync>;
+
+// Context menu commands are dispatched to their handler by name, so modules do not have to parse interactions themselves
+#[derive(Clone, Default)]
+pub struct ContextMenus {
+    user: HashMap<String, UserContextMenuHandler>,
+    message: HashMap<String, MessageContextMenuHandler>,
+}
+
+impl ContextMenus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_user(
+        &mut self,
+        name: &str,
+        handler: impl Fn(ContextMenuEvent<User>) -> ContextMenuFuture + Send + Sync + 'static,
+    ) {
+        self.user.insert(name.to_string(), Arc::new(handler));
+    }
+
+    pub fn add_message(
+        &mut self,
+        name: &str,
+        handler: impl Fn(ContextMenuEvent<Message>) -> ContextMenuFuture + Send + Sync + 'static,
+    ) {
+        self.message.insert(name.to_string(), Arc::new(handler));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.user.is_empty() && self.message.is_empty()
+    }
+
+    pub fn commands(&self) -> Vec<Command> {
+        let user_commands = self
+            .user
+            .keys()
+            .map(|name| context_menu_command(name, ContextMenuCommandAction::User(dispatch_user)));
+        let message_commands = self.message.keys().map(|name| {
+            context_menu_command(name, ContextMenuCommandAction::Message(dispatch_message))
+        });
+
+        user_commands.chain(message_commands).collect()
+    }
+}
+
+fn context_menu_command(
+    name: &str,
+    action: ContextMenuCommandAction<CommandData, BoxedError>,
+) -> Command {
+    Command {
+        name: name.to_string(),
+        qualified_name: name.to_string(),
+        context_menu_name: Some(name.to_string()),
+        context_menu_action: Some(action),
+        ..Default::default()
+    }
+}
+
+fn dispatch_user(
+    context: ApplicationContext<'_, CommandData, BoxedError>,
+    user: User,
+) -> BoxFuture<'_, Result<(), FrameworkError<'_, CommandData, BoxedError>>> {
+    Box::pin(async move {
+        let handler = match context.data.context_menus.user.get(&context.command.name) {
+            Some(handler) => Arc::clone(handler),
+            None => return Ok(()),
+        };
+
+        let event = ContextMenuEvent {
+            context: context.serenity_context.clone(),
+            interaction: context.interaction.clone(),
+            target: user,
+        };
+
+        handler(event)
+            .await
+            .map_err(|error| FrameworkError::new_command(context.into(), error))
+    })
+}
+
+fn dispatch_message(
+    context: ApplicationContext<'_, CommandData, BoxedError>,
+    message: Message,
+) -> BoxFuture<'_, Result<(), FrameworkError<'_, CommandData, BoxedError>>> {
+    Box::pin(async move {
+        let handler = match context
+            .data
+            .context_menus
+            .message
+            .get(&context.command.name)
+        {
+            Some(handler) => Arc::clone(handler),
+            None => return Ok(()),
+        };
+
+        let event = ContextMenuEvent {
+            context: context.serenity_context.clone(),
+            interaction: context.interaction.clone(),
+            target: message,
+        };
+
+        handler(event)
+            .await
+            .map_err(|error| FrameworkError::new_command(context.into(), error))
+    })
+}
+
+// Handed to every command as poise's user data, so commands can reach lum services
+pub struct CommandData {
+    pub service_manager: Arc<ServiceManager>,
+    pub locales: Arc<Locales>,
+    context_menus: ContextMenus,
+    registration: CommandRegistration,
+}
+
+impl CommandData {
+    pub async fn service<T>(&self) -> Option<Arc<Mutex<T>>>
+    where
+        T: Service,
+    {
+        self.service_manager.get_service::<T>().await
+    }
+
+    pub async fn service_by_id<T>(&self, service_id: &str) -> Option<Arc<Mutex<T>>>
+    where
+        T: Service,
+    {
+        self.service_manager
+            .get_service_by_id_as::<T>(service_id)
+            .await
+    }
+
+    // Picks the guild's locale if one is set and the locale of the invoking user's client otherwise
+    pub async fn localize(
+        &self,
+        context: &CommandContext<'_>,
+        key: &str,
+        arguments: &[(&str, &str)],
+    ) -> String {
+        let locale = self
+            .locales
+            .resolve(context.guild_id(), context.locale())
+            .await;
+
+        self.locales.format(&locale, key, arguments)
+    }
+}
+
+// Test guilds the bot joins while running get the commands right away instead of on the next start
+fn handle_event<'a>(
+    context: &'a Context,
+    event: &'a FullEvent,
+    framework: FrameworkContext<'a, CommandData, BoxedError>,
+    data: &'a CommandData,
+) -> BoxFuture<'a, Result<(), BoxedError>> {
+    Box::pin(async move {
+        if let FullEvent::GuildCreate {
+            guild,
+            is_new: Some(true),
+        } = event
+        {
+            if data.registration.is_test_guild(guild.id) {
+                let desired = create_application_commands(&framework.options.commands);
+                sync_commands(
+                    &context.http,
+                    desired,
+                    CommandScope::Guild(guild.id),
+                    data.registration.dry_run,
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// Application commands are synced once the client is ready. Prefix commands use the given prefix.
+pub fn build_framework(
+    mut commands: Vec<Command>,
+    context_menus: ContextMenus,
+    registration: CommandRegistration,
+    prefix: &str,
+    service_manager: Arc<ServiceManager>,
+    locales: Arc<Locales>,
+) -> Framework<CommandData, BoxedError> {
+    commands.extend(context_menus.commands());
+
+    let options = FrameworkOptions {
+        commands,
+        prefix_options: PrefixFrameworkOptions {
+            prefix: Some(prefix.to_string()),
+            ..Default::default()
+        },
+        event_handler: handle_event,
+        ..Default::default()
+    };
+
+    Framework::builder()
+        .options(options)
+        .setup(move |context, _ready, framework| {
+            Box::pin(async move {
+                let desired = create_application_commands(&framework.options().commands);
+
+                if registration.test_guilds.is_empty() {
+                    sync_commands(
+                        &context.http,
+                        desired,
+                        CommandScope::Global,
+                        registration.dry_run,
+                    )
+                    .await?;
+                } else {
+                    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+                    for guild_id in registration.test_guilds.iter() {
+                        sync_commands(
+                            &context.http,
+                            desired.clone(),
+                            CommandScope::Guild(*guild_id),
+                            registration.dry_run,
+                        )
+                        .await?;
+                    }
+                }
+
+                Ok(CommandData {
+                    service_manager,
+                    locales,
+                    context_menus,
+                    registration,
+                })
+            })
+        })
+        .build()
+}
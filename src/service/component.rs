@@ -0,0 +1,518 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::{error, warn};
+use serenity::{
+    all::{
+        ButtonStyle, ComponentInteraction, CreateButton, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind, Interaction,
+        InteractionId, ModalInteraction,
+    },
+    async_trait,
+    builder::Builder,
+    client::Context,
+};
+use thiserror::Error;
+use tokio::{spawn, sync::RwLock, time::timeout};
+
+use crate::event::SubscriptionGuard;
+
+use super::{
+    discord::{DiscordEvent, DiscordService},
+    modal::{ModalBuilder, ModalValues, MAX_MODAL_INPUTS},
+    BoxedError, Priority, Service, ServiceContext, ServiceInfo,
+};
+
+// Discord rejects custom IDs longer than this
+pub const MAX_CUSTOM_ID_LENGTH: usize = 100;
+
+const CUSTOM_ID_SEPARATOR: char = ':';
+
+#[derive(Debug, Error)]
+pub enum ComponentError {
+    #[error("Invalid component module or name {0}. Only lowercase letters, digits and underscores are allowed.")]
+    InvalidName(String),
+
+    #[error("Custom ID {0} is longer than {MAX_CUSTOM_ID_LENGTH} characters")]
+    CustomIdTooLong(String),
+
+    #[error("A handler for component {0} is already registered")]
+    AlreadyRegistered(String),
+
+    #[error("Modal {0} has {1} text inputs, but Discord only allows {MAX_MODAL_INPUTS}")]
+    TooManyModalInputs(String, usize),
+}
+
+fn is_valid_component_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// Custom IDs have the format module:name or module:name:payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentId {
+    pub module: String,
+    pub name: String,
+    pub payload: Option<String>,
+}
+
+impl ComponentId {
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.splitn(3, CUSTOM_ID_SEPARATOR);
+        let module = parts.next()?;
+        let name = parts.next()?;
+        let payload = parts.next();
+
+        if !is_valid_component_name(module) || !is_valid_component_name(name) {
+            return None;
+        }
+
+        Some(Self {
+            module: module.to_string(),
+            name: name.to_string(),
+            payload: payload.map(|payload| payload.to_string()),
+        })
+    }
+
+    pub fn key(&self) -> String {
+        format!("{}{}{}", self.module, CUSTOM_ID_SEPARATOR, self.name)
+    }
+}
+
+// Builds components whose custom IDs are scoped to a module, so modules can not collide with each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentScope {
+    module: String,
+}
+
+impl ComponentScope {
+    pub fn new(module: &str) -> Result<Self, ComponentError> {
+        if !is_valid_component_name(module) {
+            return Err(ComponentError::InvalidName(module.to_string()));
+        }
+
+        Ok(Self {
+            module: module.to_string(),
+        })
+    }
+
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    pub fn custom_id(&self, name: &str, payload: Option<&str>) -> Result<String, ComponentError> {
+        if !is_valid_component_name(name) {
+            return Err(ComponentError::InvalidName(name.to_string()));
+        }
+
+        let custom_id = match payload {
+            Some(payload) => format!(
+                "{}{}{}{}{}",
+                self.module, CUSTOM_ID_SEPARATOR, name, CUSTOM_ID_SEPARATOR, payload
+            ),
+            None => format!("{}{}{}", self.module, CUSTOM_ID_SEPARATOR, name),
+        };
+
+        if custom_id.chars().count() > MAX_CUSTOM_ID_LENGTH {
+            return Err(ComponentError::CustomIdTooLong(custom_id));
+        }
+
+        Ok(custom_id)
+    }
+
+    pub fn button(
+        &self,
+        name: &str,
+        payload: Option<&str>,
+        label: &str,
+        style: ButtonStyle,
+    ) -> Result<CreateButton, ComponentError> {
+        let custom_id = self.custom_id(name, payload)?;
+
+        Ok(CreateButton::new(custom_id).label(label).style(style))
+    }
+
+    pub fn select_menu(
+        &self,
+        name: &str,
+        payload: Option<&str>,
+        kind: CreateSelectMenuKind,
+    ) -> Result<CreateSelectMenu, ComponentError> {
+        let custom_id = self.custom_id(name, payload)?;
+
+        Ok(CreateSelectMenu::new(custom_id, kind))
+    }
+
+    pub fn modal(
+        &self,
+        name: &str,
+        payload: Option<&str>,
+        title: &str,
+    ) -> Result<ModalBuilder, ComponentError> {
+        let custom_id = self.custom_id(name, payload)?;
+
+        Ok(ModalBuilder::new(custom_id, title))
+    }
+}
+
+pub struct ComponentEvent {
+    pub context: Context,
+    pub interaction: ComponentInteraction,
+    pub id: ComponentId,
+}
+
+pub struct ModalEvent {
+    pub context: Context,
+    pub interaction: ModalInteraction,
+    pub id: ComponentId,
+    pub values: ModalValues,
+}
+
+// Handler futures only have to be Send, because they are run in their own task
+pub type ComponentHandlerFuture = Pin<Box<dyn Future<Output = Result<(), BoxedError>> + Send>>;
+
+type ComponentHandler = Arc<dyn Fn(ComponentEvent) -> ComponentHandlerFuture + Send + Sync>;
+type ModalHandler = Arc<dyn Fn(ModalEvent) -> ComponentHandlerFuture + Send + Sync>;
+
+#[derive(Clone)]
+enum Handler {
+    Component(ComponentHandler),
+    Modal(ModalHandler),
+}
+
+struct RegisteredHandler {
+    handler: Handler,
+    expires_at: Option<Instant>,
+}
+
+impl RegisteredHandler {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() >= expires_at)
+    }
+}
+
+type ComponentHandlers = HashMap<String, RegisteredHandler>;
+
+pub struct ComponentService {
+    info: ServiceInfo,
+    handler_timeout: Duration,
+    expired_message: String,
+    handlers: Arc<RwLock<ComponentHandlers>>,
+    subscription: Option<SubscriptionGuard<DiscordEvent<Interaction>>>,
+}
+
+impl ComponentService {
+    pub fn new() -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_components", "Components", Priority::Normal)
+                .with_group("discord")
+                .with_dependency::<DiscordService>(),
+            handler_timeout: Duration::from_secs(15),
+            expired_message: "This interaction has expired.".to_string(),
+            handlers: Arc::new(RwLock::new(HashMap::new())),
+            subscription: None,
+        }
+    }
+
+    // Each bot account needs its own ComponentService, so the instance is appended to the service ID
+    pub fn with_discord_instance(mut self, instance: &str) -> Self {
+        let id = format!("{}_{}", self.info.id, instance);
+        self.info = self
+            .info
+            .with_id(&id)
+            .with_instance_dependency::<DiscordService>(&DiscordService::instance_id(instance));
+        self
+    }
+
+    pub fn with_handler_timeout(mut self, handler_timeout: Duration) -> Self {
+        self.handler_timeout = handler_timeout;
+        self
+    }
+
+    // Sent to users interacting with components that have no handler (anymore), e.g. after a restart
+    pub fn with_expired_message(mut self, expired_message: &str) -> Self {
+        self.expired_message = expired_message.to_string();
+        self
+    }
+
+    pub async fn register(
+        &self,
+        scope: &ComponentScope,
+        name: &str,
+        handler: impl Fn(ComponentEvent) -> ComponentHandlerFuture + Send + Sync + 'static,
+    ) -> Result<(), ComponentError> {
+        self.register_handler(scope, name, Handler::Component(Arc::new(handler)), None)
+            .await
+    }
+
+    // The handler is unregistered once it expires, and interactions with its components are answered with the expired message
+    pub async fn register_expiring(
+        &self,
+        scope: &ComponentScope,
+        name: &str,
+        expires_after: Duration,
+        handler: impl Fn(ComponentEvent) -> ComponentHandlerFuture + Send + Sync + 'static,
+    ) -> Result<(), ComponentError> {
+        self.register_handler(
+            scope,
+            name,
+            Handler::Component(Arc::new(handler)),
+            Some(Instant::now() + expires_after),
+        )
+        .await
+    }
+
+    // Modals and components of a scope share their names, so a modal can not be named like a button of the same scope
+    pub async fn register_modal(
+        &self,
+        scope: &ComponentScope,
+        name: &str,
+        handler: impl Fn(ModalEvent) -> ComponentHandlerFuture + Send + Sync + 'static,
+    ) -> Result<(), ComponentError> {
+        self.register_handler(scope, name, Handler::Modal(Arc::new(handler)), None)
+            .await
+    }
+
+    async fn register_handler(
+        &self,
+        scope: &ComponentScope,
+        name: &str,
+        handler: Handler,
+        expires_at: Option<Instant>,
+    ) -> Result<(), ComponentError> {
+        let key = scope.custom_id(name, None)?;
+
+        let mut handlers = self.handlers.write().await;
+        handlers.retain(|_, handler| !handler.is_expired());
+
+        if handlers.contains_key(&key) {
+            return Err(ComponentError::AlreadyRegistered(key));
+        }
+
+        handlers.insert(
+            key,
+            RegisteredHandler {
+                handler,
+                expires_at,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub async fn unregister(&self, scope: &ComponentScope, name: &str) -> bool {
+        let key = format!("{}{}{}", scope.module(), CUSTOM_ID_SEPARATOR, name);
+
+        let mut handlers = self.handlers.write().await;
+        handlers.remove(&key).is_some()
+    }
+
+    // Returns how many handlers were unregistered
+    pub async fn unregister_scope(&self, scope: &ComponentScope) -> usize {
+        let prefix = format!("{}{}", scope.module(), CUSTOM_ID_SEPARATOR);
+
+        let mut handlers = self.handlers.write().await;
+        let count = handlers.len();
+        handlers.retain(|key, _| !key.starts_with(&prefix));
+
+        count - handlers.len()
+    }
+
+    async fn dispatch(
+        handlers: &RwLock<ComponentHandlers>,
+        handler_timeout: Duration,
+        expired_message: &str,
+        event: &DiscordEvent<Interaction>,
+    ) {
+        let (custom_id, interaction_id, token) = match &event.data {
+            Interaction::Component(interaction) => (
+                &interaction.data.custom_id,
+                interaction.id,
+                &interaction.token,
+            ),
+            Interaction::Modal(interaction) => (
+                &interaction.data.custom_id,
+                interaction.id,
+                &interaction.token,
+            ),
+            _ => return,
+        };
+
+        // Components that were not built through a ComponentScope are left to other handlers
+        let id = match ComponentId::parse(custom_id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let key = id.key();
+        let handler = {
+            let handlers = handlers.read().await;
+            handlers
+                .get(&key)
+                .filter(|handler| !handler.is_expired())
+                .map(|handler| handler.handler.clone())
+        };
+
+        let context = event.context.clone();
+        let handler_future = match (handler, &event.data) {
+            (Some(Handler::Component(handler)), Interaction::Component(interaction)) => {
+                handler(ComponentEvent {
+                    context,
+                    interaction: interaction.clone(),
+                    id,
+                })
+            }
+            (Some(Handler::Modal(handler)), Interaction::Modal(interaction)) => {
+                handler(ModalEvent {
+                    context,
+                    interaction: interaction.clone(),
+                    id,
+                    values: ModalValues::from_interaction(interaction),
+                })
+            }
+            (Some(_), _) => {
+                warn!(
+                    "Handler for {} does not handle this kind of interaction",
+                    key
+                );
+                respond_expired(
+                    &event.context,
+                    interaction_id,
+                    token,
+                    custom_id,
+                    expired_message,
+                )
+                .await;
+                return;
+            }
+            (None, _) => {
+                handlers
+                    .write()
+                    .await
+                    .retain(|_, handler| !handler.is_expired());
+                respond_expired(
+                    &event.context,
+                    interaction_id,
+                    token,
+                    custom_id,
+                    expired_message,
+                )
+                .await;
+                return;
+            }
+        };
+
+        match timeout(handler_timeout, handler_future).await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => error!("Interaction handler for {} failed: {}", key, error),
+            Err(_) => {
+                warn!(
+                    "Interaction handler for {} did not finish within {}",
+                    key,
+                    humantime::format_duration(handler_timeout)
+                );
+                respond_expired(
+                    &event.context,
+                    interaction_id,
+                    token,
+                    custom_id,
+                    expired_message,
+                )
+                .await;
+            }
+        }
+    }
+}
+
+impl Default for ComponentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Only logs if the interaction was already responded to
+async fn respond_expired(
+    context: &Context,
+    interaction_id: InteractionId,
+    token: &str,
+    custom_id: &str,
+    message: &str,
+) {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(message)
+            .ephemeral(true),
+    );
+
+    if let Err(error) = response
+        .execute(&context.http, (interaction_id, token))
+        .await
+    {
+        warn!(
+            "Unable to respond to expired interaction {}: {}",
+            custom_id, error
+        );
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for ComponentService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        let discord_service = context.dependency::<DiscordService>()?;
+        let events = Arc::clone(&discord_service.lock().await.events);
+
+        let handlers = Arc::clone(&self.handlers);
+        let handler_timeout = self.handler_timeout;
+        let expired_message = self.expired_message.clone();
+
+        let subscription = events
+            .on_interaction_create
+            .subscribe_async_closure(
+                "lum_builtin_components",
+                move |event| {
+                    let handlers = Arc::clone(&handlers);
+                    let expired_message = expired_message.clone();
+
+                    // Spawned so slow handlers do not hold up the other subscribers of the event
+                    spawn(async move {
+                        ComponentService::dispatch(
+                            &handlers,
+                            handler_timeout,
+                            &expired_message,
+                            &event,
+                        )
+                        .await;
+                    });
+
+                    Box::pin(async { Ok(()) })
+                },
+                true,
+                false,
+            )
+            .await;
+
+        self.subscription = Some(subscription);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        // Dropping the guard unsubscribes from the interactions
+        self.subscription = None;
+
+        Ok(())
+    }
+}
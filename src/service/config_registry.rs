@@ -0,0 +1,86 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Error)]
+pub enum ConfigRegistryError {
+    #[error("No config of type {0} has been registered")]
+    Missing(&'static str),
+}
+
+type StoredConfig = Arc<dyn Any + Send + Sync>;
+
+// Configs by their type, so services can look up what they need instead of having it passed to their constructor.
+// Services usually get their config in start(), so a reloaded config is picked up when they are restarted.
+#[derive(Default)]
+pub struct ConfigRegistry {
+    configs: RwLock<HashMap<TypeId, StoredConfig>>,
+}
+
+impl ConfigRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config<T>(mut self, config: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.configs
+            .get_mut()
+            .insert(TypeId::of::<T>(), Arc::new(config));
+        self
+    }
+
+    // Replaces the config of the same type, if there is one
+    pub async fn insert<T>(&self, config: T) -> Option<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let old = self
+            .configs
+            .write()
+            .await
+            .insert(TypeId::of::<T>(), Arc::new(config))?;
+
+        old.downcast().ok()
+    }
+
+    pub async fn remove<T>(&self) -> Option<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let config = self.configs.write().await.remove(&TypeId::of::<T>())?;
+        config.downcast().ok()
+    }
+
+    pub async fn get<T>(&self) -> Option<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let config = self.configs.read().await.get(&TypeId::of::<T>())?.clone();
+        config.downcast().ok()
+    }
+
+    // Like get, but with an error that can be returned from Service::start
+    pub async fn require<T>(&self) -> Result<Arc<T>, ConfigRegistryError>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.get()
+            .await
+            .ok_or(ConfigRegistryError::Missing(type_name::<T>()))
+    }
+
+    pub async fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.configs.read().await.contains_key(&TypeId::of::<T>())
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::{Duration, SystemTime},
+};
+
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::async_trait;
+use tokio::{
+    select,
+    sync::{mpsc, RwLock},
+    task::spawn_blocking,
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service, ServiceContext, ServiceInfo,
+    ServiceManager,
+};
+use crate::{
+    config::{ConfigHandler, Merge, Redact, Validate},
+    event::Event,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    // Like webhooks.announcements, as the field appears in the config file
+    pub path: String,
+    // None if the field did not exist before or does not exist anymore
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let describe = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => String::from("(unset)"),
+        };
+
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            describe(&self.old),
+            describe(&self.new)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChanged<FILE> {
+    pub old: FILE,
+    pub new: FILE,
+    // Values of secrets are redacted, so the changes can be logged
+    pub changes: Vec<FieldChange>,
+}
+
+impl<FILE> ConfigChanged<FILE>
+where
+    FILE: Serialize + Redact,
+{
+    pub fn new(old: FILE, new: FILE) -> Self {
+        let changes = match (serde_json::to_value(&old), serde_json::to_value(&new)) {
+            (Ok(old_value), Ok(new_value)) => {
+                let mut paths = Vec::new();
+                diff_paths(&old_value, &new_value, "", &mut paths);
+
+                // Changes are found on the real values, so changing a secret shows up, but only redacted values are reported
+                let redacted_old = serde_json::to_value(old.redacted()).unwrap_or_default();
+                let redacted_new = serde_json::to_value(new.redacted()).unwrap_or_default();
+                paths
+                    .into_iter()
+                    .map(|path| FieldChange {
+                        old: value_at(&redacted_old, &path).cloned(),
+                        new: value_at(&redacted_new, &path).cloned(),
+                        path,
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Self { old, new, changes }
+    }
+
+    // Names of the top-level fields that differ, as they appear in the config file
+    pub fn changed_fields(&self) -> Vec<String> {
+        let mut fields = self
+            .changes
+            .iter()
+            .map(|change| {
+                change
+                    .path
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        fields.dedup();
+
+        fields
+    }
+}
+
+// Objects are compared field by field, everything else as a whole. Paths are sorted, as the fields of a Value are.
+fn diff_paths(old: &Value, new: &Value, prefix: &str, paths: &mut Vec<String>) {
+    if let (Value::Object(old_fields), Value::Object(new_fields)) = (old, new) {
+        let mut names = old_fields
+            .keys()
+            .chain(new_fields.keys())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        for name in names {
+            let path = match prefix.is_empty() {
+                true => name.clone(),
+                false => format!("{}.{}", prefix, name),
+            };
+
+            match (old_fields.get(name), new_fields.get(name)) {
+                (Some(old), Some(new)) => diff_paths(old, new, &path, paths),
+                _ => paths.push(path),
+            }
+        }
+    } else if old != new {
+        paths.push(prefix.to_string());
+    }
+}
+
+fn value_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, name| value.get(name))
+}
+
+// The directory of the config file is watched instead of the file, so saves that replace the file are noticed as well.
+// A reload requested through the ServiceManager (e.g. by the reload admin command) is applied immediately.
+pub struct ConfigWatcherService<FILE, ENV, CLI = ()>
+where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de>,
+{
+    info: ServiceInfo,
+    config_handler: Arc<ConfigHandler<FILE, ENV, CLI>>,
+    debounce: Duration,
+    config: Arc<RwLock<FILE>>,
+    service_manager: Option<Weak<ServiceManager>>,
+    pub on_config_changed: Arc<Event<ConfigChanged<FILE>>>,
+}
+
+impl<FILE, ENV, CLI> ConfigWatcherService<FILE, ENV, CLI>
+where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Redact
+        + Clone
+        + PartialEq
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de> + Send + Sync,
+{
+    // The config is the one the bot was started with, so the first change is detected against it
+    pub fn new(config_handler: ConfigHandler<FILE, ENV, CLI>, config: FILE) -> Self {
+        Self {
+            info: ServiceInfo::new(
+                "lum_builtin_config_watcher",
+                "Config Watcher",
+                Priority::Low,
+            ),
+            config_handler: Arc::new(config_handler),
+            debounce: Duration::from_millis(500),
+            config: Arc::new(RwLock::new(config)),
+            service_manager: None,
+            on_config_changed: Arc::new(Event::new("config_watcher_on_config_changed")),
+        }
+    }
+
+    // Editors often save a file in several steps, which should cause a single reload
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub async fn config(&self) -> FILE {
+        self.config.read().await.clone()
+    }
+}
+
+// Covers the overlays as well, so adding, removing or editing one counts as a change
+fn modified_at<FILE, ENV, CLI>(
+    config_handler: &ConfigHandler<FILE, ENV, CLI>,
+) -> Vec<Option<SystemTime>>
+where
+    FILE: Serialize + for<'de> Deserialize<'de> + Merge<ENV> + Merge<CLI> + Validate + Default,
+    ENV: Serialize + for<'de> Deserialize<'de>,
+{
+    let mut paths = config_handler
+        .get_config_file_path()
+        .into_iter()
+        .collect::<Vec<_>>();
+    paths.extend(
+        config_handler
+            .get_config_overlay_file_paths()
+            .unwrap_or_default(),
+    );
+
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).ok()?.modified().ok())
+        .collect()
+}
+
+// The overlay directory may be created after the bot started, so it is watched once it exists
+fn watch_overlay_dir(watcher: &mut RecommendedWatcher, overlay_dir_path: &Path) {
+    if !overlay_dir_path.is_dir() {
+        return;
+    }
+
+    if let Err(err) = watcher.watch(overlay_dir_path, RecursiveMode::NonRecursive) {
+        warn!(
+            "Unable to watch config overlays in {}: {}",
+            overlay_dir_path.display(),
+            err
+        );
+    }
+}
+
+// Other files next to the config, like logs or the schema, are ignored
+fn is_config_event(
+    event: &notify::Event,
+    config_file_path: &Path,
+    overlay_dir_path: &Path,
+) -> bool {
+    if event.kind.is_access() {
+        return false;
+    }
+
+    event.paths.iter().any(|path| {
+        path == config_file_path
+            || path == overlay_dir_path
+            || path.parent() == Some(overlay_dir_path)
+    })
+}
+
+async fn reload<FILE, ENV, CLI>(
+    service_manager: &ServiceManager,
+    config_handler: &Arc<ConfigHandler<FILE, ENV, CLI>>,
+    config: &RwLock<FILE>,
+    on_config_changed: &Event<ConfigChanged<FILE>>,
+) where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Redact
+        + Clone
+        + PartialEq
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    CLI: Send + Sync + 'static,
+{
+    // Loading reads and may rewrite the files, which must not block the runtime
+    let loading_config_handler = Arc::clone(config_handler);
+    let loaded = spawn_blocking(move || loading_config_handler.load_config()).await;
+
+    // An invalid config is usually caught mid-edit, so the current one stays active until the file is fixed
+    let new_config = match loaded {
+        Ok(Ok(new_config)) => new_config,
+        Ok(Err(err)) => {
+            warn!("Unable to reload config, keeping the current one: {}", err);
+            return;
+        }
+        Err(err) => {
+            warn!("Unable to reload config, keeping the current one: {}", err);
+            return;
+        }
+    };
+
+    let mut config = config.write().await;
+    if *config == new_config {
+        return;
+    }
+
+    let change = ConfigChanged::new(config.clone(), new_config.clone());
+    *config = new_config.clone();
+    drop(config);
+
+    // Services that look up their config when they start get the new one after a restart
+    service_manager.config_registry.insert(new_config).await;
+
+    info!(
+        "Config changed:\n{}",
+        change
+            .changes
+            .iter()
+            .map(|change| format!(" - {}", change))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let _ = on_config_changed.dispatch(Arc::new(change)).await;
+}
+
+async fn last_modified_at<FILE, ENV, CLI>(
+    config_handler: &Arc<ConfigHandler<FILE, ENV, CLI>>,
+) -> Vec<Option<SystemTime>>
+where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    CLI: Send + Sync + 'static,
+{
+    let config_handler = Arc::clone(config_handler);
+    spawn_blocking(move || modified_at(&config_handler))
+        .await
+        .unwrap_or_default()
+}
+
+async fn watch<FILE, ENV, CLI>(
+    service_manager: Weak<ServiceManager>,
+    config_handler: Arc<ConfigHandler<FILE, ENV, CLI>>,
+    config: Arc<RwLock<FILE>>,
+    on_config_changed: Arc<Event<ConfigChanged<FILE>>>,
+    debounce: Duration,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError>
+where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Redact
+        + Clone
+        + PartialEq
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    CLI: Send + Sync + 'static,
+{
+    let config_file_path = config_handler.get_config_file_path()?;
+    let overlay_dir_path = config_handler.get_config_overlay_dir_path()?;
+    let config_dir_path = match config_file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    // Events only trigger a reload, so when the channel is full, a reload is pending anyway
+    let (event_sender, mut events) = mpsc::channel(16);
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = event_sender.try_send(event);
+    })?;
+    watcher.watch(&config_dir_path, RecursiveMode::NonRecursive)?;
+    watch_overlay_dir(&mut watcher, &overlay_dir_path);
+
+    let (subscription, mut reload_requests) = match service_manager.upgrade() {
+        Some(service_manager) => {
+            service_manager
+                .on_reload_requested
+                .subscribe_channel("config_watcher", 4, true, true)
+                .await
+        }
+        None => return Ok(()),
+    };
+
+    let mut reload_requests_open = true;
+    let mut last_modified = last_modified_at(&config_handler).await;
+    loop {
+        select! {
+            _ = cancellation_token.cancelled() => break,
+            request = reload_requests.recv(), if reload_requests_open => {
+                // Without reload requests, the files are still watched until the service is stopped
+                if request.is_none() {
+                    reload_requests_open = false;
+                    continue;
+                }
+            },
+            event = events.recv() => {
+                match event {
+                    Some(Ok(event)) if is_config_event(&event, &config_file_path, &overlay_dir_path) => {
+                        if event.paths.contains(&overlay_dir_path) {
+                            watch_overlay_dir(&mut watcher, &overlay_dir_path);
+                        }
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => {
+                        warn!("Error while watching the config: {}", err);
+                        continue;
+                    }
+                    None => return Err("The config file watcher stopped unexpectedly".into()),
+                }
+
+                select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = sleep(debounce) => {}
+                }
+                while events.try_recv().is_ok() {}
+
+                if last_modified_at(&config_handler).await == last_modified {
+                    continue;
+                }
+            },
+        }
+
+        let service_manager = match service_manager.upgrade() {
+            Some(service_manager) => service_manager,
+            None => break,
+        };
+        reload(
+            &service_manager,
+            &config_handler,
+            &config,
+            &on_config_changed,
+        )
+        .await;
+
+        // Loading may rewrite the file to add missing fields, which must not count as another change
+        last_modified = last_modified_at(&config_handler).await;
+    }
+
+    if let Some(service_manager) = service_manager.upgrade() {
+        service_manager
+            .on_reload_requested
+            .unsubscribe(&subscription)
+            .await;
+    }
+
+    Ok(())
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl<FILE, ENV, CLI> Service for ConfigWatcherService<FILE, ENV, CLI>
+where
+    FILE: Serialize
+        + for<'de> Deserialize<'de>
+        + Merge<ENV>
+        + Merge<CLI>
+        + Validate
+        + Default
+        + Redact
+        + Clone
+        + PartialEq
+        + Send
+        + Sync
+        + 'static,
+    ENV: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    CLI: Send + Sync + 'static,
+{
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.service_manager = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let service_manager = Weak::clone(self.service_manager.as_ref()?);
+        let config_handler = Arc::clone(&self.config_handler);
+        let config = Arc::clone(&self.config);
+        let on_config_changed = Arc::clone(&self.on_config_changed);
+        let debounce = self.debounce;
+
+        Some(Box::pin(async move {
+            watch(
+                service_manager,
+                config_handler,
+                config,
+                on_config_changed,
+                debounce,
+                cancellation_token,
+            )
+            .await
+        }))
+    }
+}
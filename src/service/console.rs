@@ -0,0 +1,342 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    sync::{Arc, Weak},
+    thread,
+};
+
+use ::log::warn;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::FileHistory, validate::Validator, CompletionType, Config, Context, Editor,
+    ExternalPrinter, Helper,
+};
+use serenity::async_trait;
+use tokio::{
+    io::{stdin, AsyncBufReadExt, BufReader},
+    runtime::Handle,
+    select, spawn,
+    sync::oneshot,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::log;
+
+use super::{
+    admin::{completions, execute, AdminRequest},
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service, ServiceContext, ServiceInfo,
+    ServiceManager,
+};
+
+#[cfg(unix)]
+use nix::sys::termios::{self, SetArg};
+
+const PROMPT: &str = "> ";
+const HISTORY_CAPACITY: usize = 500;
+
+// Interactive admin console on stdin. Headless deployments without a terminal should not register it.
+pub struct ConsoleService {
+    info: ServiceInfo,
+    history_file: Option<PathBuf>,
+    service_manager: Option<Weak<ServiceManager>>,
+}
+
+impl ConsoleService {
+    pub fn new() -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_console", "Console", Priority::Low),
+            history_file: None,
+            service_manager: None,
+        }
+    }
+
+    // Without a configured value, the console is only started if stdin is a terminal.
+    // Headless deployments, e.g. in Docker or under systemd, have nobody to type into it.
+    pub fn is_enabled(console: Option<bool>) -> bool {
+        console.unwrap_or_else(|| io::stdin().is_terminal())
+    }
+
+    // Without a history file, the history is lost when the bot exits
+    pub fn with_history_file(mut self, history_file: PathBuf) -> Self {
+        self.history_file = Some(history_file);
+        self
+    }
+}
+
+impl Default for ConsoleService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A task that ends on its own counts as failed, so the console idles once its input is closed
+async fn idle_until_stopped(cancellation_token: CancellationToken) -> Result<(), BoxedError> {
+    warn!("Console input was closed");
+    cancellation_token.cancelled().await;
+
+    Ok(())
+}
+
+async fn read_commands(
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError> {
+    let mut lines = BufReader::new(stdin()).lines();
+
+    loop {
+        let line = select! {
+            _ = cancellation_token.cancelled() => return Ok(()),
+            line = lines.next_line() => line?,
+        };
+
+        // Stdin was closed, e.g. when running without a terminal
+        let line = match line {
+            Some(line) => line,
+            None => return idle_until_stopped(cancellation_token).await,
+        };
+
+        let service_manager = match service_manager.upgrade() {
+            Some(service_manager) => service_manager,
+            None => return Ok(()),
+        };
+
+        let request = match AdminRequest::parse(&line) {
+            Some(request) => request,
+            None => continue,
+        };
+
+        let response = execute(&service_manager, &own_id, &request).await;
+        println!("{}", response.output);
+    }
+}
+
+// Completes commands and service IDs with the same candidates as the admin socket
+struct ConsoleHelper {
+    service_manager: Weak<ServiceManager>,
+    runtime: Handle,
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.trim_end_matches(|c: char| !c.is_whitespace()).len();
+
+        let service_manager = match self.service_manager.upgrade() {
+            Some(service_manager) => service_manager,
+            None => return Ok((start, Vec::new())),
+        };
+
+        let preceding_words = line[..start]
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let candidates = self.runtime.block_on(completions(
+            &service_manager,
+            &preceding_words,
+            &line[start..],
+        ));
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {}
+
+impl Helper for ConsoleHelper {}
+
+// rustyline blocks while reading, so the editor runs on its own thread and executes the commands through the runtime.
+// Returns when the input is closed with Ctrl-D.
+fn run_editor(
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+    history_file: Option<PathBuf>,
+    runtime: Handle,
+    cancellation_token: CancellationToken,
+) -> rustyline::Result<()> {
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .max_history_size(HISTORY_CAPACITY)?
+        .history_ignore_dups(true)?
+        .auto_add_history(true)
+        // Ctrl-C shuts the bot down, like it does without the console
+        .enable_signals(true)
+        .build();
+
+    let mut editor: Editor<ConsoleHelper, FileHistory> = Editor::with_config(config)?;
+    editor.set_helper(Some(ConsoleHelper {
+        service_manager: Weak::clone(&service_manager),
+        runtime: runtime.clone(),
+    }));
+
+    if let Some(history_file) = &history_file {
+        match editor.load_history(history_file) {
+            Ok(()) => {}
+            Err(ReadlineError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => warn!(
+                "Unable to load console history from {}: {}",
+                history_file.display(),
+                err
+            ),
+        }
+    }
+
+    // Log lines are printed above the prompt instead of into the line being edited.
+    // The printer waits for the editor to show the line, so lines logged by the editor itself are written directly.
+    let editor_thread = thread::current().id();
+    match editor.create_external_printer() {
+        Ok(mut printer) => log::set_printer(Some(Box::new(move |line| {
+            if thread::current().id() == editor_thread {
+                let _ = io::stdout().write_all(line.as_bytes());
+            } else {
+                let _ = printer.print(line);
+            }
+        }))),
+        Err(err) => warn!("Log output may interrupt the console: {}", err),
+    }
+
+    // Commands like quit stop the console, which must not take over the terminal again afterwards
+    while !cancellation_token.is_cancelled() {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                log::set_printer(None);
+                return Err(err);
+            }
+        };
+
+        if let Some(history_file) = &history_file {
+            if let Err(err) = editor.append_history(history_file) {
+                warn!(
+                    "Unable to save console history to {}: {}",
+                    history_file.display(),
+                    err
+                );
+            }
+        }
+
+        let service_manager = match service_manager.upgrade() {
+            Some(service_manager) => service_manager,
+            None => break,
+        };
+
+        if let Some(request) = AdminRequest::parse(&line) {
+            let response = runtime.block_on(execute(&service_manager, &own_id, &request));
+            println!("{}", response.output);
+        }
+    }
+
+    log::set_printer(None);
+    Ok(())
+}
+
+// Line editing with history and tab completion of commands and service IDs
+async fn edit_commands(
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+    history_file: Option<PathBuf>,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError> {
+    // The editor can't be interrupted while it waits for input, so the terminal is restored from here when the console stops
+    #[cfg(unix)]
+    let terminal_mode = termios::tcgetattr(io::stdin())?;
+
+    let (sender, receiver) = oneshot::channel();
+    let runtime = Handle::current();
+    let editor_cancellation_token = cancellation_token.clone();
+
+    // Not spawned as a blocking task, because the runtime waits for those when shutting down.
+    // The thread ends with the next key press after the console stopped.
+    thread::spawn(move || {
+        let _ = sender.send(run_editor(
+            service_manager,
+            own_id,
+            history_file,
+            runtime,
+            editor_cancellation_token,
+        ));
+    });
+
+    let result = select! {
+        _ = cancellation_token.cancelled() => None,
+        result = receiver => Some(result),
+    };
+
+    match result {
+        None => {
+            log::set_printer(None);
+
+            #[cfg(unix)]
+            termios::tcsetattr(io::stdin(), SetArg::TCSANOW, &terminal_mode)?;
+
+            Ok(())
+        }
+        Some(Ok(Ok(()))) => idle_until_stopped(cancellation_token).await,
+        Some(Ok(Err(err))) => Err(err.into()),
+        Some(Err(_)) => {
+            log::set_printer(None);
+            Err("The console editor thread panicked".into())
+        }
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for ConsoleService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    // Only a weak reference is kept, so the console does not keep the ServiceManager alive
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.service_manager = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let service_manager = Weak::clone(self.service_manager.as_ref()?);
+        let own_id = self.info.id.clone();
+
+        if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            let history_file = self.history_file.clone();
+
+            return Some(Box::pin(async move {
+                spawn(async move {
+                    edit_commands(service_manager, own_id, history_file, cancellation_token).await
+                })
+                .await?
+            }));
+        }
+
+        // Reading stdin is not Sync, but the task has to be, so the loop runs in its own task
+        Some(Box::pin(async move {
+            spawn(async move { read_commands(service_manager, own_id, cancellation_token).await })
+                .await?
+        }))
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    any::{type_name, TypeId},
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use super::{Service, ServiceHandle, ServiceManager};
+
+// Without a service ID, any registered service of the type satisfies the dependency
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct ServiceDependency {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    pub service_id: Option<String>,
+}
+
+impl ServiceDependency {
+    pub fn of<T>() -> Self
+    where
+        T: Service,
+    {
+        Self {
+            type_id: TypeId::of::<Mutex<T>>(),
+            type_name: type_name::<T>(),
+            service_id: None,
+        }
+    }
+
+    // For services that can be registered multiple times, like one DiscordService per bot account
+    pub fn of_instance<T>(service_id: &str) -> Self
+    where
+        T: Service,
+    {
+        Self {
+            service_id: Some(service_id.to_string()),
+            ..Self::of::<T>()
+        }
+    }
+
+    pub fn is_type_of<T>(&self) -> bool
+    where
+        T: Service,
+    {
+        self.type_id == TypeId::of::<Mutex<T>>()
+    }
+
+    pub fn is_satisfied_by(&self, service: &ServiceHandle, service_id: &str) -> bool {
+        service.type_id() == self.type_id
+            && self
+                .service_id
+                .as_ref()
+                .map_or(true, |dependency_id| dependency_id == service_id)
+    }
+}
+
+impl Display for ServiceDependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.service_id {
+            Some(service_id) => write!(f, "{} ({})", self.type_name, service_id),
+            None => write!(f, "{}", self.type_name),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DependencyError {
+    #[error("Service {0} was not declared as a dependency")]
+    NotDeclared(&'static str),
+
+    #[error("Service {0} with ID {1} was not declared as a dependency")]
+    InstanceNotDeclared(&'static str, String),
+}
+
+// Dependencies are keyed by the ID of the service that satisfied them
+pub struct ServiceContext {
+    pub service_manager: Arc<ServiceManager>,
+    dependencies: HashMap<String, ServiceHandle>,
+}
+
+impl ServiceContext {
+    pub fn new(
+        service_manager: Arc<ServiceManager>,
+        dependencies: HashMap<String, ServiceHandle>,
+    ) -> Self {
+        Self {
+            service_manager,
+            dependencies,
+        }
+    }
+
+    pub fn dependency<T>(&self) -> Result<Arc<Mutex<T>>, DependencyError>
+    where
+        T: Service,
+    {
+        self.dependencies
+            .values()
+            .find_map(|service| service.downcast::<T>())
+            .ok_or(DependencyError::NotDeclared(type_name::<T>()))
+    }
+
+    pub fn dependency_instance<T>(&self, service_id: &str) -> Result<Arc<Mutex<T>>, DependencyError>
+    where
+        T: Service,
+    {
+        self.dependencies
+            .get(service_id)
+            .and_then(|service| service.downcast::<T>())
+            .ok_or_else(|| {
+                DependencyError::InstanceNotDeclared(type_name::<T>(), service_id.to_string())
+            })
+    }
+}
@@ -1,55 +1,484 @@
-use super::{BoxedError, Priority, Service, ServiceInfo, ServiceManager};
-use log::{error, info, warn};
+#[cfg(feature = "poise")]
+use super::command_registration::CommandRegistration;
+#[cfg(feature = "poise")]
+use super::commands::{
+    build_framework, Command, CommandFactory, ContextMenuEvent, ContextMenuFuture, ContextMenus,
+};
+use super::{
+    bulk::{run_bulk_worker, BulkAction, BulkError, BulkJob, BulkProgress, BulkSettings},
+    discord_admin,
+    discord_data::{DataError, SharedData},
+    discord_handlers::{EventHandlers, MultiplexedFramework, RawEventMultiplexer},
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Readiness, Service, ServiceContext,
+    ServiceInfo, Status,
+};
+use crate::{
+    discord::Locales,
+    event::{Event, Observable},
+};
+use log::{info, warn};
+#[cfg(feature = "poise")]
+use serenity::all::User;
 use serenity::{
-    all::{GatewayIntents, Ready},
+    all::{
+        GatewayIntents, GuildId, Interaction, Member, Message, Reaction, Ready, ResumedEvent,
+        ShardId,
+    },
     async_trait,
-    client::{self, Cache, Context},
-    framework::{standard::Configuration, StandardFramework},
-    gateway::{ShardManager, VoiceGatewayManager},
+    client::{self, Cache, Context, RawEventHandler},
+    framework::{standard::Configuration, Framework, StandardFramework},
+    gateway::{ConnectionStage, ShardManager, ShardStageUpdateEvent, VoiceGatewayManager},
     http::Http,
-    prelude::TypeMap,
+    prelude::{TypeMap, TypeMapKey},
     Client, Error,
 };
+#[cfg(feature = "voice")]
+use songbird::{SerenityInit, Songbird};
 use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Formatter},
     sync::{Arc, OnceLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
+use thiserror::Error;
 use tokio::{
     select, spawn,
-    sync::{Mutex, Notify, RwLock},
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex, Notify, RwLock,
+    },
     task::JoinHandle,
     time::sleep,
 };
+use tokio_util::sync::CancellationToken;
+use tracing::{info_span, instrument, Instrument};
+use uuid::Uuid;
+
+const COMMAND_PREFIX: &str = "!";
+
+fn standard_framework() -> StandardFramework {
+    let framework = StandardFramework::new();
+    framework.configure(Configuration::new().prefix(COMMAND_PREFIX));
+    framework
+}
+
+#[derive(Debug, Error)]
+pub enum IntentParseError {
+    #[error("Unknown gateway intent {0}")]
+    UnknownIntent(String),
+}
+
+// Parses intent names like GUILD_MESSAGES or MESSAGE_CONTENT, as they are named in serenity's GatewayIntents
+pub fn parse_gateway_intents<S>(names: &[S]) -> Result<GatewayIntents, IntentParseError>
+where
+    S: AsRef<str>,
+{
+    let mut intents = GatewayIntents::empty();
+
+    for name in names {
+        let name = name.as_ref().trim().to_uppercase();
+        match GatewayIntents::from_name(&name) {
+            Some(intent) => intents |= intent,
+            None => return Err(IntentParseError::UnknownIntent(name)),
+        }
+    }
+
+    Ok(intents)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardInfo {
+    pub shard_id: ShardId,
+    pub stage: ConnectionStage,
+    pub latency: Option<Duration>,
+}
+
+// Serenity does not expose when a heartbeat was last acknowledged, but every acknowledgement updates the latency
+struct Heartbeat {
+    latency: Option<Duration>,
+    last_ack: Instant,
+}
+
+pub async fn shard_infos(shard_manager: &ShardManager) -> Vec<ShardInfo> {
+    let runners = shard_manager.runners.lock().await;
+
+    let mut shard_infos: Vec<ShardInfo> = runners
+        .iter()
+        .map(|(shard_id, runner)| ShardInfo {
+            shard_id: *shard_id,
+            stage: runner.stage,
+            latency: runner.latency,
+        })
+        .collect();
+    shard_infos.sort_by_key(|shard_info| shard_info.shard_id);
+
+    shard_infos
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    Connecting,
+    Ready,
+    Resuming,
+    Disconnected,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "Connecting"),
+            ConnectionState::Ready => write!(f, "Ready"),
+            ConnectionState::Resuming => write!(f, "Resuming"),
+            ConnectionState::Disconnected => write!(f, "Disconnected"),
+        }
+    }
+}
+
+// With multiple shards, the worst stage of all shards that are not connected wins
+fn connection_state(stages: &BTreeMap<ShardId, ConnectionStage>) -> ConnectionState {
+    if stages
+        .values()
+        .any(|stage| *stage == ConnectionStage::Disconnected)
+    {
+        ConnectionState::Disconnected
+    } else if stages
+        .values()
+        .any(|stage| *stage == ConnectionStage::Resuming)
+    {
+        ConnectionState::Resuming
+    } else {
+        ConnectionState::Connecting
+    }
+}
+
+// Carries the serenity Context along, so subscribers can respond to the event
+pub struct DiscordEvent<T> {
+    pub context: Context,
+    pub data: T,
+}
+
+pub struct DiscordEvents {
+    pub on_message: Event<DiscordEvent<Message>>,
+    pub on_reaction_add: Event<DiscordEvent<Reaction>>,
+    pub on_reaction_remove: Event<DiscordEvent<Reaction>>,
+    pub on_member_join: Event<DiscordEvent<Member>>,
+    pub on_interaction_create: Event<DiscordEvent<Interaction>>,
+}
+
+impl DiscordEvents {
+    pub fn new() -> Self {
+        Self {
+            on_message: Event::new("discord_on_message"),
+            on_reaction_add: Event::new("discord_on_reaction_add"),
+            on_reaction_remove: Event::new("discord_on_reaction_remove"),
+            on_member_join: Event::new("discord_on_member_join"),
+            on_interaction_create: Event::new("discord_on_interaction_create"),
+        }
+    }
+}
+
+impl Default for DiscordEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Belongs to one run of the client, so a restarted service starts with a fresh one
+#[derive(Clone)]
+pub struct ClientState {
+    pub ready: Arc<OnceLock<Ready>>,
+    pub cache: Arc<Cache>,
+    pub data: Arc<RwLock<TypeMap>>,
+    pub http: Arc<Http>,
+    pub shard_manager: Arc<ShardManager>,
+    pub voice_manager: Option<Arc<dyn VoiceGatewayManager>>,
+    pub ws_url: Arc<Mutex<String>>,
+}
 
 //TODO: Restructure
 pub struct DiscordService {
     info: ServiceInfo,
     discord_token: String,
-    pub ready: Arc<OnceLock<Ready>>,
+    intents: GatewayIntents,
+    shards: Option<u32>,
+    shard_log_interval: Duration,
+    ready_timeout: Duration,
+    max_latency: Duration,
+    heartbeat_timeout: Duration,
+    heartbeats: Arc<Mutex<HashMap<ShardId, Heartbeat>>>,
     client_handle: Option<JoinHandle<Result<(), Error>>>,
-    pub cache: OnceLock<Arc<Cache>>,
-    pub data: OnceLock<Arc<RwLock<TypeMap>>>,
-    pub http: OnceLock<Arc<Http>>,
-    pub shard_manager: OnceLock<Arc<ShardManager>>,
-    pub voice_manager: OnceLock<Arc<dyn VoiceGatewayManager>>,
-    pub ws_url: OnceLock<Arc<Mutex<String>>>,
+    client_state: Option<ClientState>,
+    pub events: Arc<DiscordEvents>,
+    pub connection_state: Arc<Observable<ConnectionState>>,
+    pub event_handlers: Arc<EventHandlers>,
+    pub locales: Arc<Locales>,
+    #[cfg(feature = "poise")]
+    commands: Option<CommandFactory>,
+    #[cfg(feature = "poise")]
+    context_menus: ContextMenus,
+    #[cfg(feature = "poise")]
+    command_registration: CommandRegistration,
+    bulk_settings: BulkSettings,
+    bulk_sender: Option<Sender<BulkJob>>,
+    bulk_worker: Option<JoinHandle<()>>,
+    pub on_bulk_progress: Arc<Event<BulkProgress>>,
+    #[cfg(feature = "voice")]
+    pub songbird: Arc<Songbird>,
 }
 
 impl DiscordService {
     pub fn new(discord_token: &str) -> Self {
+        Self::with_info(
+            ServiceInfo::new("lum_builtin_discord", "Discord", Priority::Critical),
+            discord_token,
+        )
+    }
+
+    // Additional bot accounts get their own service ID (lum_builtin_discord_<instance>), which dependent services can address
+    pub fn new_instance(instance: &str, discord_token: &str) -> Self {
+        Self::with_info(
+            ServiceInfo::new(
+                &Self::instance_id(instance),
+                &format!("Discord ({})", instance),
+                Priority::Critical,
+            ),
+            discord_token,
+        )
+    }
+
+    pub fn instance_id(instance: &str) -> String {
+        format!("lum_builtin_discord_{}", instance)
+    }
+
+    fn with_info(info: ServiceInfo, discord_token: &str) -> Self {
         Self {
-            info: ServiceInfo::new("lum_builtin_discord", "Discord", Priority::Essential),
+            info: info.with_group("discord").with_manual_readiness(),
             discord_token: discord_token.to_string(),
-            ready: Arc::new(OnceLock::new()),
+            intents: GatewayIntents::non_privileged(),
+            shards: None,
+            shard_log_interval: Duration::from_secs(300),
+            ready_timeout: Duration::from_secs(30),
+            max_latency: Duration::from_secs(5),
+            heartbeat_timeout: Duration::from_secs(180),
+            heartbeats: Arc::new(Mutex::new(HashMap::new())),
             client_handle: None,
-            cache: OnceLock::new(),
-            data: OnceLock::new(),
-            http: OnceLock::new(),
-            shard_manager: OnceLock::new(),
-            voice_manager: OnceLock::new(),
-            ws_url: OnceLock::new(),
+            client_state: None,
+            events: Arc::new(DiscordEvents::new()),
+            connection_state: Arc::new(Observable::new(
+                ConnectionState::Disconnected,
+                "discord_connection_state",
+            )),
+            event_handlers: Arc::new(EventHandlers::new()),
+            locales: Arc::new(Locales::default()),
+            bulk_settings: BulkSettings {
+                delay: Duration::from_millis(250),
+                max_retries: 3,
+            },
+            bulk_sender: None,
+            bulk_worker: None,
+            #[cfg(feature = "poise")]
+            commands: None,
+            #[cfg(feature = "poise")]
+            context_menus: ContextMenus::new(),
+            #[cfg(feature = "poise")]
+            command_registration: CommandRegistration::new(),
+            on_bulk_progress: Arc::new(Event::new("discord_on_bulk_progress")),
+            #[cfg(feature = "voice")]
+            songbird: Songbird::serenity(),
+        }
+    }
+
+    // Privileged intents like MESSAGE_CONTENT have to be enabled in the Discord developer portal first
+    pub fn with_intents(mut self, intents: GatewayIntents) -> Self {
+        self.intents = intents;
+        self
+    }
+
+    pub fn intents(&self) -> GatewayIntents {
+        self.intents
+    }
+
+    // Without a shard count, the client is autosharded with Discord's recommended shard count
+    pub fn with_shards(mut self, shards: u32) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    // Startup fails if the client did not receive the Ready event within this time
+    pub fn with_ready_timeout(mut self, ready_timeout: Duration) -> Self {
+        self.ready_timeout = ready_timeout;
+        self
+    }
+
+    // The health check fails when a connected shard's heartbeat latency exceeds this
+    pub fn with_max_latency(mut self, max_latency: Duration) -> Self {
+        self.max_latency = max_latency;
+        self
+    }
+
+    // The health check fails when a connected shard's heartbeats were not acknowledged for this long
+    pub fn with_heartbeat_timeout(mut self, heartbeat_timeout: Duration) -> Self {
+        self.heartbeat_timeout = heartbeat_timeout;
+        self
+    }
+
+    pub fn with_shard_log_interval(mut self, interval: Duration) -> Self {
+        self.shard_log_interval = interval;
+        self
+    }
+
+    // Commands are created by a factory, because poise takes ownership of them every time the client starts
+    #[cfg(feature = "poise")]
+    pub fn with_commands(
+        mut self,
+        commands: impl Fn() -> Vec<Command> + Send + Sync + 'static,
+    ) -> Self {
+        self.commands = Some(Arc::new(commands));
+        self
+    }
+
+    // Strings that commands and modules respond with, by locale
+    pub fn with_locales(mut self, locales: Locales) -> Self {
+        self.locales = Arc::new(locales);
+        self
+    }
+
+    // For events that DiscordEvents does not cover. More handlers can be added through event_handlers, even after start.
+    pub fn with_event_handler(self, handler: impl client::EventHandler + 'static) -> Self {
+        self.event_handlers.add(handler);
+        self
+    }
+
+    pub fn with_raw_event_handler(self, handler: impl RawEventHandler + 'static) -> Self {
+        self.event_handlers.add_raw(handler);
+        self
+    }
+
+    #[cfg(feature = "poise")]
+    pub fn with_command_registration(mut self, command_registration: CommandRegistration) -> Self {
+        self.command_registration = command_registration;
+        self
+    }
+
+    // Adds an entry to the context menu of users, e.g. "Show profile"
+    #[cfg(feature = "poise")]
+    pub fn with_user_context_menu(
+        mut self,
+        name: &str,
+        handler: impl Fn(ContextMenuEvent<User>) -> ContextMenuFuture + Send + Sync + 'static,
+    ) -> Self {
+        self.context_menus.add_user(name, handler);
+        self
+    }
+
+    // Adds an entry to the context menu of messages, e.g. "Report message"
+    #[cfg(feature = "poise")]
+    pub fn with_message_context_menu(
+        mut self,
+        name: &str,
+        handler: impl Fn(ContextMenuEvent<Message>) -> ContextMenuFuture + Send + Sync + 'static,
+    ) -> Self {
+        self.context_menus.add_message(name, handler);
+        self
+    }
+
+    // Delay between the actions of bulk jobs, on top of what serenity waits for rate limits
+    pub fn with_bulk_delay(mut self, delay: Duration) -> Self {
+        self.bulk_settings.delay = delay;
+        self
+    }
+
+    pub fn with_bulk_max_retries(mut self, max_retries: u32) -> Self {
+        self.bulk_settings.max_retries = max_retries;
+        self
+    }
+
+    // Jobs are worked through one after another. Progress is reported through on_bulk_progress.
+    pub async fn queue_bulk(&self, actions: Vec<BulkAction>) -> Result<Uuid, BulkError> {
+        let bulk_sender = self.bulk_sender.as_ref().ok_or(BulkError::NotStarted)?;
+
+        let id = Uuid::new_v4();
+        bulk_sender
+            .send(BulkJob { id, actions })
+            .await
+            .map_err(|_| BulkError::QueueClosed)?;
+
+        Ok(id)
+    }
+
+    // None while the service is not started
+    pub fn client_state(&self) -> Option<&ClientState> {
+        self.client_state.as_ref()
+    }
+
+    pub async fn shard_infos(&self) -> Vec<ShardInfo> {
+        match &self.client_state {
+            Some(client_state) => shard_infos(&client_state.shard_manager).await,
+            None => Vec::new(),
         }
     }
+
+    fn data_lock(&self) -> Result<&Arc<RwLock<TypeMap>>, DataError> {
+        self.client_state
+            .as_ref()
+            .map(|client_state| &client_state.data)
+            .ok_or(DataError::NotStarted)
+    }
+
+    pub async fn insert_data<K>(&self, value: K::Value) -> Result<(), DataError>
+    where
+        K: TypeMapKey,
+    {
+        self.data_lock()?.write().await.insert::<K>(value);
+        Ok(())
+    }
+
+    pub async fn data<K>(&self) -> Result<K::Value, DataError>
+    where
+        K: TypeMapKey,
+        K::Value: Clone,
+    {
+        self.data_lock()?
+            .read()
+            .await
+            .get::<K>()
+            .cloned()
+            .ok_or_else(DataError::missing::<K>)
+    }
+
+    pub async fn remove_data<K>(&self) -> Result<Option<K::Value>, DataError>
+    where
+        K: TypeMapKey,
+    {
+        Ok(self.data_lock()?.write().await.remove::<K>())
+    }
+
+    // Shared data is keyed by its type, so only one value per type can be stored
+    pub async fn insert_shared<T>(&self, value: T) -> Result<(), DataError>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.insert_data::<SharedData<T>>(Arc::new(value)).await
+    }
+
+    pub async fn shared<T>(&self) -> Result<Arc<T>, DataError>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.data::<SharedData<T>>()
+            .await
+            .map_err(|error| match error {
+                DataError::Missing(_) => DataError::missing::<T>(),
+                error => error,
+            })
+    }
+
+    pub async fn remove_shared<T>(&self) -> Result<Option<Arc<T>>, DataError>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.remove_data::<SharedData<T>>().await
+    }
 }
 
 //TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
@@ -59,83 +488,133 @@ impl Service for DiscordService {
         &self.info
     }
 
-    async fn start(&mut self, _service_manager: Arc<ServiceManager>) -> Result<(), BoxedError> {
+    #[instrument(name = "discord", skip_all, fields(service_id = %self.info.id, operation = "start"))]
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
         let client_ready_notify = Arc::new(Notify::new());
+        let ready = Arc::new(OnceLock::new());
 
-        let framework = StandardFramework::new();
-        framework.configure(Configuration::new().prefix("!"));
-
-        let mut client = Client::builder(self.discord_token.as_str(), GatewayIntents::all())
-            .framework(framework)
+        let client_builder = Client::builder(self.discord_token.as_str(), self.intents)
             .event_handler(EventHandler::new(
-                Arc::clone(&self.ready),
+                Arc::clone(&ready),
                 Arc::clone(&client_ready_notify),
+                Arc::clone(&self.info.readiness),
+                Arc::clone(&self.info.status),
+                Arc::clone(&self.events),
+                Arc::clone(&self.connection_state),
             ))
-            .await?;
+            .raw_event_handler(RawEventMultiplexer::new(Arc::clone(&self.event_handlers)));
 
-        if self.cache.set(Arc::clone(&client.cache)).is_err() {
-            error!("Could not set cache OnceLock because it was already set. This should never happen.");
-            return Err("Could not set cache OnceLock because it was already set.".into());
-        }
+        // Poise replaces the deprecated StandardFramework once commands are registered
+        #[cfg(feature = "poise")]
+        let framework: Box<dyn Framework> =
+            if self.commands.is_some() || !self.context_menus.is_empty() {
+                let commands = match &self.commands {
+                    Some(commands) => commands(),
+                    None => Vec::new(),
+                };
 
-        if self.data.set(Arc::clone(&client.data)).is_err() {
-            error!(
-                "Could not set data OnceLock because it was already set. This should never happen."
-            );
-            return Err("Could not set data OnceLock because it was already set.".into());
-        }
+                Box::new(build_framework(
+                    commands,
+                    self.context_menus.clone(),
+                    self.command_registration.clone(),
+                    COMMAND_PREFIX,
+                    Arc::clone(&context.service_manager),
+                    Arc::clone(&self.locales),
+                ))
+            } else {
+                Box::new(standard_framework())
+            };
 
-        if self.http.set(Arc::clone(&client.http)).is_err() {
-            error!(
-                "Could not set http OnceLock because it was already set. This should never happen."
-            );
-            return Err("Could not set http OnceLock because it was already set.".into());
-        }
+        #[cfg(not(feature = "poise"))]
+        let framework: Box<dyn Framework> = Box::new(standard_framework());
 
-        if self
-            .shard_manager
-            .set(Arc::clone(&client.shard_manager))
-            .is_err()
-        {
-            error!(
-                "Could not set shard_manager OnceLock because it was already set. This should never happen."
-            );
-            return Err("Could not set shard_manager OnceLock because it was already set.".into());
-        }
+        let client_builder = client_builder.framework(MultiplexedFramework::new(
+            framework,
+            Arc::clone(&self.event_handlers),
+        ));
 
-        if let Some(voice_manager) = &client.voice_manager {
-            if self.voice_manager.set(Arc::clone(voice_manager)).is_err() {
-                error!("Could not set voice_manager OnceLock because it was already set. This should never happen.");
-                return Err(
-                    "Could not set voice_manager OnceLock because it was already set.".into(),
-                );
-            }
-        } else {
+        #[cfg(feature = "voice")]
+        let client_builder = client_builder.register_songbird_with(Arc::clone(&self.songbird));
+
+        let mut client = client_builder.await?;
+
+        if client.voice_manager.is_none() {
             warn!("Voice manager is not available");
         }
 
-        if self.ws_url.set(Arc::clone(&client.ws_url)).is_err() {
-            error!("Could not set ws_url OnceLock because it was already set. This should never happen.");
-            return Err("Could not set ws_url OnceLock because it was already set.".into());
-        }
+        let client_state = ClientState {
+            ready,
+            cache: Arc::clone(&client.cache),
+            data: Arc::clone(&client.data),
+            http: Arc::clone(&client.http),
+            shard_manager: Arc::clone(&client.shard_manager),
+            voice_manager: client.voice_manager.clone(),
+            ws_url: Arc::clone(&client.ws_url),
+        };
 
-        let client_handle = spawn(async move { client.start().await });
+        let http = Arc::clone(&client.http);
+
+        let shards = self.shards;
+        self.connection_state.set(ConnectionState::Connecting).await;
+        let mut client_handle = spawn(async move {
+            match shards {
+                Some(shards) => client.start_shards(shards).await,
+                None => client.start_autosharded().await,
+            }
+        });
 
         select! {
             _ = client_ready_notify.notified() => {},
-            _ = sleep(Duration::from_secs(2)) => {},
+            result = &mut client_handle => {
+                self.connection_state.set(ConnectionState::Disconnected).await;
+                result??;
+                return Err("Discord client stopped unexpectedly".into());
+            },
+            _ = sleep(self.ready_timeout) => {
+                client_handle.abort();
+                self.connection_state.set(ConnectionState::Disconnected).await;
+                return Err(format!(
+                    "Discord client did not become ready within {}",
+                    humantime::format_duration(self.ready_timeout)
+                )
+                .into());
+            },
         }
 
-        if client_handle.is_finished() {
-            client_handle.await??;
-            return Err("Discord client stopped unexpectedly".into());
-        }
+        let (bulk_sender, bulk_receiver) = channel(64);
+        self.bulk_sender = Some(bulk_sender);
+        self.bulk_worker = Some(spawn(run_bulk_worker(
+            http,
+            bulk_receiver,
+            Arc::clone(&self.on_bulk_progress),
+            self.bulk_settings,
+        )));
 
         self.client_handle = Some(client_handle);
+
+        discord_admin::register_commands(
+            &context.service_manager,
+            &self.info.id,
+            Arc::clone(&client_state.cache),
+            Arc::clone(&client_state.shard_manager),
+        )
+        .await;
+        self.client_state = Some(client_state);
+
         Ok(())
     }
 
+    #[instrument(name = "discord", skip_all, fields(service_id = %self.info.id, operation = "stop"))]
     async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.bulk_sender = None;
+        if let Some(bulk_worker) = self.bulk_worker.take() {
+            bulk_worker.abort();
+        }
+
+        // Cleared first, so the next start gets a fresh client even if this one stopped with an error
+        self.client_state = None;
+        self.heartbeats.lock().await.clear();
+
         if let Some(client_handle) = self.client_handle.take() {
             info!("Waiting for Discord client to stop...");
 
@@ -152,20 +631,137 @@ impl Service for DiscordService {
             result?;
         }
 
+        self.connection_state
+            .set(ConnectionState::Disconnected)
+            .await;
+
         Ok(())
     }
+
+    fn health_check<'a>(&self) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let shard_manager = Arc::clone(&self.client_state.as_ref()?.shard_manager);
+        let heartbeats = Arc::clone(&self.heartbeats);
+        let heartbeat_timeout = self.heartbeat_timeout;
+        let max_latency = self.max_latency;
+
+        Some(Box::pin(async move {
+            let shard_infos = shard_infos(&shard_manager).await;
+            let mut heartbeats = heartbeats.lock().await;
+            let mut problems = Vec::new();
+
+            for shard_info in shard_infos {
+                // Disconnected shards are already reported through the Degraded status
+                if shard_info.stage != ConnectionStage::Connected {
+                    heartbeats.remove(&shard_info.shard_id);
+                    continue;
+                }
+
+                let heartbeat =
+                    heartbeats
+                        .entry(shard_info.shard_id)
+                        .or_insert_with(|| Heartbeat {
+                            latency: shard_info.latency,
+                            last_ack: Instant::now(),
+                        });
+
+                if heartbeat.latency != shard_info.latency {
+                    heartbeat.latency = shard_info.latency;
+                    heartbeat.last_ack = Instant::now();
+                }
+
+                let since_last_ack = heartbeat.last_ack.elapsed();
+                if since_last_ack > heartbeat_timeout {
+                    problems.push(format!(
+                        "Shard {} did not receive a heartbeat acknowledgement for {}",
+                        shard_info.shard_id,
+                        humantime::format_duration(Duration::from_secs(since_last_ack.as_secs()))
+                    ));
+                }
+
+                if let Some(latency) = shard_info.latency {
+                    if latency > max_latency {
+                        problems.push(format!(
+                            "Shard {} has a latency of {}ms",
+                            shard_info.shard_id,
+                            latency.as_millis()
+                        ));
+                    }
+                }
+            }
+
+            if !problems.is_empty() {
+                return Err(problems.join(", ").into());
+            }
+
+            Ok(())
+        }))
+    }
+
+    // Periodically logs the stage and latency of each shard
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let shard_manager = Arc::clone(&self.client_state.as_ref()?.shard_manager);
+        let interval = self.shard_log_interval;
+        let span = info_span!("discord", service_id = %self.info.id, operation = "shard_log");
+
+        Some(Box::pin(
+            async move {
+                loop {
+                    select! {
+                        _ = cancellation_token.cancelled() => return Ok(()),
+                        _ = sleep(interval) => {},
+                    }
+
+                    for shard_info in shard_infos(&shard_manager).await {
+                        match shard_info.latency {
+                            Some(latency) => info!(
+                                "Shard {}: {}, latency {}ms",
+                                shard_info.shard_id,
+                                shard_info.stage,
+                                latency.as_millis()
+                            ),
+                            None => info!(
+                                "Shard {}: {}, latency unknown",
+                                shard_info.shard_id, shard_info.stage
+                            ),
+                        }
+                    }
+                }
+            }
+            .instrument(span),
+        ))
+    }
 }
 
 struct EventHandler {
     client: Arc<OnceLock<Ready>>,
     ready_notify: Arc<Notify>,
+    readiness: Arc<Observable<Readiness>>,
+    status: Arc<Observable<Status>>,
+    events: Arc<DiscordEvents>,
+    connection_state: Arc<Observable<ConnectionState>>,
+    disconnected_shards: Mutex<BTreeMap<ShardId, ConnectionStage>>,
 }
 
 impl EventHandler {
-    pub fn new(client: Arc<OnceLock<Ready>>, ready_notify: Arc<Notify>) -> Self {
+    pub fn new(
+        client: Arc<OnceLock<Ready>>,
+        ready_notify: Arc<Notify>,
+        readiness: Arc<Observable<Readiness>>,
+        status: Arc<Observable<Status>>,
+        events: Arc<DiscordEvents>,
+        connection_state: Arc<Observable<ConnectionState>>,
+    ) -> Self {
         Self {
             client,
             ready_notify,
+            readiness,
+            status,
+            events,
+            connection_state,
+            disconnected_shards: Mutex::new(BTreeMap::new()),
         }
     }
 }
@@ -175,10 +771,127 @@ impl EventHandler {
 impl client::EventHandler for EventHandler {
     async fn ready(&self, _ctx: Context, data_about_bot: Ready) {
         info!("Connected to Discord as {}", data_about_bot.user.tag());
-        if self.client.set(data_about_bot).is_err() {
-            error!("Could not set client OnceLock because it was already set. This should never happen.");
-            panic!("Could not set client OnceLock because it was already set");
-        }
+
+        // Without guilds, there is nothing to wait for until the cache is ready
+        let has_guilds = !data_about_bot.guilds.is_empty();
+
+        // Every shard sends its own Ready, and so does a session that was identified again, so only the first one is kept
+        self.client.get_or_init(|| data_about_bot);
         self.ready_notify.notify_one();
+        self.connection_state.set(ConnectionState::Ready).await;
+
+        if !has_guilds {
+            self.readiness.set(Readiness::Ready).await;
+        }
+    }
+
+    async fn cache_ready(&self, _ctx: Context, _guilds: Vec<GuildId>) {
+        self.readiness.set(Readiness::Ready).await;
+    }
+
+    // Only switches between Started and Degraded, so lifecycle statuses set by the ServiceManager are left alone
+    #[instrument(name = "discord", skip_all, fields(shard_id = %event.shard_id, operation = "shard_stage_update"))]
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        let mut disconnected_shards = self.disconnected_shards.lock().await;
+
+        if event.new == ConnectionStage::Connected {
+            disconnected_shards.remove(&event.shard_id);
+        } else {
+            if event.old == ConnectionStage::Connected {
+                warn!(
+                    "Shard {} lost its connection to Discord ({})",
+                    event.shard_id, event.new
+                );
+            }
+            disconnected_shards.insert(event.shard_id, event.new);
+        }
+
+        // Shards also pass through Connected before the first Ready event
+        if disconnected_shards.is_empty() {
+            if self.client.get().is_some() {
+                self.connection_state.set(ConnectionState::Ready).await;
+            }
+        } else {
+            self.connection_state
+                .set(connection_state(&disconnected_shards))
+                .await;
+        }
+
+        let status = self.status.get().await;
+        if disconnected_shards.is_empty() {
+            if matches!(status, Status::Degraded(_)) {
+                info!("All shards reconnected to Discord");
+                self.status.set(Status::Started).await;
+            }
+
+            return;
+        }
+
+        if matches!(status, Status::Started | Status::Degraded(_)) {
+            let shards = disconnected_shards
+                .keys()
+                .map(|shard_id| shard_id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            self.status
+                .set(Status::Degraded(format!(
+                    "Shards not connected to Discord: {}",
+                    shards
+                )))
+                .await;
+        }
+    }
+
+    async fn resume(&self, _ctx: Context, _event: ResumedEvent) {
+        info!("Resumed session with Discord");
+    }
+
+    async fn message(&self, ctx: Context, new_message: Message) {
+        let event = DiscordEvent {
+            context: ctx,
+            data: new_message,
+        };
+        let _ = self.events.on_message.dispatch(Arc::new(event)).await;
+    }
+
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        let event = DiscordEvent {
+            context: ctx,
+            data: add_reaction,
+        };
+        let _ = self.events.on_reaction_add.dispatch(Arc::new(event)).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
+        let event = DiscordEvent {
+            context: ctx,
+            data: removed_reaction,
+        };
+        let _ = self
+            .events
+            .on_reaction_remove
+            .dispatch(Arc::new(event))
+            .await;
+    }
+
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        let event = DiscordEvent {
+            context: ctx,
+            data: new_member,
+        };
+        let _ = self.events.on_member_join.dispatch(Arc::new(event)).await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let event = DiscordEvent {
+            context: ctx,
+            data: interaction,
+        };
+        let _ = self
+            .events
+            .on_interaction_create
+            .dispatch(Arc::new(event))
+            .await;
     }
 }
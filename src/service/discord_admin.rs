@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use log::warn;
+use serenity::{
+    all::{ActivityData, OnlineStatus},
+    client::Cache,
+    gateway::ShardManager,
+};
+
+use super::{admin::AdminResponse, ServiceManager};
+
+const PRESENCE_USAGE: &str =
+    "Usage: presence <online|idle|dnd|invisible> [playing|listening|watching|competing|custom <text>]";
+
+// Registers the admin commands of the Discord service. They are removed again when the service stops.
+pub(crate) async fn register_commands(
+    service_manager: &ServiceManager,
+    owner: &str,
+    cache: Arc<Cache>,
+    shard_manager: Arc<ShardManager>,
+) {
+    let registry = &service_manager.command_registry;
+
+    let result = registry
+        .register(
+            owner,
+            "guilds",
+            "",
+            "List the guilds the bot is in",
+            move |_| {
+                let guilds = list_guilds(&cache);
+                Box::pin(async move { AdminResponse::success(guilds) })
+            },
+        )
+        .await;
+    if let Err(err) = result {
+        warn!("Unable to register the guilds admin command: {}", err);
+    }
+
+    let result = registry
+        .register(
+            owner,
+            "presence",
+            "<status> [activity <text>]",
+            "Set the bot's status and activity on all shards",
+            move |arguments| {
+                let shard_manager = Arc::clone(&shard_manager);
+                Box::pin(async move { set_presence(&shard_manager, &arguments).await })
+            },
+        )
+        .await;
+    if let Err(err) = result {
+        warn!("Unable to register the presence admin command: {}", err);
+    }
+}
+
+fn list_guilds(cache: &Cache) -> String {
+    let mut guilds = cache
+        .guilds()
+        .into_iter()
+        .map(|guild_id| match cache.guild(guild_id) {
+            Some(guild) => format!(
+                "{} ({}): {} members",
+                guild.name, guild_id, guild.member_count
+            ),
+            None => format!("{} (not cached)", guild_id),
+        })
+        .collect::<Vec<_>>();
+    guilds.sort();
+
+    match guilds.is_empty() {
+        true => "The bot is not in any guild".to_string(),
+        false => format!("{} guilds:\n{}", guilds.len(), guilds.join("\n")),
+    }
+}
+
+fn parse_online_status(status: &str) -> Option<OnlineStatus> {
+    match status.to_lowercase().as_str() {
+        "online" => Some(OnlineStatus::Online),
+        "idle" => Some(OnlineStatus::Idle),
+        "dnd" => Some(OnlineStatus::DoNotDisturb),
+        "invisible" => Some(OnlineStatus::Invisible),
+        _ => None,
+    }
+}
+
+fn parse_activity(arguments: &[String]) -> Result<Option<ActivityData>, String> {
+    let (kind, text) = match arguments.split_first() {
+        Some((kind, text)) => (kind.to_lowercase(), text.join(" ")),
+        None => return Ok(None),
+    };
+
+    if text.is_empty() {
+        return Err(format!("The {} activity needs a text", kind));
+    }
+
+    match kind.as_str() {
+        "playing" => Ok(Some(ActivityData::playing(text))),
+        "listening" => Ok(Some(ActivityData::listening(text))),
+        "watching" => Ok(Some(ActivityData::watching(text))),
+        "competing" => Ok(Some(ActivityData::competing(text))),
+        "custom" => Ok(Some(ActivityData::custom(text))),
+        _ => Err(format!("Unknown activity {}", kind)),
+    }
+}
+
+async fn set_presence(shard_manager: &ShardManager, arguments: &[String]) -> AdminResponse {
+    let status = match arguments.first().map(|status| parse_online_status(status)) {
+        Some(Some(status)) => status,
+        Some(None) | None => return AdminResponse::failure(PRESENCE_USAGE),
+    };
+
+    let activity = match parse_activity(&arguments[1..]) {
+        Ok(activity) => activity,
+        Err(message) => return AdminResponse::failure(format!("{}\n{}", message, PRESENCE_USAGE)),
+    };
+
+    let runners = shard_manager.runners.lock().await;
+    for runner in runners.values() {
+        runner.runner_tx.set_presence(activity.clone(), status);
+    }
+
+    AdminResponse::success(format!("Presence set on {} shards", runners.len()))
+}
@@ -0,0 +1,29 @@
+use std::{any::type_name, marker::PhantomData, sync::Arc};
+
+use serenity::prelude::TypeMapKey;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DataError {
+    #[error("Discord client has not been started")]
+    NotStarted,
+
+    #[error("No data of type {0} has been inserted")]
+    Missing(&'static str),
+}
+
+impl DataError {
+    pub fn missing<T>() -> Self {
+        DataError::Missing(type_name::<T>())
+    }
+}
+
+// Key for serenity's TypeMap that is derived from the stored type, so modules do not need to declare their own keys
+pub struct SharedData<T>(PhantomData<T>);
+
+impl<T> TypeMapKey for SharedData<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Value = Arc<T>;
+}
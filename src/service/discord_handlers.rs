@@ -0,0 +1,158 @@
+use std::sync::{Arc, RwLock};
+
+use serenity::{
+    all::{Event, FullEvent},
+    async_trait,
+    client::{Context, EventHandler, RawEventHandler},
+    framework::Framework,
+    Client,
+};
+use tokio::spawn;
+use uuid::Uuid;
+
+// Additional serenity handlers of a DiscordService. They can be added and removed at any time, even while the client is running.
+// A std RwLock is used so handlers can also be added from the synchronous builder methods. It is never held across an await.
+#[derive(Default)]
+pub struct EventHandlers {
+    handlers: RwLock<Vec<(Uuid, Arc<dyn EventHandler>)>>,
+    raw_handlers: RwLock<Vec<(Uuid, Arc<dyn RawEventHandler>)>>,
+}
+
+impl EventHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, handler: impl EventHandler + 'static) -> Uuid {
+        let uuid = Uuid::new_v4();
+        let mut handlers = self
+            .handlers
+            .write()
+            .unwrap_or_else(|error| error.into_inner());
+        handlers.push((uuid, Arc::new(handler)));
+
+        uuid
+    }
+
+    pub fn add_raw(&self, handler: impl RawEventHandler + 'static) -> Uuid {
+        let uuid = Uuid::new_v4();
+        let mut raw_handlers = self
+            .raw_handlers
+            .write()
+            .unwrap_or_else(|error| error.into_inner());
+        raw_handlers.push((uuid, Arc::new(handler)));
+
+        uuid
+    }
+
+    pub fn remove(&self, uuid: &Uuid) -> bool {
+        let mut handlers = self
+            .handlers
+            .write()
+            .unwrap_or_else(|error| error.into_inner());
+        let mut raw_handlers = self
+            .raw_handlers
+            .write()
+            .unwrap_or_else(|error| error.into_inner());
+
+        let count = handlers.len() + raw_handlers.len();
+        handlers.retain(|(handler_uuid, _)| handler_uuid != uuid);
+        raw_handlers.retain(|(handler_uuid, _)| handler_uuid != uuid);
+
+        handlers.len() + raw_handlers.len() < count
+    }
+
+    pub fn len(&self) -> usize {
+        let handlers = self
+            .handlers
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+        let raw_handlers = self
+            .raw_handlers
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+
+        handlers.len() + raw_handlers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn handlers(&self) -> Vec<Arc<dyn EventHandler>> {
+        let handlers = self
+            .handlers
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+        handlers
+            .iter()
+            .map(|(_, handler)| Arc::clone(handler))
+            .collect()
+    }
+
+    fn raw_handlers(&self) -> Vec<Arc<dyn RawEventHandler>> {
+        let raw_handlers = self
+            .raw_handlers
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+        raw_handlers
+            .iter()
+            .map(|(_, handler)| Arc::clone(handler))
+            .collect()
+    }
+}
+
+// Serenity fixes its raw event handlers when the client is built, so this single one forwards to the current ones
+pub(crate) struct RawEventMultiplexer {
+    handlers: Arc<EventHandlers>,
+}
+
+impl RawEventMultiplexer {
+    pub fn new(handlers: Arc<EventHandlers>) -> Self {
+        Self { handlers }
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl RawEventHandler for RawEventMultiplexer {
+    async fn raw_event(&self, ctx: Context, event: Event) {
+        for handler in self.handlers.raw_handlers() {
+            let (ctx, event) = (ctx.clone(), event.clone());
+            spawn(async move { handler.raw_event(ctx, event).await });
+        }
+    }
+}
+
+// The framework is the only place where serenity hands out already parsed events without fixing the receivers at build time.
+// Wrapping it lets typed event handlers be added after the client has started.
+pub(crate) struct MultiplexedFramework {
+    framework: Box<dyn Framework>,
+    handlers: Arc<EventHandlers>,
+}
+
+impl MultiplexedFramework {
+    pub fn new(framework: Box<dyn Framework>, handlers: Arc<EventHandlers>) -> Self {
+        Self {
+            framework,
+            handlers,
+        }
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Framework for MultiplexedFramework {
+    async fn init(&mut self, client: &Client) {
+        self.framework.init(client).await;
+    }
+
+    async fn dispatch(&self, ctx: Context, event: FullEvent) {
+        for handler in self.handlers.handlers() {
+            let (ctx, event) = (ctx.clone(), event.clone());
+            spawn(async move { event.dispatch(ctx, &*handler).await });
+        }
+
+        self.framework.dispatch(ctx, event).await;
+    }
+}
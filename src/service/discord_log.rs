@@ -0,0 +1,178 @@
+use std::{sync::Arc, time::Duration};
+
+use log::warn;
+use serenity::{
+    all::{ChannelId, CreateMessage},
+    async_trait,
+    http::Http,
+};
+use tokio::{select, spawn, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    discord::DiscordService, BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service,
+    ServiceContext, ServiceInfo,
+};
+use crate::log::take_forwarded_records;
+
+// Discord rejects messages longer than this
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+// Leaves room for the code block around the records
+const MAX_BATCH_LENGTH: usize = MAX_MESSAGE_LENGTH - 16;
+
+// Forwards warnings and errors to a Discord channel. Records are batched into as few messages as possible and sent periodically.
+pub struct DiscordLogService {
+    info: ServiceInfo,
+    channel_id: ChannelId,
+    interval: Duration,
+    max_messages: usize,
+    http: Option<Arc<Http>>,
+}
+
+impl DiscordLogService {
+    pub fn new(channel_id: ChannelId) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_discord_log", "Discord Log", Priority::Low)
+                .with_group("discord")
+                .with_dependency::<DiscordService>(),
+            channel_id,
+            interval: Duration::from_secs(10),
+            max_messages: 3,
+            http: None,
+        }
+    }
+
+    // Sends the records as the given bot account instead of the default one
+    pub fn with_discord_instance(mut self, instance: &str) -> Self {
+        let id = format!("{}_{}", self.info.id, instance);
+        self.info = self
+            .info
+            .with_id(&id)
+            .with_instance_dependency::<DiscordService>(&DiscordService::instance_id(instance));
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // Records that do not fit into this many messages per interval are dropped
+    pub fn with_max_messages(mut self, max_messages: usize) -> Self {
+        self.max_messages = max_messages;
+        self
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+}
+
+fn truncate(line: &str, max_length: usize) -> String {
+    if line.chars().count() <= max_length {
+        return line.to_string();
+    }
+
+    let mut truncated: String = line.chars().take(max_length - 3).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+fn batch_records(records: Vec<String>) -> Vec<String> {
+    let mut batches = Vec::new();
+    let mut batch = String::new();
+
+    for record in records {
+        let record = truncate(&record, MAX_BATCH_LENGTH);
+
+        if !batch.is_empty()
+            && batch.chars().count() + record.chars().count() + 1 > MAX_BATCH_LENGTH
+        {
+            batches.push(batch);
+            batch = String::new();
+        }
+
+        if !batch.is_empty() {
+            batch.push('\n');
+        }
+        batch.push_str(&record);
+    }
+
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for DiscordLogService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        let discord_service = context.dependency::<DiscordService>()?;
+        let discord_service = discord_service.lock().await;
+
+        let http = match discord_service.client_state() {
+            Some(client_state) => Arc::clone(&client_state.http),
+            None => return Err("Discord client has not been started".into()),
+        };
+        self.http = Some(http);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.http = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let http = Arc::clone(self.http.as_ref()?);
+        let channel_id = self.channel_id;
+        let interval = self.interval;
+        let max_messages = self.max_messages;
+
+        Some(Box::pin(async move {
+            loop {
+                select! {
+                    _ = cancellation_token.cancelled() => return Ok(()),
+                    _ = sleep(interval) => {},
+                }
+
+                let batches = batch_records(take_forwarded_records());
+                if batches.len() > max_messages {
+                    warn!(
+                        "Dropped {} batches of log records because too many were logged at once",
+                        batches.len() - max_messages
+                    );
+                }
+
+                //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+                for batch in batches.into_iter().take(max_messages) {
+                    let message = CreateMessage::new().content(format!("```\n{}\n```", batch));
+
+                    // Sending is spawned because serenity's futures are not Sync, but the task has to be
+                    let http = Arc::clone(&http);
+                    let send = spawn(async move { channel_id.send_message(&http, message).await });
+
+                    match send.await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(error)) => {
+                            warn!("Unable to send log records to Discord: {}", error)
+                        }
+                        Err(error) => warn!("Unable to send log records to Discord: {}", error),
+                    }
+                }
+            }
+        }))
+    }
+}
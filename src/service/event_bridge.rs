@@ -0,0 +1,321 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Weak},
+};
+
+use log::{debug, warn};
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, Msg};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::async_trait;
+use tokio::{
+    select, spawn,
+    sync::mpsc::{channel, Sender},
+};
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service, ServiceContext, ServiceInfo,
+    ServiceManager,
+};
+
+// Outgoing events are dropped instead of slowing down local dispatch when the broker can't keep up
+const OUTGOING_BUFFER: usize = 256;
+
+// An event that another lum instance published through the bridge. It is published on the EventBus as RemoteEvent<T>
+// instead of T, so it is never sent back to the broker and local subscribers can tell the two apart.
+#[derive(Debug)]
+pub struct RemoteEvent<T> {
+    pub origin: String,
+    pub payload: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    origin: String,
+    payload: Value,
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+trait Route: Send + Sync {
+    // Local events are serialized and handed to the sender with their topic, so the bridge can publish them
+    async fn subscribe(
+        &self,
+        service_manager: &ServiceManager,
+        sender: Sender<(String, Value)>,
+    ) -> Uuid;
+    async fn unsubscribe(&self, service_manager: &ServiceManager, uuid: Uuid);
+    async fn deliver(
+        &self,
+        service_manager: &ServiceManager,
+        origin: String,
+        payload: Value,
+    ) -> Result<(), BoxedError>;
+}
+
+struct TypedRoute<T> {
+    topic: String,
+    payload: PhantomData<fn() -> T>,
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl<T> Route for TypedRoute<T>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    async fn subscribe(
+        &self,
+        service_manager: &ServiceManager,
+        sender: Sender<(String, Value)>,
+    ) -> Uuid {
+        let topic = self.topic.clone();
+        service_manager
+            .event_bus
+            .subscribe_closure::<T, _>(
+                format!("event_bridge_{}", self.topic),
+                move |payload| {
+                    let payload = serde_json::to_value(&*payload)?;
+                    if sender.try_send((topic.clone(), payload)).is_err() {
+                        warn!("Event bridge is lagging behind, dropped event {}", topic);
+                    }
+
+                    Ok(())
+                },
+                true,
+                false,
+            )
+            .await
+            // Routes don't know the payload type of each other, so the bridge unsubscribes them by Uuid
+            .detach()
+    }
+
+    async fn unsubscribe(&self, service_manager: &ServiceManager, uuid: Uuid) {
+        service_manager.event_bus.unsubscribe::<T, _>(&uuid).await;
+    }
+
+    async fn deliver(
+        &self,
+        service_manager: &ServiceManager,
+        origin: String,
+        payload: Value,
+    ) -> Result<(), BoxedError> {
+        let payload = serde_json::from_value::<T>(payload)?;
+        let _ = service_manager
+            .event_bus
+            .publish(RemoteEvent { origin, payload })
+            .await;
+
+        Ok(())
+    }
+}
+
+// Shares events of the EventBus with other lum instances through Redis pub/sub, e.g. between shards on different hosts.
+// Every event type is published to its own channel, named by the prefix and the topic it was registered with.
+pub struct EventBridgeService {
+    info: ServiceInfo,
+    url: String,
+    prefix: String,
+    instance_id: String,
+    routes: Arc<HashMap<String, Arc<dyn Route>>>,
+    client: Option<Client>,
+    service_manager: Option<Weak<ServiceManager>>,
+}
+
+impl EventBridgeService {
+    pub fn new(url: &str) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_event_bridge", "Event Bridge", Priority::Normal),
+            url: url.to_string(),
+            prefix: String::from("lum."),
+            instance_id: Uuid::new_v4().to_string(),
+            routes: Arc::new(HashMap::new()),
+            client: None,
+            service_manager: None,
+        }
+    }
+
+    // Instances only share events if they use the same prefix
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    // Local events of type T are published to the topic and events from other instances on the topic are published as RemoteEvent<T>
+    pub fn with_event<T>(mut self, topic: &str) -> Self
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        let route = TypedRoute::<T> {
+            topic: topic.to_string(),
+            payload: PhantomData,
+        };
+
+        Arc::make_mut(&mut self.routes).insert(topic.to_string(), Arc::new(route));
+        self
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+}
+
+struct Bridge {
+    client: Client,
+    service_manager: Weak<ServiceManager>,
+    routes: Arc<HashMap<String, Arc<dyn Route>>>,
+    prefix: String,
+    instance_id: String,
+}
+
+impl Bridge {
+    async fn run(self, cancellation_token: CancellationToken) -> Result<(), BoxedError> {
+        let mut publisher = self.client.get_multiplexed_async_connection().await?;
+        let mut subscriber = self.client.get_async_pubsub().await?;
+
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for topic in self.routes.keys() {
+            subscriber
+                .subscribe(format!("{}{}", self.prefix, topic))
+                .await?;
+        }
+
+        let (sender, mut outgoing) = channel(OUTGOING_BUFFER);
+        let mut subscriptions = Vec::new();
+        if let Some(service_manager) = self.service_manager.upgrade() {
+            //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+            for route in self.routes.values() {
+                let uuid = route.subscribe(&service_manager, sender.clone()).await;
+                subscriptions.push((Arc::clone(route), uuid));
+            }
+        }
+        drop(sender);
+
+        let mut messages = subscriber.on_message();
+        let result = loop {
+            select! {
+                _ = cancellation_token.cancelled() => break Ok(()),
+                outgoing = outgoing.recv() => {
+                    let (topic, payload) = match outgoing {
+                        Some(outgoing) => outgoing,
+                        None => break Ok(()),
+                    };
+
+                    if let Err(err) = self.publish(&mut publisher, &topic, payload).await {
+                        break Err(err);
+                    }
+                },
+                message = messages.next() => {
+                    let message = match message {
+                        Some(message) => message,
+                        None => break Err("Connection to the event broker was closed".into()),
+                    };
+
+                    let channel = message.get_channel_name().to_string();
+                    if let Err(err) = self.deliver(message).await {
+                        debug!("Ignoring invalid event on {}: {}", channel, err);
+                    }
+                },
+            }
+        };
+
+        if let Some(service_manager) = self.service_manager.upgrade() {
+            //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+            for (route, uuid) in subscriptions {
+                route.unsubscribe(&service_manager, uuid).await;
+            }
+        }
+
+        result
+    }
+
+    async fn publish(
+        &self,
+        publisher: &mut MultiplexedConnection,
+        topic: &str,
+        payload: Value,
+    ) -> Result<(), BoxedError> {
+        let envelope = Envelope {
+            origin: self.instance_id.clone(),
+            payload,
+        };
+
+        publisher
+            .publish::<_, _, ()>(
+                format!("{}{}", self.prefix, topic),
+                serde_json::to_string(&envelope)?,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn deliver(&self, message: Msg) -> Result<(), BoxedError> {
+        let topic = message
+            .get_channel_name()
+            .strip_prefix(self.prefix.as_str())
+            .unwrap_or_default();
+        let route = match self.routes.get(topic) {
+            Some(route) => route,
+            None => return Ok(()),
+        };
+
+        let envelope = serde_json::from_slice::<Envelope>(message.get_payload_bytes())?;
+        if envelope.origin == self.instance_id {
+            return Ok(());
+        }
+
+        match self.service_manager.upgrade() {
+            Some(service_manager) => {
+                route
+                    .deliver(&service_manager, envelope.origin, envelope.payload)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for EventBridgeService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        self.client = Some(Client::open(self.url.as_str())?);
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.client = None;
+        self.service_manager = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let bridge = Bridge {
+            client: self.client.clone()?,
+            service_manager: Weak::clone(self.service_manager.as_ref()?),
+            routes: Arc::clone(&self.routes),
+            prefix: self.prefix.clone(),
+            instance_id: self.instance_id.clone(),
+        };
+
+        // Spawned because the futures of the Redis connections are not Sync, but the task has to be
+        Some(Box::pin(async move {
+            spawn(bridge.run(cancellation_token)).await?
+        }))
+    }
+}
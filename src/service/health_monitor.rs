@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use log::{error, warn};
+use tokio::{
+    spawn,
+    sync::Mutex,
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+
+use super::{service_manager::ServiceManager, Status};
+
+pub struct HealthMonitor {
+    pub interval: Duration,
+    pub check_timeout: Duration,
+    pub failure_threshold: u32,
+
+    failures: Mutex<HashMap<String, u32>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl HealthMonitor {
+    pub fn new(interval: Duration, failure_threshold: u32) -> Self {
+        Self {
+            interval,
+            check_timeout: Duration::from_secs(5),
+            failure_threshold,
+            failures: Mutex::new(HashMap::new()),
+            task: Mutex::new(None),
+        }
+    }
+
+    // A check that takes longer counts as failed. Should be well below the interval, so slow checks don't pile up.
+    pub fn with_check_timeout(mut self, check_timeout: Duration) -> Self {
+        self.check_timeout = check_timeout;
+        self
+    }
+
+    pub async fn is_running(&self) -> bool {
+        match self.task.lock().await.as_ref() {
+            Some(task) => !task.is_finished(),
+            None => false,
+        }
+    }
+
+    // The task ends on its own once the ServiceManager is dropped
+    pub async fn start(&self, service_manager: Weak<ServiceManager>) {
+        let mut task = self.task.lock().await;
+        if task.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+
+        let interval = self.interval;
+        *task = Some(spawn(async move {
+            loop {
+                sleep(interval).await;
+
+                let service_manager = match service_manager.upgrade() {
+                    Some(service_manager) => service_manager,
+                    None => return,
+                };
+
+                service_manager.health_monitor.check(&service_manager).await;
+            }
+        }));
+    }
+
+    pub async fn stop(&self) {
+        if let Some(task) = self.task.lock().await.take() {
+            task.abort();
+        }
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    pub async fn check(&self, service_manager: &Arc<ServiceManager>) {
+        let services = service_manager.services().await;
+
+        for service in services {
+            let service_lock = service.lock().await;
+            let info = service_lock.info();
+            let id = info.id.clone();
+            let name = info.name.clone();
+
+            if !matches!(
+                info.status.get().await,
+                Status::Started | Status::Degraded(_)
+            ) {
+                self.failures.lock().await.remove(&id);
+                continue;
+            }
+
+            // The service is not locked while it is checked, so a slow check does not hold up starting or stopping it
+            let health_check = service_lock.health_check();
+            drop(service_lock);
+
+            let result = match health_check {
+                Some(health_check) => match timeout(self.check_timeout, health_check).await {
+                    Ok(result) => result,
+                    Err(_) => Err("Health check timed out".into()),
+                },
+                None => Ok(()),
+            };
+
+            let error = match result {
+                Ok(()) => {
+                    self.failures.lock().await.remove(&id);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            let mut failures = self.failures.lock().await;
+            let failure_count = failures.entry(id.clone()).or_insert(0);
+            *failure_count += 1;
+
+            if *failure_count < self.failure_threshold {
+                warn!(
+                    "Health check of service {} failed ({}/{}): {}",
+                    name, failure_count, self.failure_threshold, error
+                );
+                continue;
+            }
+
+            error!(
+                "Health check of service {} failed {} times in a row: {}. Service will be marked as failed.",
+                name, failure_count, error
+            );
+            failures.remove(&id);
+            drop(failures);
+
+            // The service may have been stopped while it was checked
+            let service_lock = service.lock().await;
+            if !matches!(
+                service_lock.info().status.get().await,
+                Status::Started | Status::Degraded(_)
+            ) {
+                continue;
+            }
+
+            service_manager
+                .fail_service(
+                    &*service_lock,
+                    Status::RuntimeError(format!("Health check failed: {}", error)),
+                )
+                .await;
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Weak},
+};
+
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use log::{info, warn};
+use serde::Serialize;
+use serenity::async_trait;
+use tokio::{net::TcpListener, pin, select, spawn};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    admin::{execute, tokens_match, AdminRequest, AdminResponse},
+    BoxedError, LifetimedPinnedBoxedFutureResult, OverallStatus, Priority, Service, ServiceContext,
+    ServiceInfo, ServiceManager,
+};
+
+// GET /status, /health and /ready are open, so they can be used as probes.
+// POST /services/<id>/(start|stop|restart), /reload and /shutdown need the token as bearer token and are disabled without one.
+pub struct HttpAdminService {
+    info: ServiceInfo,
+    address: SocketAddr,
+    token: Option<String>,
+    listener: Option<Arc<TcpListener>>,
+    service_manager: Option<Weak<ServiceManager>>,
+    // Cancelled in stop, so keep-alive connections don't outlive the service
+    connections: CancellationToken,
+}
+
+impl HttpAdminService {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_http_admin", "HTTP Admin", Priority::Low),
+            address,
+            token: None,
+            listener: None,
+            service_manager: None,
+            connections: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+struct RequestContext {
+    service_manager: Weak<ServiceManager>,
+    token: Option<String>,
+    own_id: String,
+}
+
+fn json_response<T>(status: StatusCode, body: &T) -> Response<Full<Bytes>>
+where
+    T: Serialize,
+{
+    let (status, body) = match serde_json::to_vec(body) {
+        Ok(body) => (status, body),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{{\"error\":\"{}\"}}", error).into_bytes(),
+        ),
+    };
+
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        "application/json".parse().expect("valid header"),
+    );
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, &AdminResponse::failure(message))
+}
+
+fn status_code(overall_status: OverallStatus) -> StatusCode {
+    match overall_status {
+        OverallStatus::Healthy | OverallStatus::Degraded => StatusCode::OK,
+        OverallStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+fn is_authorized(request: &Request<Incoming>, token: &str) -> bool {
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .is_some_and(|given| tokens_match(token, given))
+}
+
+fn admin_request(path: &[&str]) -> Option<AdminRequest> {
+    match path {
+        ["services", service_id, action @ ("start" | "stop" | "restart")] => {
+            Some(AdminRequest::new(action, vec![service_id.to_string()]))
+        }
+        ["reload"] => Some(AdminRequest::new("reload", Vec::new())),
+        ["shutdown"] => Some(AdminRequest::new("quit", Vec::new())),
+        _ => None,
+    }
+}
+
+async fn handle_request(
+    request: Request<Incoming>,
+    context: Arc<RequestContext>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let service_manager = match context.service_manager.upgrade() {
+        Some(service_manager) => service_manager,
+        None => {
+            return Ok(error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Shutting down",
+            ))
+        }
+    };
+
+    let path = request
+        .uri()
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let response = match (request.method(), path.as_slice()) {
+        (&Method::GET, ["status"]) => {
+            let snapshot = service_manager.status_snapshot().await;
+            json_response(status_code(snapshot.overall_status), &snapshot)
+        }
+        (&Method::GET, ["health"]) => {
+            let overall_status = service_manager.overall_status().await;
+            json_response(status_code(overall_status), &overall_status)
+        }
+        (&Method::GET, ["ready"]) => {
+            let overall_readiness = service_manager.overall_readiness().await;
+            json_response(status_code(overall_readiness), &overall_readiness)
+        }
+        (&Method::POST, path) => {
+            let admin_request = match admin_request(path) {
+                Some(admin_request) => admin_request,
+                None => return Ok(error_response(StatusCode::NOT_FOUND, "Not found")),
+            };
+
+            let token = match &context.token {
+                Some(token) => token,
+                None => {
+                    return Ok(error_response(
+                        StatusCode::FORBIDDEN,
+                        "Service control is disabled because no token is configured",
+                    ))
+                }
+            };
+
+            if !is_authorized(&request, token) {
+                return Ok(error_response(StatusCode::UNAUTHORIZED, "Invalid token"));
+            }
+
+            let response = execute(&service_manager, &context.own_id, &admin_request).await;
+            let status = match response.success {
+                true => StatusCode::OK,
+                false => StatusCode::BAD_REQUEST,
+            };
+            json_response(status, &response)
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    };
+
+    Ok(response)
+}
+
+async fn accept_connections(
+    listener: Arc<TcpListener>,
+    context: Arc<RequestContext>,
+    connections: CancellationToken,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError> {
+    loop {
+        let (stream, address) = select! {
+            _ = cancellation_token.cancelled() => return Ok(()),
+            connection = listener.accept() => connection?,
+        };
+
+        let context = Arc::clone(&context);
+        let connections = connections.clone();
+        spawn(async move {
+            let service = service_fn(move |request| handle_request(request, Arc::clone(&context)));
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            pin!(connection);
+
+            // Requests that are being handled are still answered before the connection is closed
+            let result = select! {
+                _ = connections.cancelled() => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+                result = connection.as_mut() => result,
+            };
+
+            if let Err(error) = result {
+                warn!("HTTP admin connection from {} failed: {}", address, error);
+            }
+        });
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for HttpAdminService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        let listener = TcpListener::bind(self.address).await?;
+        info!("Serving HTTP admin API on {}", self.address);
+
+        self.listener = Some(Arc::new(listener));
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+        self.connections = CancellationToken::new();
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.connections.cancel();
+        self.listener = None;
+        self.service_manager = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let listener = Arc::clone(self.listener.as_ref()?);
+        let context = Arc::new(RequestContext {
+            service_manager: Weak::clone(self.service_manager.as_ref()?),
+            token: self.token.clone(),
+            own_id: self.info.id.clone(),
+        });
+        let connections = self.connections.clone();
+
+        Some(Box::pin(async move {
+            accept_connections(listener, context, connections, cancellation_token).await
+        }))
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceMetrics {
+    pub started_at: Option<Instant>,
+    pub start_count: u32,
+    pub failure_count: u32,
+    pub last_start_duration: Option<Duration>,
+    pub background_task_restarts: u32,
+}
+
+impl ServiceMetrics {
+    pub fn uptime(&self) -> Option<Duration> {
+        self.started_at.map(|started_at| started_at.elapsed())
+    }
+}
+
+impl Display for ServiceMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.uptime() {
+            Some(uptime) => write!(
+                f,
+                "up {}",
+                humantime::format_duration(Duration::from_secs(uptime.as_secs()))
+            )?,
+            None => write!(f, "down")?,
+        }
+
+        write!(
+            f,
+            ", {} starts, {} failures, {} task restarts",
+            self.start_count, self.failure_count, self.background_task_restarts
+        )?;
+
+        if let Some(last_start_duration) = self.last_start_duration {
+            write!(f, ", last start took {}ms", last_start_duration.as_millis())?;
+        }
+
+        Ok(())
+    }
+}
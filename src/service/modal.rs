@@ -0,0 +1,143 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use serenity::all::{
+    ActionRowComponent, CreateActionRow, CreateInputText, CreateModal, InputTextStyle,
+    ModalInteraction,
+};
+use thiserror::Error;
+
+use super::component::ComponentError;
+
+// Discord rejects modals with more text inputs than this
+pub const MAX_MODAL_INPUTS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum ModalValueError {
+    #[error("Modal field {0} was not filled in")]
+    Missing(String),
+
+    #[error("Modal field {0} has an invalid value: {1}")]
+    Invalid(String, String),
+}
+
+// Created through ComponentScope::modal, so the submission is routed back to the module that opened the modal
+#[derive(Debug, Clone)]
+pub struct ModalBuilder {
+    custom_id: String,
+    title: String,
+    inputs: Vec<CreateInputText>,
+}
+
+impl ModalBuilder {
+    pub fn new(custom_id: String, title: &str) -> Self {
+        Self {
+            custom_id,
+            title: title.to_string(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn with_short_input(self, field: &str, label: &str, required: bool) -> Self {
+        self.with_input(
+            CreateInputText::new(InputTextStyle::Short, label, field).required(required),
+        )
+    }
+
+    pub fn with_paragraph_input(self, field: &str, label: &str, required: bool) -> Self {
+        self.with_input(
+            CreateInputText::new(InputTextStyle::Paragraph, label, field).required(required),
+        )
+    }
+
+    // For inputs that need more configuration, like placeholders or length limits
+    pub fn with_input(mut self, input: CreateInputText) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn custom_id(&self) -> &str {
+        &self.custom_id
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    // Discord rejects modals with too many inputs without telling why, so this is checked before sending it
+    pub fn build(self) -> Result<CreateModal, ComponentError> {
+        if self.inputs.len() > MAX_MODAL_INPUTS {
+            return Err(ComponentError::TooManyModalInputs(
+                self.custom_id,
+                self.inputs.len(),
+            ));
+        }
+
+        let components = self
+            .inputs
+            .into_iter()
+            .map(CreateActionRow::InputText)
+            .collect();
+
+        Ok(CreateModal::new(self.custom_id, self.title).components(components))
+    }
+}
+
+// The values of a submitted modal by field. Optional fields that were left empty are treated as missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModalValues {
+    values: HashMap<String, String>,
+}
+
+impl ModalValues {
+    pub fn from_interaction(interaction: &ModalInteraction) -> Self {
+        let values = interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|action_row| action_row.components.iter())
+            .filter_map(|component| match component {
+                ActionRowComponent::InputText(input_text) => input_text
+                    .value
+                    .as_ref()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (input_text.custom_id.clone(), value.clone())),
+                _ => None,
+            })
+            .collect();
+
+        Self { values }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.values.get(field).map(|value| value.as_str())
+    }
+
+    pub fn require(&self, field: &str) -> Result<&str, ModalValueError> {
+        self.get(field)
+            .ok_or_else(|| ModalValueError::Missing(field.to_string()))
+    }
+
+    pub fn parse<T>(&self, field: &str) -> Result<T, ModalValueError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.require(field)?;
+
+        value
+            .trim()
+            .parse()
+            .map_err(|error: T::Err| ModalValueError::Invalid(field.to_string(), error.to_string()))
+    }
+
+    pub fn parse_optional<T>(&self, field: &str) -> Result<Option<T>, ModalValueError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.get(field) {
+            Some(_) => self.parse(field).map(Some),
+            None => Ok(None),
+        }
+    }
+}
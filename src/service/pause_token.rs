@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use tokio::sync::watch::{self, Sender};
+
+#[derive(Debug, Clone)]
+pub struct PauseToken {
+    sender: Arc<Sender<bool>>,
+}
+
+impl PauseToken {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub fn pause(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.sender.send_replace(false);
+    }
+
+    // Background tasks should call this at points where they can safely be suspended
+    pub async fn wait_while_paused(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|paused| !*paused).await;
+    }
+}
+
+impl Default for PauseToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
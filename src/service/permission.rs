@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::PathBuf,
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{Guild, GuildId, Member, Permissions, RoleId, UserId},
+    async_trait,
+};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use super::{BoxedError, Priority, Service, ServiceContext, ServiceInfo, StartupPhase};
+
+#[derive(Debug, Error)]
+pub enum PermissionOverrideError {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+
+    #[error("Unable to serialize or deserialize permission overrides: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Requirement {
+    Everyone,
+    BotAdmin,
+    GuildOwner,
+    User(UserId),
+    Role(RoleId),
+    Permissions(Permissions),
+    Any(Vec<Requirement>),
+    All(Vec<Requirement>),
+}
+
+impl Requirement {
+    pub fn is_met(&self, context: &PermissionContext, bot_admins: &HashSet<UserId>) -> bool {
+        match self {
+            Requirement::Everyone => true,
+            Requirement::BotAdmin => bot_admins.contains(&context.user_id),
+            Requirement::GuildOwner => context.is_guild_owner,
+            Requirement::User(user_id) => context.user_id == *user_id,
+            Requirement::Role(role_id) => context.role_ids.contains(role_id),
+            Requirement::Permissions(permissions) => context
+                .permissions
+                .is_some_and(|context_permissions| context_permissions.contains(*permissions)),
+            Requirement::Any(requirements) => requirements
+                .iter()
+                .any(|requirement| requirement.is_met(context, bot_admins)),
+            Requirement::All(requirements) => requirements
+                .iter()
+                .all(|requirement| requirement.is_met(context, bot_admins)),
+        }
+    }
+}
+
+// Everything about the invoking user that requirements are checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionContext {
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    pub role_ids: Vec<RoleId>,
+    pub is_guild_owner: bool,
+    pub permissions: Option<Permissions>,
+}
+
+impl PermissionContext {
+    // Outside of guilds, only user-based requirements can be met
+    pub fn direct_message(user_id: UserId) -> Self {
+        Self {
+            user_id,
+            guild_id: None,
+            role_ids: Vec::new(),
+            is_guild_owner: false,
+            permissions: None,
+        }
+    }
+
+    pub fn from_member(guild: &Guild, member: &Member) -> Self {
+        Self {
+            user_id: member.user.id,
+            guild_id: Some(guild.id),
+            role_ids: member.roles.clone(),
+            is_guild_owner: guild.owner_id == member.user.id,
+            permissions: Some(guild.member_permissions(member)),
+        }
+    }
+}
+
+type PermissionOverrides = HashMap<GuildId, HashMap<String, Requirement>>;
+
+pub struct PermissionService {
+    info: ServiceInfo,
+    bot_admins: HashSet<UserId>,
+    overrides_path: PathBuf,
+    overrides: RwLock<PermissionOverrides>,
+}
+
+impl PermissionService {
+    pub fn new(bot_admins: Vec<UserId>, overrides_path: PathBuf) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_permissions", "Permissions", Priority::Critical)
+                .with_phase(StartupPhase::Infrastructure),
+            bot_admins: bot_admins.into_iter().collect(),
+            overrides_path,
+            overrides: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_bot_admin(&self, user_id: UserId) -> bool {
+        self.bot_admins.contains(&user_id)
+    }
+
+    // Guilds can override the requirement a command declared. Bot admins are always permitted.
+    pub async fn is_permitted(
+        &self,
+        command: &str,
+        default_requirement: &Requirement,
+        context: &PermissionContext,
+    ) -> bool {
+        if self.is_bot_admin(context.user_id) {
+            return true;
+        }
+
+        let requirement = match context.guild_id {
+            Some(guild_id) => self.get_override(guild_id, command).await,
+            None => None,
+        };
+
+        requirement
+            .as_ref()
+            .unwrap_or(default_requirement)
+            .is_met(context, &self.bot_admins)
+    }
+
+    pub async fn get_override(&self, guild_id: GuildId, command: &str) -> Option<Requirement> {
+        let overrides = self.overrides.read().await;
+        overrides
+            .get(&guild_id)
+            .and_then(|guild_overrides| guild_overrides.get(command))
+            .cloned()
+    }
+
+    pub async fn set_override(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+        requirement: Requirement,
+    ) -> Result<(), PermissionOverrideError> {
+        let mut overrides = self.overrides.write().await;
+        overrides
+            .entry(guild_id)
+            .or_default()
+            .insert(command.to_string(), requirement);
+
+        self.save_overrides(&overrides)
+    }
+
+    // Returns false if there was no override to remove
+    pub async fn remove_override(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+    ) -> Result<bool, PermissionOverrideError> {
+        let mut overrides = self.overrides.write().await;
+
+        let removed = match overrides.get_mut(&guild_id) {
+            Some(guild_overrides) => guild_overrides.remove(command).is_some(),
+            None => false,
+        };
+
+        if !removed {
+            return Ok(false);
+        }
+
+        overrides.retain(|_, guild_overrides| !guild_overrides.is_empty());
+        self.save_overrides(&overrides)?;
+
+        Ok(true)
+    }
+
+    fn load_overrides(&self) -> Result<PermissionOverrides, PermissionOverrideError> {
+        if !self.overrides_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let overrides_json = fs::read_to_string(&self.overrides_path)?;
+        let overrides = serde_json::from_str(&overrides_json)?;
+
+        Ok(overrides)
+    }
+
+    fn save_overrides(
+        &self,
+        overrides: &PermissionOverrides,
+    ) -> Result<(), PermissionOverrideError> {
+        if let Some(parent) = self.overrides_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let overrides_json = serde_json::to_string_pretty(overrides)?;
+        fs::write(&self.overrides_path, overrides_json)?;
+
+        Ok(())
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for PermissionService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, _context: ServiceContext) -> Result<(), BoxedError> {
+        let overrides = self.load_overrides()?;
+        info!("Loaded permission overrides for {} guilds", overrides.len());
+
+        *self.overrides.write().await = overrides;
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        let overrides = self.overrides.read().await;
+        self.save_overrides(&overrides)?;
+
+        Ok(())
+    }
+}
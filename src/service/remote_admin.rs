@@ -0,0 +1,223 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Weak},
+};
+
+use log::{info, warn};
+use serenity::async_trait;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    select, spawn,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    admin::{execute, tokens_match, RemoteAdminRequest, RemoteAdminResponse},
+    BoxedError, LifetimedPinnedBoxedFutureResult, Priority, Service, ServiceContext, ServiceInfo,
+    ServiceManager,
+};
+
+// Remote administration over TCP with one JSON message per line. Clients have to authenticate with the token first.
+// The connection is not encrypted, so it should only be exposed through a tunnel or a private network.
+pub struct RemoteAdminService {
+    info: ServiceInfo,
+    address: SocketAddr,
+    token: String,
+    listener: Option<Arc<TcpListener>>,
+    service_manager: Option<Weak<ServiceManager>>,
+    // Cancelled in stop, so open sessions don't outlive the service
+    connections: CancellationToken,
+}
+
+impl RemoteAdminService {
+    pub fn new(address: SocketAddr, token: &str) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_remote_admin", "Remote Admin", Priority::Low),
+            address,
+            token: token.to_string(),
+            listener: None,
+            service_manager: None,
+            connections: CancellationToken::new(),
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+async fn respond(
+    service_manager: &ServiceManager,
+    own_id: &str,
+    request: &RemoteAdminRequest,
+) -> RemoteAdminResponse {
+    if let RemoteAdminRequest::Status = request {
+        return RemoteAdminResponse::Status {
+            snapshot: service_manager.status_snapshot().await,
+        };
+    }
+
+    match request.to_admin_request() {
+        Some(admin_request) => execute(service_manager, own_id, &admin_request)
+            .await
+            .into(),
+        None => RemoteAdminResponse::Error {
+            message: "Already authenticated".to_string(),
+        },
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    token: Arc<String>,
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+) -> Result<(), BoxedError> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut authenticated = false;
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = match serde_json::from_str::<RemoteAdminRequest>(&line) {
+            Ok(request) => request,
+            Err(error) => {
+                let response = RemoteAdminResponse::Error {
+                    message: format!("Invalid request: {}", error),
+                };
+                write_response(&mut writer, &response).await?;
+                continue;
+            }
+        };
+
+        let response = match (&request, authenticated) {
+            (RemoteAdminRequest::Authenticate { token: given }, false) => {
+                if !tokens_match(&token, given) {
+                    let response = RemoteAdminResponse::Error {
+                        message: "Invalid token".to_string(),
+                    };
+                    write_response(&mut writer, &response).await?;
+                    return Ok(());
+                }
+
+                authenticated = true;
+                RemoteAdminResponse::Authenticated
+            }
+            (_, false) => {
+                let response = RemoteAdminResponse::Error {
+                    message: "Not authenticated".to_string(),
+                };
+                write_response(&mut writer, &response).await?;
+                return Ok(());
+            }
+            (request, true) => match service_manager.upgrade() {
+                Some(service_manager) => respond(&service_manager, &own_id, request).await,
+                None => return Ok(()),
+            },
+        };
+
+        write_response(&mut writer, &response).await?;
+    }
+
+    Ok(())
+}
+
+async fn write_response(
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    response: &RemoteAdminResponse,
+) -> Result<(), BoxedError> {
+    let mut response = serde_json::to_string(response)?;
+    response.push('\n');
+    writer.write_all(response.as_bytes()).await?;
+
+    Ok(())
+}
+
+async fn accept_connections(
+    listener: Arc<TcpListener>,
+    token: Arc<String>,
+    service_manager: Weak<ServiceManager>,
+    own_id: String,
+    connections: CancellationToken,
+    cancellation_token: CancellationToken,
+) -> Result<(), BoxedError> {
+    loop {
+        let (stream, address) = select! {
+            _ = cancellation_token.cancelled() => return Ok(()),
+            connection = listener.accept() => connection?,
+        };
+
+        let token = Arc::clone(&token);
+        let service_manager = Weak::clone(&service_manager);
+        let own_id = own_id.clone();
+        let connections = connections.clone();
+        spawn(async move {
+            let result = select! {
+                _ = connections.cancelled() => Ok(()),
+                result = handle_connection(stream, token, service_manager, own_id) => result,
+            };
+
+            if let Err(error) = result {
+                warn!("Remote admin connection from {} failed: {}", address, error);
+            }
+        });
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for RemoteAdminService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        if self.token.is_empty() {
+            return Err("Remote admin token must not be empty".into());
+        }
+
+        let listener = TcpListener::bind(self.address).await?;
+        info!("Listening for remote admin connections on {}", self.address);
+
+        self.listener = Some(Arc::new(listener));
+        self.service_manager = Some(Arc::downgrade(&context.service_manager));
+        self.connections = CancellationToken::new();
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.connections.cancel();
+        self.listener = None;
+        self.service_manager = None;
+
+        Ok(())
+    }
+
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let listener = Arc::clone(self.listener.as_ref()?);
+        let service_manager = Weak::clone(self.service_manager.as_ref()?);
+        let token = Arc::new(self.token.clone());
+        let own_id = self.info.id.clone();
+        let connections = self.connections.clone();
+
+        Some(Box::pin(async move {
+            accept_connections(
+                listener,
+                token,
+                service_manager,
+                own_id,
+                connections,
+                cancellation_token,
+            )
+            .await
+        }))
+    }
+}
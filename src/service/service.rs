@@ -2,16 +2,19 @@ use std::{
     cmp::Ordering,
     hash::{Hash, Hasher},
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
 use downcast_rs::{impl_downcast, DowncastSync};
+use tokio_util::sync::CancellationToken;
 
 use crate::event::Observable;
 
 use super::{
-    service_manager::ServiceManager,
-    types::{Priority, Status},
+    context::{ServiceContext, ServiceDependency},
+    pause_token::PauseToken,
+    types::{Priority, Readiness, StartupPhase, Status, TaskRestartPolicy},
     BoxedError, LifetimedPinnedBoxedFutureResult,
 };
 
@@ -20,8 +23,16 @@ pub struct ServiceInfo {
     pub id: String,
     pub name: String,
     pub priority: Priority,
-
-    pub status: Observable<Status>,
+    pub phase: StartupPhase,
+    pub groups: Vec<String>,
+    pub dependencies: Vec<ServiceDependency>,
+
+    pub status: Arc<Observable<Status>>,
+    pub readiness: Arc<Observable<Readiness>>,
+    pub manual_readiness: bool,
+    pub lazy: bool,
+    pub task_restart_policy: Option<TaskRestartPolicy>,
+    pub pause_token: PauseToken,
 }
 
 impl ServiceInfo {
@@ -30,11 +41,110 @@ impl ServiceInfo {
             id: id.to_string(),
             name: name.to_string(),
             priority,
-            status: Observable::new(Status::Stopped, format!("{}_status_change", id)),
+            phase: StartupPhase::Core,
+            groups: Vec::new(),
+            dependencies: Vec::new(),
+            status: Arc::new(
+                Observable::new(Status::Stopped, format!("{}_status_change", id)).with_sticky(true),
+            ),
+            readiness: Arc::new(
+                Observable::new(Readiness::NotReady, format!("{}_readiness_change", id))
+                    .with_sticky(true),
+            ),
+            manual_readiness: false,
+            lazy: false,
+            task_restart_policy: None,
+            pause_token: PauseToken::new(),
+        }
+    }
+
+    // Services that can be registered multiple times need a distinct ID per instance
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self.status = Arc::new(
+            Observable::new(Status::Stopped, format!("{}_status_change", id)).with_sticky(true),
+        );
+        self.readiness = Arc::new(
+            Observable::new(Readiness::NotReady, format!("{}_readiness_change", id))
+                .with_sticky(true),
+        );
+        self
+    }
+
+    // The service reports readiness itself instead of being marked as ready as soon as it started
+    pub fn with_manual_readiness(mut self) -> Self {
+        self.manual_readiness = true;
+        self
+    }
+
+    // Lazy services are not started by start_services, but the first time they are requested
+    pub fn with_lazy_startup(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    // Lets the watchdog re-spawn the background task before marking the service as failed
+    pub fn with_task_restart(mut self, max_restarts: u32, delay: Duration) -> Self {
+        self.task_restart_policy = Some(TaskRestartPolicy {
+            max_restarts,
+            delay,
+        });
+        self
+    }
+
+    pub fn with_phase(mut self, phase: StartupPhase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_dependency<T>(mut self) -> Self
+    where
+        T: Service,
+    {
+        let dependency = ServiceDependency::of::<T>();
+        if !self.dependencies.contains(&dependency) {
+            self.dependencies.push(dependency);
+        }
+
+        self
+    }
+
+    // Replaces a dependency on any service of the type with a dependency on the service with the given ID
+    pub fn with_instance_dependency<T>(mut self, service_id: &str) -> Self
+    where
+        T: Service,
+    {
+        self.dependencies
+            .retain(|dependency| !dependency.is_type_of::<T>());
+        self.dependencies
+            .push(ServiceDependency::of_instance::<T>(service_id));
+
+        self
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        if !self.is_in_group(group) {
+            self.groups.push(group.to_string());
         }
+
+        self
+    }
+
+    pub fn is_in_group(&self, group: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|service_group| service_group == group)
     }
 }
 
+// IDs are used in config lookups, event names and CLI addressing, so they are restricted to [a-z0-9_]
+pub fn is_valid_service_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|character| matches!(character, 'a'..='z' | '0'..='9' | '_'))
+}
+
 impl PartialEq for ServiceInfo {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -64,15 +174,34 @@ impl Hash for ServiceInfo {
 #[async_trait]
 pub trait Service: DowncastSync {
     fn info(&self) -> &ServiceInfo;
-    async fn start(&mut self, service_manager: Arc<ServiceManager>) -> Result<(), BoxedError>;
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError>;
     async fn stop(&mut self) -> Result<(), BoxedError>;
-    fn task<'a>(&self) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+    // Should stop accepting new work and return once in-flight work is finished. The service is stopped afterwards.
+    async fn drain(&mut self) -> Result<(), BoxedError> {
+        Ok(())
+    }
+
+    // The task should finish on its own once the cancellation token is cancelled. If it doesn't, it will be aborted after a grace period.
+    fn task<'a>(
+        &self,
+        _cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        None
+    }
+
+    // Like the task, the check works on state cloned out of the service, so the service is not locked while it runs.
+    // Services without a check are considered healthy.
+    fn health_check<'a>(&self) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
         None
     }
 
     async fn is_available(&self) -> bool {
         matches!(self.info().status.get().await, Status::Started)
     }
+
+    async fn is_ready(&self) -> bool {
+        self.is_available().await && self.info().readiness.get().await == Readiness::Ready
+    }
 }
 
 impl_downcast!(sync Service);
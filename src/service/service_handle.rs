@@ -0,0 +1,68 @@
+use std::{
+    any::{Any, TypeId},
+    ops::Deref,
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+
+use super::Service;
+
+// Keeps a typed reference next to the type-erased one, so services can be downcasted without unsafe code
+#[derive(Clone)]
+pub struct ServiceHandle {
+    service: Arc<Mutex<dyn Service>>,
+    any: Arc<dyn Any + Send + Sync>,
+}
+
+impl ServiceHandle {
+    pub fn new<T>(service: T) -> Self
+    where
+        T: Service,
+    {
+        Self::from(Arc::new(Mutex::new(service)))
+    }
+
+    pub fn service(&self) -> &Arc<Mutex<dyn Service>> {
+        &self.service
+    }
+
+    // The TypeId of the Mutex<T> the handle points to
+    pub fn type_id(&self) -> TypeId {
+        (*self.any).type_id()
+    }
+
+    pub fn is<T>(&self) -> bool
+    where
+        T: Service,
+    {
+        self.any.is::<Mutex<T>>()
+    }
+
+    pub fn downcast<T>(&self) -> Option<Arc<Mutex<T>>>
+    where
+        T: Service,
+    {
+        Arc::clone(&self.any).downcast::<Mutex<T>>().ok()
+    }
+}
+
+impl<T> From<Arc<Mutex<T>>> for ServiceHandle
+where
+    T: Service,
+{
+    fn from(service: Arc<Mutex<T>>) -> Self {
+        Self {
+            service: Arc::clone(&service) as Arc<Mutex<dyn Service>>,
+            any: service,
+        }
+    }
+}
+
+impl Deref for ServiceHandle {
+    type Target = Arc<Mutex<dyn Service>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.service
+    }
+}
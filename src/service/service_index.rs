@@ -0,0 +1,115 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::event::Observable;
+
+use super::{
+    Priority, Readiness, Service, ServiceHandle, ServiceInfoSnapshot, StartupPhase, Status,
+};
+
+// Caches the parts of a service's ServiceInfo that don't change after registration,
+// so the ServiceManager doesn't have to lock every service for lookups and status queries
+#[derive(Clone)]
+pub struct IndexedService {
+    pub handle: ServiceHandle,
+    pub id: String,
+    pub name: String,
+    pub priority: Priority,
+    pub phase: StartupPhase,
+    pub groups: Vec<String>,
+    pub lazy: bool,
+    pub status: Arc<Observable<Status>>,
+    pub readiness: Arc<Observable<Readiness>>,
+}
+
+impl IndexedService {
+    pub fn new(handle: ServiceHandle, service: &dyn Service) -> Self {
+        let info = service.info();
+
+        Self {
+            handle,
+            id: info.id.clone(),
+            name: info.name.clone(),
+            priority: info.priority,
+            phase: info.phase,
+            groups: info.groups.clone(),
+            lazy: info.lazy,
+            status: Arc::clone(&info.status),
+            readiness: Arc::clone(&info.readiness),
+        }
+    }
+
+    pub fn is_in_group(&self, group: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|service_group| service_group == group)
+    }
+
+    pub async fn snapshot(&self) -> ServiceInfoSnapshot {
+        ServiceInfoSnapshot {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            priority: self.priority,
+            groups: self.groups.clone(),
+            status: self.status.get().await,
+            readiness: self.readiness.get().await,
+            since: self.status.last_changed().await,
+        }
+    }
+}
+
+// Keeps services in registration order while allowing lookups by ID
+#[derive(Default)]
+pub struct ServiceIndex {
+    order: Vec<String>,
+    services: HashMap<String, IndexedService>,
+}
+
+impl ServiceIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, service_id: &str) -> bool {
+        self.services.contains_key(service_id)
+    }
+
+    pub fn get(&self, service_id: &str) -> Option<&IndexedService> {
+        self.services.get(service_id)
+    }
+
+    // Returns false if a service with the same ID is already indexed
+    pub fn insert(&mut self, service: IndexedService) -> bool {
+        if self.contains(&service.id) {
+            return false;
+        }
+
+        self.order.push(service.id.clone());
+        self.services.insert(service.id.clone(), service);
+
+        true
+    }
+
+    // Swaps out the service with the same ID while keeping its position in the registration order
+    pub fn replace(&mut self, service: IndexedService) -> Option<IndexedService> {
+        if !self.contains(&service.id) {
+            return None;
+        }
+
+        self.services.insert(service.id.clone(), service)
+    }
+
+    pub fn remove(&mut self, service_id: &str) -> Option<IndexedService> {
+        let service = self.services.remove(service_id)?;
+        self.order.retain(|id| id != service_id);
+
+        Some(service)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &IndexedService> {
+        self.order.iter().filter_map(|id| self.services.get(id))
+    }
+}
@@ -1,40 +1,123 @@
 use super::{
-    service::Service,
-    types::{OverallStatus, Priority, ShutdownError, StartupError, Status},
+    circuit_breaker::CircuitBreaker,
+    command_registry::CommandRegistry,
+    config_registry::ConfigRegistry,
+    context::{ServiceContext, ServiceDependency},
+    health_monitor::HealthMonitor,
+    metrics::ServiceMetrics,
+    service::{is_valid_service_id, Service},
+    service_handle::ServiceHandle,
+    service_index::{IndexedService, ServiceIndex},
+    snapshot::{BackgroundTaskSnapshot, ServiceInfoSnapshot, StatusSnapshot},
+    types::{
+        BuildError, CircuitBreakerResetError, DrainError, OverallStatus, PauseError, Readiness,
+        RegistrationError, RemovalError, ReplacementError, RestartError, ResumeError,
+        ShutdownError, StartupError, StartupPhase, Status, WaitError,
+    },
+    BoxedError,
+};
+use crate::{
+    event::{Event, EventBus, EventRepeater},
+    service::Taskchain,
 };
-use crate::{event::EventRepeater, service::Taskchain};
 use log::{error, info, warn};
 use std::{
+    any::type_name,
     collections::HashMap,
-    error::Error,
     fmt::{self, Display},
-    mem,
     sync::{Arc, OnceLock, Weak},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 use tokio::{
-    spawn,
-    sync::{Mutex, MutexGuard},
+    select, spawn,
+    sync::{Mutex, MutexGuard, RwLock},
     task::JoinHandle,
-    time::timeout,
+    time::{sleep, timeout},
 };
+use tokio_util::sync::CancellationToken;
+use tracing::{field, info_span, instrument, Instrument, Span};
 
-#[derive(Default)]
 pub struct ServiceManagerBuilder {
-    services: Vec<Arc<Mutex<dyn Service>>>,
+    services: Vec<ServiceHandle>,
+    health_check_interval: Duration,
+    health_check_timeout: Duration,
+    health_check_failure_threshold: u32,
+    circuit_breaker_threshold: u32,
+    circuit_breaker_window: Duration,
+    task_grace_period: Duration,
+    drain_timeout: Duration,
+    config_registry: ConfigRegistry,
 }
 
 impl ServiceManagerBuilder {
     pub fn new() -> Self {
         Self {
             services: Vec::new(),
+            health_check_interval: Duration::from_secs(30),
+            health_check_timeout: Duration::from_secs(5),
+            health_check_failure_threshold: 3,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_window: Duration::from_secs(300),
+            task_grace_period: Duration::from_secs(5),
+            drain_timeout: Duration::from_secs(30),
+            config_registry: ConfigRegistry::new(),
         }
     }
 
+    // Makes the config available to services through the ServiceManager's config registry, before any of them starts
+    pub fn with_config<T>(mut self, config: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.config_registry = self.config_registry.with_config(config);
+        self
+    }
+
+    // How long each service gets to finish its in-flight work when draining
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    // How long background tasks get to finish after being cancelled before they are aborted
+    pub fn with_task_grace_period(mut self, grace_period: Duration) -> Self {
+        self.task_grace_period = grace_period;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, window: Duration) -> Self {
+        self.circuit_breaker_threshold = failure_threshold;
+        self.circuit_breaker_window = window;
+        self
+    }
+
+    pub fn with_health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
+
+    pub fn with_health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+
+    pub fn with_health_check_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.health_check_failure_threshold = failure_threshold;
+        self
+    }
+
     //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
-    pub async fn with_service(mut self, service: Arc<Mutex<dyn Service>>) -> Self {
+    pub async fn with_service<S>(mut self, service: S) -> Result<Self, BuildError>
+    where
+        S: Into<ServiceHandle>,
+    {
+        let service = service.into();
         let lock = service.lock().await;
 
+        if !is_valid_service_id(&lock.info().id) {
+            return Err(BuildError::InvalidServiceId(lock.info().id.clone()));
+        }
+
         let mut found = false;
         for registered_service in self.services.iter() {
             let registered_service = registered_service.lock().await;
@@ -50,21 +133,54 @@ impl ServiceManagerBuilder {
                 lock.info().name,
                 lock.info().id
             );
-            return self;
+            return Ok(self);
         }
 
         drop(lock);
 
         self.services.push(service);
-        self
+        Ok(self)
     }
 
     pub async fn build(self) -> Arc<ServiceManager> {
+        let mut services = ServiceIndex::new();
+        for service in self.services {
+            let service_lock = service.lock().await;
+            let indexed_service = IndexedService::new(service.clone(), &*service_lock);
+            drop(service_lock);
+
+            services.insert(indexed_service);
+        }
+
         let service_manager = ServiceManager {
             weak: OnceLock::new(),
-            services: self.services,
+            services: RwLock::new(services),
+            shutdown_request: CancellationToken::new(),
             background_tasks: Mutex::new(HashMap::new()),
+            task_grace_period: self.task_grace_period,
+            drain_timeout: self.drain_timeout,
+            metrics: Arc::new(Mutex::new(HashMap::new())),
             on_status_change: EventRepeater::new("service_manager_on_status_change").await,
+            on_service_added: Event::new("service_manager_on_service_added"),
+            on_service_removed: Event::new("service_manager_on_service_removed"),
+            on_service_started: Event::new("service_manager_on_service_started"),
+            on_service_stopped: Event::new("service_manager_on_service_stopped"),
+            on_service_failed: Event::new("service_manager_on_service_failed"),
+            on_service_draining: Event::new("service_manager_on_service_draining"),
+            on_service_drained: Event::new("service_manager_on_service_drained"),
+            on_reload_requested: Event::new("service_manager_on_reload_requested"),
+            health_monitor: HealthMonitor::new(
+                self.health_check_interval,
+                self.health_check_failure_threshold,
+            )
+            .with_check_timeout(self.health_check_timeout),
+            circuit_breaker: CircuitBreaker::new(
+                self.circuit_breaker_threshold,
+                self.circuit_breaker_window,
+            ),
+            command_registry: CommandRegistry::new(),
+            config_registry: self.config_registry,
+            event_bus: EventBus::new(),
         };
 
         let arc = Arc::new(service_manager);
@@ -76,16 +192,50 @@ impl ServiceManagerBuilder {
             unreachable!("Unable to set ServiceManager's Weak self-reference in ServiceManagerBuilder because it was already set.");
         }
 
+        arc.health_monitor.start(Arc::downgrade(&arc)).await;
+
         arc
     }
 }
 
+impl Default for ServiceManagerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct BackgroundTask {
+    join_handle: JoinHandle<Result<(), BoxedError>>,
+    cancellation_token: CancellationToken,
+    started_at: SystemTime,
+    started: Instant,
+}
+
 pub struct ServiceManager {
     weak: OnceLock<Weak<Self>>,
-    background_tasks: Mutex<HashMap<String, JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>>>,
-
-    pub services: Vec<Arc<Mutex<dyn Service>>>,
-    pub on_status_change: Arc<EventRepeater<Status>>,
+    background_tasks: Mutex<HashMap<String, BackgroundTask>>,
+    task_grace_period: Duration,
+    drain_timeout: Duration,
+    metrics: Arc<Mutex<HashMap<String, ServiceMetrics>>>,
+    services: RwLock<ServiceIndex>,
+    shutdown_request: CancellationToken,
+
+    // The ID of the service and its new status
+    pub on_status_change: Arc<EventRepeater<(String, Status)>>,
+    pub on_service_added: Event<ServiceInfoSnapshot>,
+    pub on_service_removed: Event<ServiceInfoSnapshot>,
+    pub on_service_started: Event<ServiceInfoSnapshot>,
+    pub on_service_stopped: Event<ServiceInfoSnapshot>,
+    pub on_service_failed: Event<ServiceInfoSnapshot>,
+    pub on_service_draining: Event<ServiceInfoSnapshot>,
+    pub on_service_drained: Event<ServiceInfoSnapshot>,
+    pub on_reload_requested: Event<()>,
+    pub health_monitor: HealthMonitor,
+    pub circuit_breaker: CircuitBreaker,
+    pub command_registry: CommandRegistry,
+    pub config_registry: ConfigRegistry,
+    // For events between modules, which do not belong to the ServiceManager itself
+    pub event_bus: EventBus,
 }
 
 impl ServiceManager {
@@ -93,30 +243,184 @@ impl ServiceManager {
         ServiceManagerBuilder::new()
     }
 
+    // Services can not stop the bot themselves, so they ask whoever runs it to shut down
+    pub fn request_shutdown(&self) {
+        self.shutdown_request.cancel();
+    }
+
+    pub fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_request.is_cancelled()
+    }
+
+    pub async fn shutdown_requested(&self) {
+        self.shutdown_request.cancelled().await;
+    }
+
+    // Services that can apply a new configuration at runtime subscribe to on_reload_requested
+    pub async fn request_reload(&self) {
+        let _ = self.on_reload_requested.dispatch(Arc::new(())).await;
+    }
+
     pub async fn manages_service(&self, service_id: &str) -> bool {
-        for service in self.services.iter() {
-            let service_lock = service.lock().await;
+        self.services.read().await.contains(service_id)
+    }
 
-            if service_lock.info().id == service_id {
-                return true;
-            }
+    pub async fn services(&self) -> Vec<ServiceHandle> {
+        self.services
+            .read()
+            .await
+            .iter()
+            .map(|service| service.handle.clone())
+            .collect()
+    }
+
+    pub async fn add_service<S>(&self, service: S, start: bool) -> Result<(), RegistrationError>
+    where
+        S: Into<ServiceHandle>,
+    {
+        let service = service.into();
+        let service_lock = service.lock().await;
+        let indexed_service = IndexedService::new(service.clone(), &*service_lock);
+        drop(service_lock);
+
+        if !is_valid_service_id(&indexed_service.id) {
+            return Err(RegistrationError::InvalidServiceId(indexed_service.id));
+        }
+
+        if !self.services.write().await.insert(indexed_service.clone()) {
+            warn!(
+                "Tried to add service {} ({}), but a service with that ID already exists.",
+                indexed_service.name, indexed_service.id
+            );
+            return Err(RegistrationError::ServiceAlreadyManaged(indexed_service.id));
+        }
+
+        info!("Added service {}", indexed_service.name);
+
+        let snapshot = indexed_service.snapshot().await;
+        let _ = self.on_service_added.dispatch(Arc::new(snapshot)).await;
+
+        if start {
+            self.start_service(Arc::clone(&service)).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove_service(&self, service_id: &str) -> Result<(), RemovalError> {
+        let service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => return Err(RemovalError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        if service.priority.is_essential() {
+            return Err(RemovalError::ServiceEssential(service_id.to_string()));
+        }
+
+        let status = service.status.get().await;
+        if status.is_running() {
+            self.stop_service(Arc::clone(&service.handle)).await?;
+        } else {
+            // The service is not running anymore, but its background task and status attachment may still be around
+            let service_lock = service.handle.lock().await;
+            self.stop_background_task(&service_lock).await?;
+            drop(service_lock);
+
+            let service_status_event = service.status.as_ref().as_ref();
+            let _ = self.on_status_change.detach(service_status_event).await;
+        }
+
+        self.services.write().await.remove(service_id);
+
+        let snapshot = service.snapshot().await;
+        info!("Removed service {}", snapshot.name);
+
+        let _ = self.on_service_removed.dispatch(Arc::new(snapshot)).await;
+
+        Ok(())
+    }
+
+    // The new instance takes over the registration of the old one and is started if the old one was not stopped
+    pub async fn replace_service<S>(
+        &self,
+        service_id: &str,
+        new_service: S,
+    ) -> Result<(), ReplacementError>
+    where
+        S: Into<ServiceHandle>,
+    {
+        let old_service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => return Err(ReplacementError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let new_service = new_service.into();
+        let new_service_lock = new_service.lock().await;
+        let new_indexed_service = IndexedService::new(new_service.clone(), &*new_service_lock);
+        drop(new_service_lock);
+
+        if new_indexed_service.id != old_service.id {
+            return Err(ReplacementError::ServiceIdMismatch(
+                old_service.id,
+                new_indexed_service.id,
+            ));
+        }
+
+        let status = old_service.status.get().await;
+        if status.is_running() {
+            self.stop_service(Arc::clone(&old_service.handle)).await?;
+        } else {
+            // The old instance is not running anymore, but its background task and status attachment may still be around
+            let service_lock = old_service.handle.lock().await;
+            self.stop_background_task(&service_lock).await?;
+            drop(service_lock);
+
+            let service_status_event = old_service.status.as_ref().as_ref();
+            let _ = self.on_status_change.detach(service_status_event).await;
+        }
+
+        self.services.write().await.replace(new_indexed_service);
+
+        // The failures of the old instance should not count against the new one
+        self.circuit_breaker.reset(service_id).await;
+
+        info!("Replaced service {}", old_service.name);
+
+        if status != Status::Stopped {
+            self.start_service(Arc::clone(&new_service)).await?;
         }
 
-        false
+        Ok(())
     }
 
     pub async fn start_service(
         &self,
         service: Arc<Mutex<dyn Service>>,
+    ) -> Result<(), StartupError> {
+        self.start_service_for(service, &[]).await
+    }
+
+    // Dependents are the services that are resolving their dependencies and wait for this one, which is how cycles are detected.
+    // The service ID is only known once the service is locked, so it is recorded in the span afterwards.
+    #[instrument(name = "service", skip_all, fields(service_id = field::Empty, operation = "start"))]
+    async fn start_service_for(
+        &self,
+        service: Arc<Mutex<dyn Service>>,
+        dependents: &[String],
     ) -> Result<(), StartupError> {
         let service_id = service.lock().await.info().id.clone();
+        Span::current().record("service_id", service_id.as_str());
         if !self.manages_service(&service_id).await {
             return Err(StartupError::ServiceNotManaged(service_id.clone()));
         }
 
-        let mut service_lock = service.lock().await;
+        let service_lock = service.lock().await;
 
         let status = service_lock.info().status.get().await;
+        if matches!(status, Status::Disabled(_)) {
+            return Err(StartupError::ServiceDisabled(service_id.clone()));
+        }
+
         if !matches!(status, Status::Stopped) {
             return Err(StartupError::ServiceNotStopped(service_id.clone()));
         }
@@ -127,8 +431,14 @@ impl ServiceManager {
             ));
         }
 
-        let service_status_event = service_lock.info().status.as_ref();
-        let attachment_result = self.on_status_change.attach(service_status_event, 2).await;
+        let service_status_event = service_lock.info().status.as_ref().as_ref();
+        let status_service_id = service_id.clone();
+        let attachment_result = self
+            .on_status_change
+            .attach_mapped(service_status_event, 2, move |status| {
+                (status_service_id.clone(), status.clone())
+            })
+            .await;
         if let Err(err) = attachment_result {
             return Err(StartupError::StatusAttachmentFailed(
                 service_id.clone(),
@@ -137,7 +447,29 @@ impl ServiceManager {
         }
 
         service_lock.info().status.set(Status::Starting).await;
-        self.init_service(&mut service_lock).await?;
+        service_lock.info().readiness.set(Readiness::NotReady).await;
+
+        // Dependencies are started without holding the lock, as they may depend on this service as well
+        let dependencies = service_lock.info().dependencies.clone();
+        drop(service_lock);
+
+        let mut resolving = dependents.to_vec();
+        resolving.push(service_id.clone());
+        let resolve_result = self
+            .resolve_dependencies(&service_id, &dependencies, &resolving)
+            .await;
+
+        let mut service_lock = service.lock().await;
+        let dependencies = match resolve_result {
+            Ok(dependencies) => dependencies,
+            Err(error) => {
+                self.fail_service(&*service_lock, Status::FailedToStart(error.to_string()))
+                    .await;
+                return Err(error);
+            }
+        };
+
+        self.init_service(&mut service_lock, dependencies).await?;
         self.start_background_task(&service_lock, Arc::clone(&service))
             .await;
 
@@ -147,11 +479,13 @@ impl ServiceManager {
     }
 
     //TODO: Clean up
+    #[instrument(name = "service", skip_all, fields(service_id = field::Empty, operation = "stop"))]
     pub async fn stop_service(
         &self,
         service: Arc<Mutex<dyn Service>>,
     ) -> Result<(), ShutdownError> {
         let service_id = service.lock().await.info().id.clone();
+        Span::current().record("service_id", service_id.as_str());
         if !(self.manages_service(&service_id).await) {
             return Err(ShutdownError::ServiceNotManaged(service_id.clone()));
         }
@@ -159,17 +493,22 @@ impl ServiceManager {
         let mut service_lock = service.lock().await;
 
         let status = service_lock.info().status.get().await;
-        if !matches!(status, Status::Started) {
+        if !status.is_running() {
             return Err(ShutdownError::ServiceNotStarted(service_id.clone()));
         }
 
-        self.stop_background_task(&service_lock).await;
+        if let Err(error) = self.stop_background_task(&service_lock).await {
+            self.fail_service(&*service_lock, Status::FailedToStop(error.to_string()))
+                .await;
+            return Err(error);
+        }
+        service_lock.info().pause_token.resume();
 
         service_lock.info().status.set(Status::Stopping).await;
 
         self.shutdown_service(&mut service_lock).await?;
 
-        let service_status_event = service_lock.info().status.as_ref();
+        let service_status_event = service_lock.info().status.as_ref().as_ref();
         let detach_result = self.on_status_change.detach(service_status_event).await;
         if let Err(err) = detach_result {
             return Err(ShutdownError::StatusDetachmentFailed(
@@ -183,14 +522,220 @@ impl ServiceManager {
         Ok(())
     }
 
+    // Asks the service to stop accepting new work and waits for its in-flight work to finish. The service keeps running until it is stopped.
+    #[instrument(name = "service", skip_all, fields(service_id = %service_id, operation = "drain"))]
+    pub async fn drain_service(&self, service_id: &str) -> Result<(), DrainError> {
+        let service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => return Err(DrainError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let mut service_lock = service.handle.lock().await;
+
+        let status = service.status.get().await;
+        if !matches!(
+            status,
+            Status::Started | Status::Paused | Status::Degraded(_)
+        ) {
+            return Err(DrainError::ServiceNotStarted(service_id.to_string()));
+        }
+
+        // Paused services have to be able to work off what is left
+        service_lock.info().pause_token.resume();
+        service.status.set(Status::Draining).await;
+        service.readiness.set(Readiness::NotReady).await;
+
+        info!("Draining service {}", service.name);
+        let _ = self
+            .on_service_draining
+            .dispatch(Arc::new(service.snapshot().await))
+            .await;
+
+        let drain_result = timeout(self.drain_timeout, service_lock.drain()).await;
+        drop(service_lock);
+
+        match drain_result {
+            Ok(Ok(())) => {
+                info!("Drained service {}", service.name);
+                let _ = self
+                    .on_service_drained
+                    .dispatch(Arc::new(service.snapshot().await))
+                    .await;
+
+                Ok(())
+            }
+            Ok(Err(error)) => {
+                warn!("Service {} failed to drain: {}", service.name, error);
+                Err(DrainError::FailedToDrainService(
+                    service_id.to_string(),
+                    error.to_string(),
+                ))
+            }
+            Err(_) => {
+                warn!(
+                    "Service {} did not finish draining within {}",
+                    service.name,
+                    humantime::format_duration(self.drain_timeout)
+                );
+                Err(DrainError::Timeout(
+                    service_id.to_string(),
+                    self.drain_timeout,
+                ))
+            }
+        }
+    }
+
+    // Services are drained in the same order they are stopped in, so services of later phases stop feeding work into earlier ones first
+    pub async fn drain(&self) -> Vec<Result<(), DrainError>> {
+        let mut results = Vec::new();
+
+        for (_, services) in self.services_by_phase().await.into_iter().rev() {
+            for service in services.into_iter().rev() {
+                let status = service.status.get().await;
+                if !matches!(
+                    status,
+                    Status::Started | Status::Paused | Status::Degraded(_)
+                ) {
+                    continue;
+                }
+
+                let result = self.drain_service(&service.id).await;
+
+                results.push(result);
+            }
+        }
+
+        results
+    }
+
+    // Brings a service back up, no matter if it is currently running or failed
+    #[instrument(name = "service", skip_all, fields(service_id = %service_id, operation = "restart"))]
+    pub async fn restart_service(&self, service_id: &str) -> Result<(), RestartError> {
+        let service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => return Err(RestartError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let status = service.status.get().await;
+        match status {
+            Status::Disabled(_) => {
+                return Err(RestartError::ServiceDisabled(service_id.to_string()));
+            }
+            status if status.is_running() => {
+                self.stop_service(Arc::clone(&service.handle)).await?;
+            }
+            status if status.is_failure() => {
+                // A failed service may still have its background task and status attachment around
+                let service_lock = service.handle.lock().await;
+                self.stop_background_task(&service_lock).await?;
+                service_lock.info().pause_token.resume();
+                drop(service_lock);
+
+                let service_status_event = service.status.as_ref().as_ref();
+                let _ = self.on_status_change.detach(service_status_event).await;
+
+                service.status.set(Status::Stopped).await;
+            }
+            _ => {}
+        }
+
+        self.start_service(Arc::clone(&service.handle)).await?;
+
+        info!("Restarted service {}", service.name);
+
+        Ok(())
+    }
+
+    #[instrument(name = "service", skip_all, fields(service_id = %service_id, operation = "pause"))]
+    pub async fn pause_service(&self, service_id: &str) -> Result<(), PauseError> {
+        let service = match self.get_service_by_id(service_id).await {
+            Some(service) => service,
+            None => return Err(PauseError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let service_lock = service.lock().await;
+
+        let status = service_lock.info().status.get().await;
+        if !matches!(status, Status::Started) {
+            return Err(PauseError::ServiceNotStarted(service_id.to_string()));
+        }
+
+        service_lock.info().pause_token.pause();
+        service_lock.info().status.set(Status::Paused).await;
+
+        info!("Paused service {}", service_lock.info().name);
+
+        Ok(())
+    }
+
+    #[instrument(name = "service", skip_all, fields(service_id = %service_id, operation = "resume"))]
+    pub async fn resume_service(&self, service_id: &str) -> Result<(), ResumeError> {
+        let service = match self.get_service_by_id(service_id).await {
+            Some(service) => service,
+            None => return Err(ResumeError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let service_lock = service.lock().await;
+
+        let status = service_lock.info().status.get().await;
+        if !matches!(status, Status::Paused) {
+            return Err(ResumeError::ServiceNotPaused(service_id.to_string()));
+        }
+
+        service_lock.info().pause_token.resume();
+        service_lock.info().status.set(Status::Started).await;
+
+        info!("Resumed service {}", service_lock.info().name);
+
+        Ok(())
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
     pub async fn start_services(&self) -> Vec<Result<(), StartupError>> {
         let mut results = Vec::new();
+        let mut failed_phase = None;
+
+        for (phase, services) in self.services_by_phase().await {
+            if let Some(failed_phase) = failed_phase {
+                for service in services {
+                    results.push(Err(StartupError::PreviousPhaseFailed(
+                        service.id,
+                        failed_phase,
+                    )));
+                }
 
-        for service in &self.services {
-            let service_arc_clone = Arc::clone(service);
-            let result = self.start_service(service_arc_clone).await;
+                continue;
+            }
 
-            results.push(result);
+            for service in services.iter() {
+                // Lazy services are started the first time they are requested
+                if service.lazy {
+                    continue;
+                }
+
+                let result = self.start_service(Arc::clone(&service.handle)).await;
+
+                results.push(result);
+            }
+
+            // The next phase may only start once all essential services of this phase are up
+            for service in services.iter() {
+                if service.lazy {
+                    continue;
+                }
+
+                let status = service.status.get().await;
+                if service.priority.is_essential()
+                    && !matches!(status, Status::Started | Status::Degraded(_))
+                {
+                    error!(
+                        "Essential service {} of startup phase {} did not start. Services of later phases will not be started.",
+                        service.name, phase
+                    );
+                    failed_phase = Some(phase);
+                    break;
+                }
+            }
         }
 
         results
@@ -199,9 +744,44 @@ impl ServiceManager {
     pub async fn stop_services(&self) -> Vec<Result<(), ShutdownError>> {
         let mut results = Vec::new();
 
-        for service in &self.services {
-            let service_arc_clone = Arc::clone(service);
-            let result = self.stop_service(service_arc_clone).await;
+        for (_, services) in self.services_by_phase().await.into_iter().rev() {
+            for service in services.into_iter().rev() {
+                let result = self.stop_service(service.handle.service().clone()).await;
+
+                results.push(result);
+            }
+        }
+
+        results
+    }
+
+    pub async fn get_group(&self, group: &str) -> Vec<Arc<Mutex<dyn Service>>> {
+        self.services
+            .read()
+            .await
+            .iter()
+            .filter(|service| service.is_in_group(group))
+            .map(|service| Arc::clone(&service.handle))
+            .collect()
+    }
+
+    pub async fn start_group(&self, group: &str) -> Vec<Result<(), StartupError>> {
+        let mut results = Vec::new();
+
+        for service in self.get_group(group).await {
+            let result = self.start_service(service).await;
+
+            results.push(result);
+        }
+
+        results
+    }
+
+    pub async fn stop_group(&self, group: &str) -> Vec<Result<(), ShutdownError>> {
+        let mut results = Vec::new();
+
+        for service in self.get_group(group).await {
+            let result = self.stop_service(service).await;
 
             results.push(result);
         }
@@ -209,48 +789,261 @@ impl ServiceManager {
         results
     }
 
-    /*
-        I tried to do this in safe rust for 3 days, but I couldn't figure it out
-        Should you come up with a way to do this in safe rust, please make a PR! :)
-        Anyways, this should never cause any issues, since we checked if the service is of type T
-    */
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    pub async fn group_status(&self, group: &str) -> OverallStatus {
+        let mut group_status = OverallStatus::Healthy;
+
+        let services = self.indexed_services().await;
+        for service in services.iter().filter(|service| service.is_in_group(group)) {
+            match service.status.get().await {
+                Status::Started => {}
+                Status::Degraded(_) => group_status = OverallStatus::Degraded,
+                _ => return OverallStatus::Unhealthy,
+            }
+        }
+
+        group_status
+    }
+
+    pub async fn get_service_handle(&self, service_id: &str) -> Option<ServiceHandle> {
+        let services = self.services.read().await;
+        services
+            .get(service_id)
+            .map(|service| service.handle.clone())
+    }
+
+    pub async fn get_service_by_id(&self, service_id: &str) -> Option<Arc<Mutex<dyn Service>>> {
+        let service = self.get_service_handle(service_id).await?;
+        Some(Arc::clone(service.service()))
+    }
+
+    pub async fn get_service_by_id_as<T>(&self, service_id: &str) -> Option<Arc<Mutex<T>>>
+    where
+        T: Service,
+    {
+        let service = self.get_indexed_service(service_id).await?;
+        let typed_service = service.handle.downcast::<T>()?;
+
+        self.start_if_lazy(&service).await;
+
+        Some(typed_service)
+    }
+
     pub async fn get_service<T>(&self) -> Option<Arc<Mutex<T>>>
     where
         T: Service,
     {
-        for service in self.services.iter() {
-            let lock = service.lock().await;
+        let service = self.get_indexed_service_of::<T>().await?;
+        let typed_service = service.handle.downcast::<T>()?;
 
-            let is_t = lock.as_any().is::<T>();
-            if is_t {
-                let service_ptr: *const Arc<Mutex<dyn Service>> = service;
+        self.start_if_lazy(&service).await;
 
-                unsafe {
-                    let t_ptr: *const Arc<Mutex<T>> = mem::transmute(service_ptr);
-                    return Some(Arc::clone(&*t_ptr));
-                }
+        Some(typed_service)
+    }
+
+    pub async fn wait_for_status(
+        &self,
+        service_id: &str,
+        status: Status,
+        max_wait: Duration,
+    ) -> Result<(), WaitError> {
+        let service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => return Err(WaitError::ServiceNotManaged(service_id.to_string())),
+        };
+
+        let wait_result = timeout(
+            max_wait,
+            service.status.wait_for(|new_status| *new_status == status),
+        )
+        .await;
+
+        match wait_result {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(WaitError::EventClosed(service_id.to_string())),
+            Err(_) => Err(WaitError::Timeout(service_id.to_string(), status)),
+        }
+    }
+
+    pub async fn wait_for_started<T>(&self, max_wait: Duration) -> Result<Arc<Mutex<T>>, WaitError>
+    where
+        T: Service,
+    {
+        let service = match self.get_indexed_service_of::<T>().await {
+            Some(service) => service,
+            None => return Err(WaitError::ServiceNotManaged(type_name::<T>().to_string())),
+        };
+
+        self.wait_for_status(&service.id, Status::Started, max_wait)
+            .await?;
+
+        match service.handle.downcast::<T>() {
+            Some(typed_service) => Ok(typed_service),
+            None => Err(WaitError::ServiceNotManaged(type_name::<T>().to_string())),
+        }
+    }
+
+    pub async fn ensure_started<T>(&self) -> Result<Arc<Mutex<T>>, StartupError>
+    where
+        T: Service,
+    {
+        let service = match self.get_indexed_service_of::<T>().await {
+            Some(service) => service,
+            None => {
+                return Err(StartupError::ServiceNotManaged(
+                    type_name::<T>().to_string(),
+                ))
             }
+        };
+
+        let status = service.status.get().await;
+        if status == Status::Stopped {
+            self.start_service(Arc::clone(&service.handle)).await?;
         }
 
-        None
+        match service.handle.downcast::<T>() {
+            Some(typed_service) => Ok(typed_service),
+            None => Err(StartupError::ServiceNotManaged(
+                type_name::<T>().to_string(),
+            )),
+        }
     }
 
-    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
-    pub async fn overall_status(&self) -> OverallStatus {
-        for service in self.services.iter() {
-            let service = service.lock().await;
+    pub async fn metrics(&self) -> HashMap<String, ServiceMetrics> {
+        self.metrics.lock().await.clone()
+    }
 
-            if service.info().priority != Priority::Essential {
-                continue;
+    pub async fn service_metrics(&self, service_id: &str) -> Option<ServiceMetrics> {
+        self.metrics.lock().await.get(service_id).cloned()
+    }
+
+    pub async fn background_tasks(&self) -> Vec<BackgroundTaskSnapshot> {
+        let metrics = self.metrics.lock().await;
+        let tasks = self.background_tasks.lock().await;
+
+        let mut snapshots: Vec<BackgroundTaskSnapshot> = tasks
+            .iter()
+            .map(|(service_id, task)| BackgroundTaskSnapshot {
+                service_id: service_id.clone(),
+                is_finished: task.join_handle.is_finished(),
+                started_at: task.started_at,
+                running_for: task.started.elapsed(),
+                restarts: metrics
+                    .get(service_id)
+                    .map(|service_metrics| service_metrics.background_task_restarts)
+                    .unwrap_or(0),
+            })
+            .collect();
+        snapshots.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+
+        snapshots
+    }
+
+    pub async fn background_task(&self, service_id: &str) -> Option<BackgroundTaskSnapshot> {
+        self.background_tasks()
+            .await
+            .into_iter()
+            .find(|snapshot| snapshot.service_id == service_id)
+    }
+
+    pub(crate) async fn record_task_restart(&self, service_id: &str) {
+        let mut metrics = self.metrics.lock().await;
+        let service_metrics = metrics.entry(service_id.to_string()).or_default();
+        service_metrics.background_task_restarts += 1;
+    }
+
+    pub async fn reset_circuit_breaker(
+        &self,
+        service_id: &str,
+    ) -> Result<(), CircuitBreakerResetError> {
+        let service = match self.get_indexed_service(service_id).await {
+            Some(service) => service,
+            None => {
+                return Err(CircuitBreakerResetError::ServiceNotManaged(
+                    service_id.to_string(),
+                ))
             }
+        };
 
-            let status = service.info().status.get().await;
-            if status != Status::Started {
-                return OverallStatus::Unhealthy;
+        let status = service.status.get().await;
+        if !matches!(status, Status::Disabled(_)) {
+            return Err(CircuitBreakerResetError::ServiceNotDisabled(
+                service_id.to_string(),
+            ));
+        }
+
+        self.circuit_breaker.reset(service_id).await;
+        service.status.set(Status::Stopped).await;
+
+        info!("Reset circuit breaker of service {}", service.name);
+
+        Ok(())
+    }
+
+    // Records the failure and sets the service's status, unless the failure trips the circuit breaker
+    pub(crate) async fn fail_service(&self, service: &dyn Service, status: Status) {
+        let info = service.info();
+        info.readiness.set(Readiness::NotReady).await;
+
+        let mut metrics = self.metrics.lock().await;
+        let service_metrics = metrics.entry(info.id.clone()).or_default();
+        service_metrics.failure_count += 1;
+        service_metrics.started_at = None;
+        drop(metrics);
+
+        if self.circuit_breaker.record_failure(&info.id).await {
+            error!(
+                "Service {} failed {} times within {}. Circuit breaker tripped, the service will be disabled until it is reset.",
+                info.name,
+                self.circuit_breaker.failure_threshold,
+                humantime::format_duration(self.circuit_breaker.window)
+            );
+
+            info.status.set(Status::Disabled(status.to_string())).await;
+        } else {
+            info.status.set(status).await;
+        }
+
+        let snapshot = ServiceInfoSnapshot::of(service).await;
+        let _ = self.on_service_failed.dispatch(Arc::new(snapshot)).await;
+    }
+
+    pub async fn overall_status(&self) -> OverallStatus {
+        self.compute_overall_status(false).await
+    }
+
+    // Like overall_status, but started services also have to be ready to count as healthy
+    pub async fn overall_readiness(&self) -> OverallStatus {
+        self.compute_overall_status(true).await
+    }
+
+    pub async fn wait_for_readiness(&self, max_wait: Duration) -> OverallStatus {
+        let deadline = Instant::now() + max_wait;
+
+        loop {
+            let overall_readiness = self.overall_readiness().await;
+
+            // Once readiness caught up with the status, waiting any longer won't change anything
+            if overall_readiness == self.overall_status().await || Instant::now() >= deadline {
+                return overall_readiness;
             }
+
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    pub async fn status_snapshot(&self) -> StatusSnapshot {
+        let mut service_snapshots = Vec::new();
+
+        for service in self.indexed_services().await {
+            service_snapshots.push(service.snapshot().await);
         }
 
-        OverallStatus::Healthy
+        StatusSnapshot {
+            overall_status: self.overall_status().await,
+            services: service_snapshots,
+        }
     }
 
     //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
@@ -263,33 +1056,35 @@ impl ServiceManager {
         let mut non_failed_optionals = Vec::new();
         let mut others = Vec::new();
 
-        for service in self.services.iter() {
-            let service = service.lock().await;
-            let info = service.info();
-            let priority = &info.priority;
-            let status = info.status.get().await;
+        let metrics = self.metrics().await;
+
+        for service in self.indexed_services().await {
+            let priority = &service.priority;
+            let status = service.status.get().await;
+            let line = match metrics.get(&service.id) {
+                Some(service_metrics) => {
+                    format!(" - {}: {} ({})", service.name, status, service_metrics)
+                }
+                None => format!(" - {}: {}", service.name, status),
+            };
 
             match status {
-                Status::Started | Status::Stopped => match priority {
-                    Priority::Essential => {
-                        non_failed_essentials.push(format!(" - {}: {}", info.name, status));
-                    }
-                    Priority::Optional => {
-                        non_failed_optionals.push(format!(" - {}: {}", info.name, status));
+                Status::Started | Status::Stopped | Status::Paused | Status::Draining => {
+                    if priority.is_essential() {
+                        non_failed_essentials.push(line);
+                    } else {
+                        non_failed_optionals.push(line);
                     }
-                },
-                Status::FailedToStart(_) | Status::FailedToStop(_) | Status::RuntimeError(_) => {
-                    match priority {
-                        Priority::Essential => {
-                            failed_essentials.push(format!(" - {}: {}", info.name, status));
-                        }
-                        Priority::Optional => {
-                            failed_optionals.push(format!(" - {}: {}", info.name, status));
-                        }
+                }
+                status if status.is_failure() => {
+                    if priority.is_essential() {
+                        failed_essentials.push(line);
+                    } else {
+                        failed_optionals.push(line);
                     }
                 }
                 _ => {
-                    others.push(format!(" - {}: {}", info.name, status));
+                    others.push(line);
                 }
             }
         }
@@ -333,9 +1128,174 @@ impl ServiceManager {
         text_buffer
     }
 
+    // Within a phase, services are ordered by priority. Services with the same priority keep their registration order
+    async fn services_by_phase(&self) -> Vec<(StartupPhase, Vec<IndexedService>)> {
+        let mut ordered_services = self.indexed_services().await;
+        ordered_services.sort_by_key(|service| (service.phase, service.priority));
+
+        let mut phases: Vec<(StartupPhase, Vec<_>)> = Vec::new();
+        for service in ordered_services {
+            match phases.last_mut() {
+                Some((last_phase, services)) if *last_phase == service.phase => {
+                    services.push(service)
+                }
+                _ => phases.push((service.phase, vec![service])),
+            }
+        }
+
+        phases
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    async fn compute_overall_status(&self, require_readiness: bool) -> OverallStatus {
+        let mut overall_status = OverallStatus::Healthy;
+
+        for service in self.indexed_services().await {
+            let priority = service.priority;
+            let status = service.status.get().await;
+
+            // Optional services that are not running only degrade the bot if they actually failed
+            if !priority.is_essential() {
+                if status.is_failure() || matches!(status, Status::Degraded(_)) {
+                    overall_status = OverallStatus::Degraded;
+                }
+
+                continue;
+            }
+
+            let is_up = match status {
+                Status::Degraded(_) => {
+                    overall_status = OverallStatus::Degraded;
+                    true
+                }
+                Status::Started => {
+                    !require_readiness || service.readiness.get().await == Readiness::Ready
+                }
+                Status::Paused | Status::Draining => true,
+                Status::Stopped => service.lazy,
+                _ => false,
+            };
+
+            if !is_up {
+                return OverallStatus::Unhealthy;
+            }
+        }
+
+        overall_status
+    }
+
+    // Clones the index entries, so callers can await on them without holding the lock of the index
+    async fn indexed_services(&self) -> Vec<IndexedService> {
+        self.services.read().await.iter().cloned().collect()
+    }
+
+    async fn get_indexed_service(&self, service_id: &str) -> Option<IndexedService> {
+        self.services.read().await.get(service_id).cloned()
+    }
+
+    async fn get_indexed_service_of<T>(&self) -> Option<IndexedService>
+    where
+        T: Service,
+    {
+        let services = self.services.read().await;
+        let service = services.iter().find(|service| service.handle.is::<T>());
+        service.cloned()
+    }
+
+    async fn start_if_lazy(&self, service: &IndexedService) {
+        if !service.lazy {
+            return;
+        }
+
+        let status = service.status.get().await;
+        if status != Status::Stopped {
+            return;
+        }
+
+        info!("Lazy service {} was requested, starting it", service.name);
+
+        if let Err(error) = self.start_service(Arc::clone(&service.handle)).await {
+            error!("Failed to start lazy service: {}", error);
+        }
+    }
+
+    //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+    async fn resolve_dependencies(
+        &self,
+        service_id: &str,
+        dependencies: &[ServiceDependency],
+        resolving: &[String],
+    ) -> Result<HashMap<String, ServiceHandle>, StartupError> {
+        let mut resolved_dependencies = HashMap::new();
+
+        for dependency in dependencies.iter() {
+            let services = self.services.read().await;
+            let dependency_service = services
+                .iter()
+                .find(|registered_service| {
+                    dependency.is_satisfied_by(&registered_service.handle, &registered_service.id)
+                })
+                .cloned();
+            drop(services);
+
+            let dependency_service = match dependency_service {
+                Some(dependency_service) => dependency_service,
+                None => {
+                    return Err(StartupError::MissingDependency(
+                        service_id.to_string(),
+                        dependency.to_string(),
+                    ))
+                }
+            };
+
+            // Like a -> b -> a, starting from where the cycle begins
+            if let Some(position) = resolving
+                .iter()
+                .position(|resolving_id| *resolving_id == dependency_service.id)
+            {
+                let mut cycle = resolving[position..].to_vec();
+                cycle.push(dependency_service.id.clone());
+
+                return Err(StartupError::DependencyCycle(
+                    service_id.to_string(),
+                    cycle.join(" -> "),
+                ));
+            }
+
+            // Dependencies that were not started yet (e.g. lazy ones) are started on demand
+            let status = dependency_service.status.get().await;
+            if status == Status::Stopped {
+                let start_result = Box::pin(
+                    self.start_service_for(Arc::clone(&dependency_service.handle), resolving),
+                )
+                .await;
+
+                if let Err(StartupError::DependencyCycle(_, cycle)) = start_result {
+                    return Err(StartupError::DependencyCycle(service_id.to_string(), cycle));
+                }
+            }
+
+            let status = dependency_service.status.get().await;
+            if !matches!(
+                status,
+                Status::Started | Status::Paused | Status::Degraded(_)
+            ) {
+                return Err(StartupError::DependencyNotStarted(
+                    service_id.to_string(),
+                    dependency.to_string(),
+                ));
+            }
+
+            resolved_dependencies.insert(dependency_service.id, dependency_service.handle);
+        }
+
+        Ok(resolved_dependencies)
+    }
+
     async fn init_service(
         &self,
         service: &mut MutexGuard<'_, dyn Service>,
+        dependencies: HashMap<String, ServiceHandle>,
     ) -> Result<(), StartupError> {
         let weak = match self.weak.get() {
             Some(weak) => weak,
@@ -358,19 +1318,30 @@ impl ServiceManager {
         };
 
         //TODO: Add to config instead of hardcoding duration
-        let start = service.start(arc);
+        let start_time = Instant::now();
+        let start = service.start(ServiceContext::new(arc, dependencies));
         let timeout_result = timeout(Duration::from_secs(10), start).await;
 
         match timeout_result {
             Ok(start_result) => match start_result {
                 Ok(()) => {
+                    let mut metrics = self.metrics.lock().await;
+                    let service_metrics = metrics.entry(service.info().id.clone()).or_default();
+                    service_metrics.start_count += 1;
+                    service_metrics.started_at = Some(Instant::now());
+                    service_metrics.last_start_duration = Some(start_time.elapsed());
+                    drop(metrics);
+
                     service.info().status.set(Status::Started).await;
+                    if !service.info().manual_readiness {
+                        service.info().readiness.set(Readiness::Ready).await;
+                    }
+
+                    let snapshot = ServiceInfoSnapshot::of(&**service).await;
+                    let _ = self.on_service_started.dispatch(Arc::new(snapshot)).await;
                 }
                 Err(error) => {
-                    service
-                        .info()
-                        .status
-                        .set(Status::FailedToStart(error.to_string()))
+                    self.fail_service(&**service, Status::FailedToStart(error.to_string()))
                         .await;
                     return Err(StartupError::FailedToStartService(
                         service.info().id.clone(),
@@ -378,10 +1349,7 @@ impl ServiceManager {
                 }
             },
             Err(error) => {
-                service
-                    .info()
-                    .status
-                    .set(Status::FailedToStart(error.to_string()))
+                self.fail_service(&**service, Status::FailedToStart(error.to_string()))
                     .await;
                 return Err(StartupError::FailedToStartService(
                     service.info().id.clone(),
@@ -396,6 +1364,11 @@ impl ServiceManager {
         &self,
         service: &mut MutexGuard<'_, dyn Service>,
     ) -> Result<(), ShutdownError> {
+        // Commands of a stopping service would work on state that is about to go away
+        self.command_registry
+            .unregister_owner(&service.info().id)
+            .await;
+
         //TODO: Add to config instead of hardcoding duration
         let stop = service.stop();
         let timeout_result = timeout(Duration::from_secs(10), stop).await;
@@ -403,13 +1376,20 @@ impl ServiceManager {
         match timeout_result {
             Ok(stop_result) => match stop_result {
                 Ok(()) => {
+                    if let Some(service_metrics) =
+                        self.metrics.lock().await.get_mut(&service.info().id)
+                    {
+                        service_metrics.started_at = None;
+                    }
+
                     service.info().status.set(Status::Stopped).await;
+                    service.info().readiness.set(Readiness::NotReady).await;
+
+                    let snapshot = ServiceInfoSnapshot::of(&**service).await;
+                    let _ = self.on_service_stopped.dispatch(Arc::new(snapshot)).await;
                 }
                 Err(error) => {
-                    service
-                        .info()
-                        .status
-                        .set(Status::FailedToStop(error.to_string()))
+                    self.fail_service(&**service, Status::FailedToStop(error.to_string()))
                         .await;
                     return Err(ShutdownError::FailedToStopService(
                         service.info().id.clone(),
@@ -417,10 +1397,7 @@ impl ServiceManager {
                 }
             },
             Err(error) => {
-                service
-                    .info()
-                    .status
-                    .set(Status::FailedToStop(error.to_string()))
+                self.fail_service(&**service, Status::FailedToStop(error.to_string()))
                     .await;
                 return Err(ShutdownError::FailedToStopService(
                     service.info().id.clone(),
@@ -448,67 +1425,162 @@ impl ServiceManager {
             return;
         }
 
-        let task = service_lock.task();
+        let cancellation_token = CancellationToken::new();
+        let task = service_lock.task(cancellation_token.clone());
         if let Some(task) = task {
             let mut taskchain = Taskchain::new(task);
 
+            let weak = self.weak.get().cloned();
+            let watchdog_cancellation_token = cancellation_token.clone();
             taskchain.append(|result| async move {
-                let service = service.lock().await;
-
-                match result {
-                    Ok(()) => {
-                        error!(
-                            "Background task of service {} ended unexpectedly! Service will be marked as failed.",
-                            service.info().name
-                        );
-
-                        service
-                            .info()
-                            .status
-                            .set(Status::RuntimeError("Background task ended unexpectedly!".to_string()))
-                            .await;
+                let cancellation_token = watchdog_cancellation_token;
+                let mut result = result;
+                let mut restarts = 0;
+
+                loop {
+                    // The task was asked to stop, so it ending is expected. The service is locked by whoever is stopping it.
+                    if cancellation_token.is_cancelled() {
+                        return result;
+                    }
+
+                    let service_lock = service.lock().await;
+
+                    let reason = match &result {
+                        Ok(()) => "ended unexpectedly".to_string(),
+                        Err(error) => format!("ended with error: {}", error),
+                    };
+
+                    let restart_policy = service_lock.info().task_restart_policy;
+                    if let Some(restart_policy) = restart_policy {
+                        if restarts < restart_policy.max_restarts {
+                            restarts += 1;
+                            warn!(
+                                "Background task of service {} {}. Restarting it in {} ({}/{}).",
+                                service_lock.info().name,
+                                reason,
+                                humantime::format_duration(restart_policy.delay),
+                                restarts,
+                                restart_policy.max_restarts
+                            );
+                            drop(service_lock);
+
+                            select! {
+                                _ = sleep(restart_policy.delay) => {},
+                                _ = cancellation_token.cancelled() => return result,
+                            }
+
+                            let service_lock = service.lock().await;
+                            if let Some(service_manager) =
+                                weak.as_ref().and_then(|weak| weak.upgrade())
+                            {
+                                service_manager
+                                    .record_task_restart(&service_lock.info().id)
+                                    .await;
+                            }
+
+                            if let Some(task) = service_lock.task(cancellation_token.clone()) {
+                                drop(service_lock);
+                                result = task.await;
+                                continue;
+                            }
+
+                            result = Err("Service did not provide a new background task".into());
+                            continue;
+                        }
                     }
 
-                    Err(error) => {
-                        error!(
-                            "Background task of service {} ended with error: {}. Service will be marked as failed.",
-                            service.info().name,
+                    error!(
+                        "Background task of service {} {}! Service will be marked as failed.",
+                        service_lock.info().name,
+                        reason
+                    );
+
+                    let status = match &result {
+                        Ok(()) => {
+                            Status::RuntimeError("Background task ended unexpectedly!".to_string())
+                        }
+                        Err(error) => Status::RuntimeError(format!(
+                            "Background task ended with error: {}",
                             error
-                        );
-
-                        service
-                            .info()
-                            .status
-                            .set(Status::RuntimeError(
-                                format!("Background task ended with error: {}", error),
-                            ))
-                            .await;
+                        )),
+                    };
+
+                    match weak.and_then(|weak| weak.upgrade()) {
+                        Some(service_manager) => {
+                            service_manager.fail_service(&*service_lock, status).await
+                        }
+                        None => {
+                            service_lock.info().status.set(status).await;
+                        }
                     }
+
+                    return Ok(());
                 }
-                Ok(())
             });
 
-            let join_handle = spawn(taskchain.run());
-
-            self.background_tasks
-                .lock()
-                .await
-                .insert(service_lock.info().id.clone(), join_handle);
+            // Not a child of the span of the operation that started it, as the task outlives it
+            let span = info_span!(
+                parent: None,
+                "service",
+                service_id = %service_lock.info().id,
+                operation = "task"
+            );
+            let join_handle = spawn(taskchain.run().instrument(span));
+
+            self.background_tasks.lock().await.insert(
+                service_lock.info().id.clone(),
+                BackgroundTask {
+                    join_handle,
+                    cancellation_token,
+                    started_at: SystemTime::now(),
+                    started: Instant::now(),
+                },
+            );
         }
     }
 
-    async fn stop_background_task(&self, service_lock: &MutexGuard<'_, dyn Service>) {
-        if !self
-            .has_background_task_registered(&service_lock.info().id)
+    async fn stop_background_task(
+        &self,
+        service_lock: &MutexGuard<'_, dyn Service>,
+    ) -> Result<(), ShutdownError> {
+        let task = self
+            .background_tasks
+            .lock()
             .await
-        {
-            return;
+            .remove(&service_lock.info().id);
+
+        let mut task = match task {
+            Some(task) => task,
+            None => return Ok(()),
+        };
+
+        task.cancellation_token.cancel();
+
+        let grace_period_result = timeout(self.task_grace_period, &mut task.join_handle).await;
+        if grace_period_result.is_ok() {
+            return Ok(());
+        }
+
+        warn!(
+            "Background task of service {} did not finish within {} after being cancelled. Aborting it.",
+            service_lock.info().name,
+            humantime::format_duration(self.task_grace_period)
+        );
+        task.join_handle.abort();
+
+        // Aborting only takes effect once the task yields, so make sure it is actually gone before tearing down the service
+        let abort_result = timeout(self.task_grace_period, &mut task.join_handle).await;
+        if abort_result.is_err() {
+            error!(
+                "Background task of service {} did not stop after being aborted.",
+                service_lock.info().name
+            );
+            return Err(ShutdownError::BackgroundTaskNotStopped(
+                service_lock.info().id.clone(),
+            ));
         }
 
-        let mut tasks_lock = self.background_tasks.lock().await;
-        let task = tasks_lock.get(&service_lock.info().id).unwrap();
-        task.abort();
-        tasks_lock.remove(&service_lock.info().id);
+        Ok(())
     }
 }
 
@@ -516,15 +1588,19 @@ impl Display for ServiceManager {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Services: ")?;
 
-        if self.services.is_empty() {
+        // Formatting can't wait for the lock, as it may happen inside the runtime
+        let services = match self.services.try_read() {
+            Ok(services) => services,
+            Err(_) => return write!(f, "(busy)"),
+        };
+        if services.is_empty() {
             write!(f, "None")?;
             return Ok(());
         }
 
-        let mut services = self.services.iter().peekable();
+        let mut services = services.iter().peekable();
         while let Some(service) = services.next() {
-            let service = service.blocking_lock();
-            write!(f, "{} ({})", service.info().name, service.info().id)?;
+            write!(f, "{} ({})", service.name, service.id)?;
             if services.peek().is_some() {
                 write!(f, ", ")?;
             }
@@ -532,3 +1608,54 @@ impl Display for ServiceManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use serenity::async_trait;
+
+    use super::ServiceManager;
+    use crate::{
+        log::test_setup,
+        service::{BoxedError, Priority, Service, ServiceContext, ServiceHandle, ServiceInfo},
+    };
+
+    struct FailingService {
+        info: ServiceInfo,
+    }
+
+    //TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+    #[async_trait]
+    impl Service for FailingService {
+        fn info(&self) -> &ServiceInfo {
+            &self.info
+        }
+
+        async fn start(&mut self, _context: ServiceContext) -> Result<(), BoxedError> {
+            Err("Unable to connect".into())
+        }
+
+        async fn stop(&mut self) -> Result<(), BoxedError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_failed_essential_service() {
+        let log = test_setup();
+        let service_manager = ServiceManager::builder().build().await;
+        let service = FailingService {
+            info: ServiceInfo::new("failing", "Failing", Priority::Critical),
+        };
+        service_manager
+            .add_service(ServiceHandle::new(service), false)
+            .await
+            .unwrap();
+
+        let results = service_manager.start_services().await;
+
+        assert!(results.iter().all(|result| result.is_err()));
+        log.assert_logged(Level::Error, "Essential service Failing of startup phase");
+        log.assert_not_logged(Level::Info, "Started service Failing");
+    }
+}
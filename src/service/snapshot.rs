@@ -0,0 +1,47 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::{OverallStatus, Priority, Readiness, Service, Status};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceInfoSnapshot {
+    pub id: String,
+    pub name: String,
+    pub priority: Priority,
+    pub groups: Vec<String>,
+    pub status: Status,
+    pub readiness: Readiness,
+    pub since: SystemTime,
+}
+
+impl ServiceInfoSnapshot {
+    pub async fn of(service: &dyn Service) -> Self {
+        let info = service.info();
+
+        Self {
+            id: info.id.clone(),
+            name: info.name.clone(),
+            priority: info.priority,
+            groups: info.groups.clone(),
+            status: info.status.get().await,
+            readiness: info.readiness.get().await,
+            since: info.status.last_changed().await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackgroundTaskSnapshot {
+    pub service_id: String,
+    pub is_finished: bool,
+    pub started_at: SystemTime,
+    pub running_for: Duration,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub overall_status: OverallStatus,
+    pub services: Vec<ServiceInfoSnapshot>,
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use log::{error, info, warn};
+use tokio::{select, spawn, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    BoxedError, LifetimedPinnedBoxedFutureResult, OverallStatus, Service, ServiceContext,
+    ServiceInfo, ServiceManager, TaskRestartPolicy,
+};
+
+// Bundles the services of a child ServiceManager into a single service, so the parent only sees their aggregated status
+pub struct Supervisor {
+    info: ServiceInfo,
+    service_manager: Arc<ServiceManager>,
+    restart_policy: Option<TaskRestartPolicy>,
+}
+
+impl Supervisor {
+    pub fn new(info: ServiceInfo, service_manager: Arc<ServiceManager>) -> Self {
+        Self {
+            info,
+            service_manager,
+            restart_policy: None,
+        }
+    }
+
+    // Lets the supervisor restart failed child services before its own status is affected
+    pub fn with_restart_policy(mut self, max_restarts: u32, delay: Duration) -> Self {
+        self.restart_policy = Some(TaskRestartPolicy {
+            max_restarts,
+            delay,
+        });
+        self
+    }
+
+    pub fn service_manager(&self) -> &Arc<ServiceManager> {
+        &self.service_manager
+    }
+
+    pub async fn overall_status(&self) -> OverallStatus {
+        self.service_manager.overall_status().await
+    }
+}
+
+#[async_trait]
+impl Service for Supervisor {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, _context: ServiceContext) -> Result<(), BoxedError> {
+        self.service_manager.start_services().await;
+
+        if self.service_manager.overall_status().await == OverallStatus::Unhealthy {
+            let status_overview = self.service_manager.status_overview().await;
+            return Err(format!(
+                "Essential services of supervisor {} failed to start:\n{}",
+                self.info.name, status_overview
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        let errors: Vec<String> = self
+            .service_manager
+            .stop_services()
+            .await
+            .into_iter()
+            .filter_map(|result| result.err())
+            .map(|error| error.to_string())
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(errors.join(", ").into());
+        }
+
+        Ok(())
+    }
+
+    async fn drain(&mut self) -> Result<(), BoxedError> {
+        let errors: Vec<String> = self
+            .service_manager
+            .drain()
+            .await
+            .into_iter()
+            .filter_map(|result| result.err())
+            .map(|error| error.to_string())
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(errors.join(", ").into());
+        }
+
+        Ok(())
+    }
+
+    // Watches the child services and restarts them according to the restart policy. Ends with an error once the children are unhealthy.
+    fn task<'a>(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let service_manager = Arc::clone(&self.service_manager);
+        let restart_policy = self.restart_policy;
+        let name = self.info.name.clone();
+
+        Some(Box::pin(async move {
+            let (subscription, mut receiver) = service_manager
+                .on_service_failed
+                .subscribe_channel(format!("supervisor_{}", name), 16, false, true)
+                .await;
+
+            let mut restarts: HashMap<String, u32> = HashMap::new();
+
+            let result = loop {
+                let failed_service = select! {
+                    _ = cancellation_token.cancelled() => break Ok(()),
+                    failed_service = receiver.recv() => failed_service,
+                };
+
+                let failed_service = match failed_service {
+                    Some(failed_service) => failed_service,
+                    None => break Err("Supervisor stopped receiving failures".into()),
+                };
+
+                if let Some(restart_policy) = restart_policy {
+                    let service_restarts = restarts.entry(failed_service.id.clone()).or_insert(0);
+
+                    if *service_restarts < restart_policy.max_restarts {
+                        *service_restarts += 1;
+                        warn!(
+                            "Service {} of supervisor {} failed. Restarting it in {} ({}/{}).",
+                            failed_service.name,
+                            name,
+                            humantime::format_duration(restart_policy.delay),
+                            service_restarts,
+                            restart_policy.max_restarts
+                        );
+
+                        select! {
+                            _ = sleep(restart_policy.delay) => {},
+                            _ = cancellation_token.cancelled() => break Ok(()),
+                        }
+
+                        // Restarting is spawned because the futures of services are not Sync, but the task has to be
+                        let restart_service_manager = Arc::clone(&service_manager);
+                        let service_id = failed_service.id.clone();
+                        let restart = spawn(async move {
+                            restart_service_manager
+                                .restart_service(&service_id)
+                                .await
+                                .map_err(|error| error.to_string())
+                        });
+
+                        match restart.await {
+                            Ok(Ok(())) => info!(
+                                "Supervisor {} restarted service {}",
+                                name, failed_service.name
+                            ),
+                            Ok(Err(error)) => error!(
+                                "Supervisor {} failed to restart service {}: {}",
+                                name, failed_service.name, error
+                            ),
+                            Err(error) => error!(
+                                "Supervisor {} failed to restart service {}: {}",
+                                name, failed_service.name, error
+                            ),
+                        }
+                    }
+                }
+
+                if service_manager.overall_status().await == OverallStatus::Unhealthy {
+                    break Err(format!(
+                        "Essential services of supervisor {} failed:\n{}",
+                        name,
+                        service_manager.status_overview().await
+                    )
+                    .into());
+                }
+            };
+
+            service_manager
+                .on_service_failed
+                .unsubscribe(&subscription)
+                .await;
+
+            result
+        }))
+    }
+
+    fn health_check<'a>(&self) -> Option<LifetimedPinnedBoxedFutureResult<'a, ()>> {
+        let service_manager = Arc::clone(&self.service_manager);
+
+        Some(Box::pin(async move {
+            match service_manager.overall_status().await {
+                OverallStatus::Unhealthy => Err("Essential child services are not running".into()),
+                _ => Ok(()),
+            }
+        }))
+    }
+}
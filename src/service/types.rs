@@ -3,8 +3,10 @@ use std::{
     fmt::{self, Display},
     future::Future,
     pin::Pin,
+    time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::event::event_repeater::{AttachError, DetachError};
@@ -18,15 +20,40 @@ pub type LifetimedPinnedBoxedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Se
 pub type LifetimedPinnedBoxedFutureResult<'a, T> =
     LifetimedPinnedBoxedFuture<'a, Result<T, BoxedError>>;
 
-#[derive(Debug, Clone)]
+// The reasons are compared as well, so a service that is degraded for another reason notifies about the new one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
     Started,
     Stopped,
     Starting,
     Stopping,
+    Paused,
+    Draining,
+    Degraded(String),
     FailedToStart(String),
     FailedToStop(String),
     RuntimeError(String),
+    Disabled(String),
+}
+
+impl Status {
+    // The service is up, even if it may currently not be doing its work
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Status::Started | Status::Paused | Status::Draining | Status::Degraded(_)
+        )
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Status::FailedToStart(_)
+                | Status::FailedToStop(_)
+                | Status::RuntimeError(_)
+                | Status::Disabled(_)
+        )
+    }
 }
 
 impl Display for Status {
@@ -36,33 +63,36 @@ impl Display for Status {
             Status::Stopped => write!(f, "Stopped"),
             Status::Starting => write!(f, "Starting"),
             Status::Stopping => write!(f, "Stopping"),
+            Status::Paused => write!(f, "Paused"),
+            Status::Draining => write!(f, "Draining"),
+            Status::Degraded(reason) => write!(f, "Degraded: {}", reason),
             Status::FailedToStart(error) => write!(f, "Failed to start: {}", error),
             Status::FailedToStop(error) => write!(f, "Failed to stop: {}", error),
             Status::RuntimeError(error) => write!(f, "Runtime error: {}", error),
+            Status::Disabled(reason) => write!(f, "Disabled: {}", reason),
         }
     }
 }
 
-impl PartialEq for Status {
-    fn eq(&self, other: &Self) -> bool {
-        matches!(
-            (self, other),
-            (Status::Started, Status::Started)
-                | (Status::Stopped, Status::Stopped)
-                | (Status::Starting, Status::Starting)
-                | (Status::Stopping, Status::Stopping)
-                | (Status::FailedToStart(_), Status::FailedToStart(_))
-                | (Status::FailedToStop(_), Status::FailedToStop(_))
-                | (Status::RuntimeError(_), Status::RuntimeError(_))
-        )
-    }
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Readiness {
+    NotReady,
+    Ready,
 }
 
-impl Eq for Status {}
+impl Display for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Readiness::NotReady => write!(f, "Not ready"),
+            Readiness::Ready => write!(f, "Ready"),
+        }
+    }
+}
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum OverallStatus {
     Healthy,
+    Degraded,
     Unhealthy,
 }
 
@@ -70,26 +100,61 @@ impl Display for OverallStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             OverallStatus::Healthy => write!(f, "Healthy"),
+            OverallStatus::Degraded => write!(f, "Degraded"),
             OverallStatus::Unhealthy => write!(f, "Unhealthy"),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Priority {
-    Essential,
-    Optional,
+    Critical,
+    High,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    // A failure of an essential service makes the whole bot unhealthy, other failures only degrade it
+    pub fn is_essential(&self) -> bool {
+        matches!(self, Priority::Critical | Priority::High)
+    }
 }
 
 impl Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Priority::Essential => write!(f, "Essential"),
-            Priority::Optional => write!(f, "Optional"),
+            Priority::Critical => write!(f, "Critical"),
+            Priority::High => write!(f, "High"),
+            Priority::Normal => write!(f, "Normal"),
+            Priority::Low => write!(f, "Low"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum StartupPhase {
+    Infrastructure,
+    Core,
+    Modules,
+}
+
+impl Display for StartupPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartupPhase::Infrastructure => write!(f, "Infrastructure"),
+            StartupPhase::Core => write!(f, "Core"),
+            StartupPhase::Modules => write!(f, "Modules"),
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TaskRestartPolicy {
+    pub max_restarts: u32,
+    pub delay: Duration,
+}
+
 #[derive(Debug, Error)]
 pub enum StartupError {
     #[error("Service {0} is not managed by this Service Manager")]
@@ -98,6 +163,9 @@ pub enum StartupError {
     #[error("Service {0} is not stopped")]
     ServiceNotStopped(String),
 
+    #[error("Service {0} is disabled by its circuit breaker")]
+    ServiceDisabled(String),
+
     #[error("Service {0} already has a background task running")]
     BackgroundTaskAlreadyRunning(String),
 
@@ -108,6 +176,18 @@ pub enum StartupError {
 
     #[error("Service {0} failed to start")]
     FailedToStartService(String),
+
+    #[error("Service {0} depends on {1}, which is not managed by this Service Manager")]
+    MissingDependency(String, String),
+
+    #[error("Service {0} depends on {1}, which could not be started")]
+    DependencyNotStarted(String, String),
+
+    #[error("Service {0} can not be started because of the dependency cycle {1}")]
+    DependencyCycle(String, String),
+
+    #[error("Service {0} was not started because an essential service of startup phase {1} failed to start")]
+    PreviousPhaseFailed(String, StartupPhase),
 }
 
 #[derive(Debug, Error)]
@@ -121,8 +201,125 @@ pub enum ShutdownError {
     #[error("Service {0} failed to stop")]
     FailedToStopService(String),
 
+    #[error("Background task of service {0} did not stop after being aborted")]
+    BackgroundTaskNotStopped(String),
+
     #[error(
         "Failed to detach Service Manager's status_change EventRepeater from {0}'s status_change Event: {1}"
     )]
     StatusDetachmentFailed(String, DetachError),
 }
+
+#[derive(Debug, Error)]
+pub enum DrainError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is not started")]
+    ServiceNotStarted(String),
+
+    #[error("Service {0} failed to drain: {1}")]
+    FailedToDrainService(String, String),
+
+    #[error("Service {0} did not finish draining within {}", humantime::format_duration(*.1))]
+    Timeout(String, Duration),
+}
+
+#[derive(Debug, Error)]
+pub enum PauseError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is not started")]
+    ServiceNotStarted(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ResumeError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is not paused")]
+    ServiceNotPaused(String),
+}
+
+#[derive(Debug, Error)]
+pub enum CircuitBreakerResetError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is not disabled")]
+    ServiceNotDisabled(String),
+}
+
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("Service ID \"{0}\" is invalid. IDs must not be empty and may only contain lowercase letters, digits and underscores")]
+    InvalidServiceId(String),
+}
+
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    #[error("Service ID \"{0}\" is invalid. IDs must not be empty and may only contain lowercase letters, digits and underscores")]
+    InvalidServiceId(String),
+
+    #[error("Service {0} is already managed by this Service Manager")]
+    ServiceAlreadyManaged(String),
+
+    #[error("Service was registered but failed to start: {0}")]
+    Startup(#[from] StartupError),
+}
+
+#[derive(Debug, Error)]
+pub enum RemovalError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is essential and can not be removed")]
+    ServiceEssential(String),
+
+    #[error("Service was not removed because it failed to stop: {0}")]
+    Shutdown(#[from] ShutdownError),
+}
+
+#[derive(Debug, Error)]
+pub enum ReplacementError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} can not be replaced by service {1}, as their IDs differ")]
+    ServiceIdMismatch(String, String),
+
+    #[error("Service was not replaced because the old instance failed to stop: {0}")]
+    Shutdown(#[from] ShutdownError),
+
+    #[error("Service was replaced, but the new instance failed to start: {0}")]
+    Startup(#[from] StartupError),
+}
+
+#[derive(Debug, Error)]
+pub enum RestartError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} is disabled by its circuit breaker")]
+    ServiceDisabled(String),
+
+    #[error("Service was not restarted because it failed to stop: {0}")]
+    Shutdown(#[from] ShutdownError),
+
+    #[error("Service failed to start again: {0}")]
+    Startup(#[from] StartupError),
+}
+
+#[derive(Debug, Error)]
+pub enum WaitError {
+    #[error("Service {0} is not managed by this Service Manager")]
+    ServiceNotManaged(String),
+
+    #[error("Service {0} did not reach status {1} in time")]
+    Timeout(String, Status),
+
+    #[error("Status event of service {0} closed while waiting")]
+    EventClosed(String),
+}
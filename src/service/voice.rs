@@ -0,0 +1,165 @@
+use std::{path::PathBuf, sync::Arc};
+
+use log::{info, warn};
+use serenity::{
+    all::{ChannelId, GuildId},
+    async_trait,
+};
+use songbird::{
+    error::JoinError,
+    input::{File, Input},
+    tracks::TrackHandle,
+    Songbird,
+};
+use thiserror::Error;
+
+use super::{discord::DiscordService, BoxedError, Priority, Service, ServiceContext, ServiceInfo};
+
+#[derive(Debug, Error)]
+pub enum VoiceError {
+    #[error("Voice service has not been started")]
+    NotStarted,
+
+    #[error("Not connected to a voice channel in guild {0}")]
+    NotConnected(GuildId),
+
+    #[error("Unable to join or leave voice channel: {0}")]
+    Join(#[from] Box<JoinError>),
+}
+
+// Shares the songbird instance of the DiscordService, so voice connections use its gateway
+pub struct VoiceService {
+    info: ServiceInfo,
+    songbird: Option<Arc<Songbird>>,
+}
+
+impl VoiceService {
+    pub fn new() -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_voice", "Voice", Priority::Normal)
+                .with_group("discord")
+                .with_dependency::<DiscordService>(),
+            songbird: None,
+        }
+    }
+
+    // Joins voice channels as the given bot account instead of the default one
+    pub fn with_discord_instance(mut self, instance: &str) -> Self {
+        let id = format!("{}_{}", self.info.id, instance);
+        self.info = self
+            .info
+            .with_id(&id)
+            .with_instance_dependency::<DiscordService>(&DiscordService::instance_id(instance));
+        self
+    }
+
+    fn songbird(&self) -> Result<&Arc<Songbird>, VoiceError> {
+        self.songbird.as_ref().ok_or(VoiceError::NotStarted)
+    }
+
+    pub fn is_connected(&self, guild_id: GuildId) -> bool {
+        self.songbird
+            .as_ref()
+            .is_some_and(|songbird| songbird.get(guild_id).is_some())
+    }
+
+    pub async fn join(&self, guild_id: GuildId, channel_id: ChannelId) -> Result<(), VoiceError> {
+        self.songbird()?
+            .join(guild_id, channel_id)
+            .await
+            .map_err(Box::new)?;
+
+        Ok(())
+    }
+
+    pub async fn leave(&self, guild_id: GuildId) -> Result<(), VoiceError> {
+        let songbird = self.songbird()?;
+        if songbird.get(guild_id).is_none() {
+            return Err(VoiceError::NotConnected(guild_id));
+        }
+
+        songbird.remove(guild_id).await.map_err(Box::new)?;
+
+        Ok(())
+    }
+
+    // Plays the input alongside anything that is already playing in the guild
+    pub async fn play(
+        &self,
+        guild_id: GuildId,
+        input: impl Into<Input>,
+    ) -> Result<TrackHandle, VoiceError> {
+        let call = self
+            .songbird()?
+            .get(guild_id)
+            .ok_or(VoiceError::NotConnected(guild_id))?;
+
+        let track_handle = call.lock().await.play_input(input.into());
+
+        Ok(track_handle)
+    }
+
+    pub async fn play_file(
+        &self,
+        guild_id: GuildId,
+        path: PathBuf,
+    ) -> Result<TrackHandle, VoiceError> {
+        self.play(guild_id, File::new(path)).await
+    }
+
+    pub async fn stop(&self, guild_id: GuildId) -> Result<(), VoiceError> {
+        let call = self
+            .songbird()?
+            .get(guild_id)
+            .ok_or(VoiceError::NotConnected(guild_id))?;
+
+        call.lock().await.stop();
+
+        Ok(())
+    }
+}
+
+impl Default for VoiceService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for VoiceService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, context: ServiceContext) -> Result<(), BoxedError> {
+        let discord_service = context.dependency::<DiscordService>()?;
+        let songbird = Arc::clone(&discord_service.lock().await.songbird);
+        self.songbird = Some(songbird);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        let songbird = match self.songbird.take() {
+            Some(songbird) => songbird,
+            None => return Ok(()),
+        };
+
+        let guild_ids: Vec<_> = songbird.iter().map(|(guild_id, _)| guild_id).collect();
+
+        //TODO: When Rust allows async closures, refactor this to use iterator methods instead of for loop
+        for guild_id in guild_ids {
+            if let Err(error) = songbird.remove(guild_id).await {
+                warn!(
+                    "Unable to leave voice channel in guild {}: {}",
+                    guild_id, error
+                );
+            }
+        }
+
+        info!("Left all voice channels");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use log::warn;
+use serenity::{
+    all::{ExecuteWebhook, StatusCode, WebhookId},
+    async_trait,
+    builder::Builder,
+    http::{Http, HttpError},
+    Error,
+};
+use thiserror::Error;
+use tokio::time::sleep;
+
+use super::{BoxedError, Priority, Service, ServiceContext, ServiceInfo};
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Webhook service has not been started")]
+    NotStarted,
+
+    #[error("Unknown webhook {0}")]
+    UnknownWebhook(String),
+
+    #[error("Invalid URL for webhook {0}")]
+    InvalidUrl(String),
+
+    #[error("Unable to send to webhook {0}: {1}")]
+    SendFailed(String, Error),
+}
+
+pub(crate) struct Webhook {
+    id: WebhookId,
+    token: String,
+}
+
+// Expects URLs like https://discord.com/api/webhooks/<id>/<token>
+pub(crate) fn parse_webhook_url(url: &str) -> Option<Webhook> {
+    let (_, path) = url.split_once("/api/webhooks/")?;
+    let path = path.split(['?', '#']).next()?;
+    let (id, token) = path.split_once('/')?;
+    let token = token.trim_end_matches('/');
+
+    let id = id.parse::<u64>().ok().filter(|id| *id != 0)?;
+    if token.is_empty() || token.contains('/') {
+        return None;
+    }
+
+    Some(Webhook {
+        id: WebhookId::new(id),
+        token: token.to_string(),
+    })
+}
+
+// Client errors other than rate limits will not go away by retrying
+fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            !response.status_code.is_client_error()
+                || response.status_code == StatusCode::TOO_MANY_REQUESTS
+        }
+        Error::Http(HttpError::Request(_)) => true,
+        _ => false,
+    }
+}
+
+// Executing webhooks does not need the bot token, so this works even when the Discord client is not connected
+pub struct WebhookService {
+    info: ServiceInfo,
+    urls: HashMap<String, String>,
+    max_retries: u32,
+    retry_delay: Duration,
+    http: Arc<Http>,
+    webhooks: Option<HashMap<String, Webhook>>,
+}
+
+impl WebhookService {
+    pub fn new(urls: HashMap<String, String>) -> Self {
+        Self {
+            info: ServiceInfo::new("lum_builtin_webhooks", "Webhooks", Priority::Low),
+            urls,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(2),
+            http: Arc::new(Http::new("")),
+            webhooks: None,
+        }
+    }
+
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    pub fn webhook_names(&self) -> Vec<&str> {
+        self.urls.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub async fn send(&self, name: &str, payload: ExecuteWebhook) -> Result<(), WebhookError> {
+        let webhooks = self.webhooks.as_ref().ok_or(WebhookError::NotStarted)?;
+        let webhook = webhooks
+            .get(name)
+            .ok_or_else(|| WebhookError::UnknownWebhook(name.to_string()))?;
+
+        let mut attempt = 0;
+        loop {
+            let result = payload
+                .clone()
+                .execute(&self.http, (webhook.id, &webhook.token, false))
+                .await;
+
+            let error = match result {
+                Ok(_) => return Ok(()),
+                Err(error) => error,
+            };
+
+            if attempt >= self.max_retries || !is_retryable(&error) {
+                return Err(WebhookError::SendFailed(name.to_string(), error));
+            }
+
+            attempt += 1;
+            warn!(
+                "Unable to send to webhook {}: {}. Retrying in {} ({}/{}).",
+                name,
+                error,
+                humantime::format_duration(self.retry_delay),
+                attempt,
+                self.max_retries
+            );
+            sleep(self.retry_delay).await;
+        }
+    }
+}
+
+//TODO: When Rust allows async trait methods to be object-safe, refactor this to not use async_trait anymore
+#[async_trait]
+impl Service for WebhookService {
+    fn info(&self) -> &ServiceInfo {
+        &self.info
+    }
+
+    async fn start(&mut self, _context: ServiceContext) -> Result<(), BoxedError> {
+        let mut webhooks = HashMap::new();
+
+        for (name, url) in self.urls.iter() {
+            let webhook =
+                parse_webhook_url(url).ok_or_else(|| WebhookError::InvalidUrl(name.clone()))?;
+            webhooks.insert(name.clone(), webhook);
+        }
+
+        self.webhooks = Some(webhooks);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), BoxedError> {
+        self.webhooks = None;
+
+        Ok(())
+    }
+}